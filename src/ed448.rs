@@ -0,0 +1,270 @@
+//! Ed448 public key derivation (RFC 8032 5.2.5).
+//!
+//! None of the crypto backends expose Ed448, PKCS#8 v1 keys only carry the seed and the public
+//! key has to be derived here to match it against a certificate. This is only used for key
+//! matching, the arithmetic is not constant time and must not be used for signing.
+
+use rsa::BigUint;
+
+use crate::generic_private_key::ED448_SEED_LENGTH as SEED_LENGTH;
+
+const SHAKE256_RATE: usize = 136;
+
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+const KECCAK_RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const KECCAK_PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in KECCAK_ROUND_CONSTANTS {
+        // θ
+        let mut columns = [0u64; 5];
+        for (x, column) in columns.iter_mut().enumerate() {
+            *column = (0..5).fold(0, |parity, y| parity ^ state[x + 5 * y]);
+        }
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+        // ρ and π
+        let mut last = state[1];
+        for (rho, pi) in KECCAK_RHO.iter().zip(KECCAK_PI) {
+            let current = state[pi];
+            state[pi] = last.rotate_left(*rho);
+            last = current;
+        }
+        // χ
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&state[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // ι
+        state[0] ^= round_constant;
+    }
+}
+
+fn xor_block(state: &mut [u64; 25], block: &[u8]) {
+    for (i, byte) in block.iter().enumerate() {
+        state[i / 8] ^= (*byte as u64) << (8 * (i % 8));
+    }
+}
+
+/// SHAKE256 (FIPS 202) with an output of at most one block.
+fn shake256<const N: usize>(input: &[u8]) -> [u8; N] {
+    assert!(N <= SHAKE256_RATE);
+    let mut state = [0u64; 25];
+    let mut blocks = input.chunks_exact(SHAKE256_RATE);
+    for block in &mut blocks {
+        xor_block(&mut state, block);
+        keccak_f(&mut state);
+    }
+    let mut last = [0u8; SHAKE256_RATE];
+    let remainder = blocks.remainder();
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[remainder.len()] ^= 0x1f;
+    last[SHAKE256_RATE - 1] ^= 0x80;
+    xor_block(&mut state, &last);
+    keccak_f(&mut state);
+
+    let mut output = [0u8; N];
+    for (i, byte) in output.iter_mut().enumerate() {
+        *byte = (state[i / 8] >> (8 * (i % 8))) as u8;
+    }
+    output
+}
+
+/// Arithmetic on edwards448 (x² + y² = 1 + d·x²·y²) in projective coordinates.
+struct Curve {
+    p: BigUint,
+    d: BigUint,
+}
+
+struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+impl Curve {
+    fn new() -> Self {
+        let p = (BigUint::from(1u32) << 448) - (BigUint::from(1u32) << 224) - 1u32;
+        let d = &p - 39081u32;
+        Self { p, d }
+    }
+
+    fn base_point(&self) -> Point {
+        let coordinate = |decimal: &[u8]| BigUint::parse_bytes(decimal, 10).unwrap();
+        Point {
+            x: coordinate(b"224580040295924300187604334099896036246789641632564134246125461686950415467406032909029192869357953282578032075146446173674602635247710"),
+            y: coordinate(b"298819210078481492676017930443930673437544040154080242095928241372331506189835876003536878655418784733982303233503462500531545062832660"),
+            z: BigUint::from(1u32),
+        }
+    }
+
+    fn identity(&self) -> Point {
+        Point {
+            x: BigUint::from(0u32),
+            y: BigUint::from(1u32),
+            z: BigUint::from(1u32),
+        }
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.p
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        ((a + &self.p) - b) % &self.p
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + b) % &self.p
+    }
+
+    /// RFC 8032 5.2.4, point addition.
+    fn add_points(&self, p1: &Point, p2: &Point) -> Point {
+        let a = self.mul(&p1.z, &p2.z);
+        let b = self.mul(&a, &a);
+        let c = self.mul(&p1.x, &p2.x);
+        let d = self.mul(&p1.y, &p2.y);
+        let e = self.mul(&self.mul(&self.d, &c), &d);
+        let f = self.sub(&b, &e);
+        let g = self.add(&b, &e);
+        let h = self.mul(&self.add(&p1.x, &p1.y), &self.add(&p2.x, &p2.y));
+        Point {
+            x: self.mul(&self.mul(&a, &f), &self.sub(&self.sub(&h, &c), &d)),
+            y: self.mul(&self.mul(&a, &g), &self.sub(&d, &c)),
+            z: self.mul(&f, &g),
+        }
+    }
+
+    /// RFC 8032 5.2.4, point doubling.
+    fn double(&self, p1: &Point) -> Point {
+        let sum = self.add(&p1.x, &p1.y);
+        let b = self.mul(&sum, &sum);
+        let c = self.mul(&p1.x, &p1.x);
+        let d = self.mul(&p1.y, &p1.y);
+        let e = self.add(&c, &d);
+        let h = self.mul(&p1.z, &p1.z);
+        let j = self.sub(&e, &self.add(&h, &h));
+        Point {
+            x: self.mul(&self.sub(&b, &e), &j),
+            y: self.mul(&e, &self.sub(&c, &d)),
+            z: self.mul(&e, &j),
+        }
+    }
+
+    /// Multiplies the base point by a little-endian scalar.
+    fn mul_base(&self, scalar: &[u8]) -> Point {
+        let base = self.base_point();
+        let mut result = self.identity();
+        for byte in scalar.iter().rev() {
+            for bit in (0..8).rev() {
+                result = self.double(&result);
+                if (byte >> bit) & 1 == 1 {
+                    result = self.add_points(&result, &base);
+                }
+            }
+        }
+        result
+    }
+
+    /// RFC 8032 5.2.2, the little-endian y coordinate with the low bit of x in the last byte.
+    fn encode(&self, point: &Point) -> [u8; SEED_LENGTH] {
+        let z_inverse = point.z.modpow(&(&self.p - 2u32), &self.p);
+        let x = self.mul(&point.x, &z_inverse);
+        let y = self.mul(&point.y, &z_inverse);
+        let mut encoded = [0u8; SEED_LENGTH];
+        let y = y.to_bytes_le();
+        encoded[..y.len()].copy_from_slice(&y);
+        if x.to_bytes_le()[0] & 1 == 1 {
+            encoded[SEED_LENGTH - 1] |= 0x80;
+        }
+        encoded
+    }
+}
+
+/// Derives the Ed448 public key from a 57 byte seed.
+pub(crate) fn public_key(seed: &[u8]) -> Option<[u8; SEED_LENGTH]> {
+    if seed.len() != SEED_LENGTH {
+        return None;
+    }
+    let digest: [u8; 2 * SEED_LENGTH] = shake256(seed);
+    let mut scalar = [0u8; SEED_LENGTH];
+    scalar.copy_from_slice(&digest[..SEED_LENGTH]);
+    scalar[0] &= 0xfc;
+    scalar[SEED_LENGTH - 2] |= 0x80;
+    scalar[SEED_LENGTH - 1] = 0;
+    let curve = Curve::new();
+    Some(curve.encode(&curve.mul_base(&scalar)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ed448::{public_key, shake256};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_shake256() {
+        let digest: [u8; 32] = shake256(b"");
+        assert_eq!(
+            hex(&digest),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f"
+        );
+    }
+
+    #[test]
+    fn test_public_key() {
+        // RFC 8032 7.4, the first test vector.
+        let seed = [
+            0x6c, 0x82, 0xa5, 0x62, 0xcb, 0x80, 0x8d, 0x10, 0xd6, 0x32, 0xbe, 0x89, 0xc8, 0x51,
+            0x3e, 0xbf, 0x6c, 0x92, 0x9f, 0x34, 0xdd, 0xfa, 0x8c, 0x9f, 0x63, 0xc9, 0x96, 0x0e,
+            0xf6, 0xe3, 0x48, 0xa3, 0x52, 0x8c, 0x8a, 0x3f, 0xcc, 0x2f, 0x04, 0x4e, 0x39, 0xa3,
+            0xfc, 0x5b, 0x94, 0x49, 0x2f, 0x8f, 0x03, 0x2e, 0x75, 0x49, 0xa2, 0x00, 0x98, 0xf9,
+            0x5b,
+        ];
+        assert_eq!(
+            hex(&public_key(&seed).unwrap()),
+            "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180"
+        );
+        assert!(public_key(&seed[1..]).is_none());
+    }
+}
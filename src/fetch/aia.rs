@@ -0,0 +1,296 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use std::sync::Mutex;
+
+use rustls_pki_types::CertificateDer;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_CA_ISSUERS;
+use x509_parser::prelude::FromDer;
+
+use crate::fetch::{FetchError, HttpFetcher};
+use crate::parser::pkcs7::{parse_pkcs7_certificates, Pkcs7ParseError};
+use crate::validate::validate::is_issued_by;
+use crate::ParsedPkiData;
+
+/// Upper bound on how many issuers are chased for a single certificate.
+const MAX_CHAIN_DEPTH: usize = 8;
+/// Default number of `caIssuers` URLs whose downloads are kept.
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AiaError {
+    #[error(transparent)]
+    FetchError(#[from] FetchError),
+    #[error(transparent)]
+    Pkcs7ParseError(#[from] Pkcs7ParseError),
+    #[error("InvalidIssuerCertificate")]
+    InvalidIssuerCertificate,
+}
+
+/// Returns the `caIssuers` URLs of the Authority Information Access extension.
+pub fn ca_issuers_urls(certificate: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for extension in certificate.extensions() {
+        if let ParsedExtension::AuthorityInfoAccess(aia) = extension.parsed_extension() {
+            for access_description in &aia.accessdescs {
+                if access_description.access_method != OID_PKIX_ACCESS_DESCRIPTOR_CA_ISSUERS {
+                    continue;
+                }
+                if let GeneralName::URI(uri) = access_description.access_location {
+                    urls.push(uri.to_string());
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// Issuer certificates are served as a single DER certificate, a degenerate PKCS#7 bundle (RFC 5280 4.2.2.1),
+/// and by some CAs as PEM.
+pub fn parse_issuer_response(body: &[u8]) -> Result<Vec<CertificateDer<'static>>, AiaError> {
+    if body.starts_with(b"-----BEGIN") {
+        let certificates = rustls_pemfile::certs(&mut Cursor::new(body))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AiaError::InvalidIssuerCertificate)?;
+        return Ok(certificates);
+    }
+    if X509Certificate::from_der(body).is_ok() {
+        return Ok(vec![CertificateDer::from(body.to_vec())]);
+    }
+    Ok(parse_pkcs7_certificates(body)?)
+}
+
+/// Downloaded issuers by URL, the oldest download is evicted once the capacity is reached.
+#[derive(Default)]
+struct IssuerCache {
+    entries: HashMap<String, Vec<CertificateDer<'static>>>,
+    order: VecDeque<String>,
+}
+
+impl IssuerCache {
+    fn insert(&mut self, url: String, issuers: Vec<CertificateDer<'static>>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        if self.entries.insert(url.clone(), issuers).is_none() {
+            self.order.push_back(url);
+        }
+        while self.order.len() > capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Optional step completing leaf-only bundles by downloading the missing issuers referenced
+/// through the Authority Information Access extension. Downloads are cached by URL.
+///
+/// The stores do not call it, it performs network I/O an air-gapped deployment may not allow.
+/// Opt in by running [`AiaChainCompleter::complete_chain`] on the retrieved `ParsedPkiData`
+/// before building the identities.
+pub struct AiaChainCompleter<F: HttpFetcher> {
+    fetcher: F,
+    cache: Mutex<IssuerCache>,
+    cache_capacity: usize,
+}
+
+impl<F: HttpFetcher> AiaChainCompleter<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            cache: Default::default(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Keep the downloads of at most `cache_capacity` URLs, 0 disables the cache.
+    pub fn with_cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    pub async fn fetch_issuers(&self, url: &str) -> Result<Vec<CertificateDer<'static>>, AiaError> {
+        if let Some(cached) = self.cache.lock().unwrap().entries.get(url) {
+            return Ok(cached.clone());
+        }
+        tracing::debug!("Fetching issuer certificate from {}", url);
+        let body = self.fetcher.get(url).await?;
+        let issuers = parse_issuer_response(&body)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), issuers.clone(), self.cache_capacity);
+        Ok(issuers)
+    }
+
+    /// Downloads the issuers of every certificate in `pki_data` whose issuer is not part of it,
+    /// and appends them to `pki_data.x509`, so they end up in the identity's intermediates.
+    /// Returns the number of certificates added.
+    pub async fn complete_chain(
+        &self,
+        pki_data: &mut ParsedPkiData<'_>,
+    ) -> Result<usize, AiaError> {
+        let mut added = 0;
+        let mut pending: Vec<CertificateDer<'static>> = pki_data
+            .x509
+            .iter()
+            .map(|x| CertificateDer::from(x.to_vec()))
+            .collect();
+        for _ in 0..MAX_CHAIN_DEPTH {
+            let mut discovered = Vec::new();
+            for der in &pending {
+                let Ok((_, certificate)) = X509Certificate::from_der(der) else {
+                    continue;
+                };
+                if certificate.subject() == certificate.issuer()
+                    || self.has_issuer(pki_data, &certificate)
+                {
+                    continue;
+                }
+                for url in ca_issuers_urls(&certificate) {
+                    let issuers = match self.fetch_issuers(&url).await {
+                        Ok(issuers) => issuers,
+                        Err(err) => {
+                            tracing::warn!("Failed to fetch issuer from {}: {}", url, err);
+                            continue;
+                        }
+                    };
+                    let mut found = false;
+                    for issuer in issuers {
+                        let Ok((_, parsed_issuer)) = X509Certificate::from_der(&issuer) else {
+                            continue;
+                        };
+                        if !is_issued_by(&certificate, &parsed_issuer) {
+                            continue;
+                        }
+                        found = true;
+                        if !pki_data.x509.iter().any(|x| x.as_ref() == issuer.as_ref()) {
+                            pki_data.x509.push(issuer.clone());
+                            discovered.push(issuer);
+                            added += 1;
+                        }
+                    }
+                    if found {
+                        break;
+                    }
+                }
+            }
+            if discovered.is_empty() {
+                break;
+            }
+            pending = discovered;
+        }
+        Ok(added)
+    }
+
+    fn has_issuer(&self, pki_data: &ParsedPkiData<'_>, certificate: &X509Certificate) -> bool {
+        pki_data
+            .x509
+            .iter()
+            .any(|x| match X509Certificate::from_der(x) {
+                Ok((_, candidate)) => is_issued_by(certificate, &candidate),
+                Err(_) => false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::Ordering;
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::fetch::aia::{ca_issuers_urls, AiaChainCompleter};
+    use crate::fetch::StubFetcher;
+    use crate::parser::parse::PkiParser;
+    use crate::ParsedPkiData;
+
+    #[test]
+    fn test_ca_issuers_urls() {
+        let (_, pem) =
+            parse_x509_pem(include_bytes!("../../tests/data/aia_test_cert.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        assert_eq!(
+            ca_issuers_urls(&certificate),
+            vec!["http://www.example.com/cert.pem".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_issuers_cache_is_bounded() {
+        let (_, intermediate) =
+            parse_x509_pem(include_bytes!("../../tests/data/intermediate-ca.pem")).unwrap();
+        let urls = ["http://a.example.com/ca.der", "http://b.example.com/ca.der"];
+        let fetcher =
+            StubFetcher::new(urls.map(|url| (url.to_string(), intermediate.contents.clone())));
+        let requests = fetcher.requests();
+        let completer = AiaChainCompleter::new(fetcher).with_cache_capacity(1);
+
+        for url in [urls[0], urls[0], urls[1], urls[0]] {
+            completer.fetch_issuers(url).await.unwrap();
+        }
+        // a.example.com is served from the cache once, then evicted by b.example.com.
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(completer.cache.lock().unwrap().entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_complete_chain_skips_non_issuer() {
+        let (_, intermediate) =
+            parse_x509_pem(include_bytes!("../../tests/data/intermediate-ca.pem")).unwrap();
+        let fetcher = StubFetcher::new([(
+            "http://www.example.com/cert.pem".to_string(),
+            intermediate.contents.clone(),
+        )]);
+        let completer = AiaChainCompleter::new(fetcher);
+
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/aia_test_cert.pem")),
+            )
+            .unwrap();
+
+        // The stub issuer does not sign the test certificate, so it is not added.
+        let added = completer
+            .complete_chain(&mut parsed_pki_data)
+            .await
+            .unwrap();
+        assert_eq!(added, 0);
+        assert_eq!(parsed_pki_data.x509.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_complete_chain_fetches_issuer() {
+        let (_, intermediate) =
+            parse_x509_pem(include_bytes!("../../tests/data/intermediate-ca.pem")).unwrap();
+        let fetcher = StubFetcher::new([(
+            "http://www.example.com/intermediate-ca.der".to_string(),
+            intermediate.contents.clone(),
+        )]);
+        let completer = AiaChainCompleter::new(fetcher);
+
+        let mut parsed_pki_data = ParsedPkiData::default();
+        PkiParser::new()
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/aia-leaf.pem")),
+            )
+            .unwrap();
+
+        let added = completer
+            .complete_chain(&mut parsed_pki_data)
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            parsed_pki_data.x509[1].as_ref(),
+            intermediate.contents.as_slice()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use rustls_pki_types::CertificateRevocationListDer;
+use tokio::sync::watch;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{DistributionPointName, GeneralName, ParsedExtension};
+use x509_parser::prelude::FromDer;
+use x509_parser::revocation_list::CertificateRevocationList;
+
+use crate::fetch::{
+    refresh_at, run_refresh_loop, FetchError, HttpFetcher, RefreshCache, RETRY_INTERVAL,
+};
+use crate::ParsedPkiData;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CrlFetchError {
+    #[error(transparent)]
+    FetchError(#[from] FetchError),
+    #[error("InvalidCrl")]
+    InvalidCrl,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Returns the HTTP URLs of the certificate's CRL Distribution Points.
+pub fn crl_distribution_point_urls(certificate: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for extension in certificate.extensions() {
+        if let ParsedExtension::CRLDistributionPoints(points) = extension.parsed_extension() {
+            for point in points.points.iter() {
+                let Some(DistributionPointName::FullName(names)) = &point.distribution_point else {
+                    continue;
+                };
+                for name in names {
+                    if let GeneralName::URI(uri) = name {
+                        if uri.starts_with("http://") {
+                            urls.push(uri.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// The distribution point URLs of every certificate in the snapshot, i.e. the CRLs of every CA that issued one of them.
+pub fn distribution_point_urls(pki_data: &ParsedPkiData) -> Vec<String> {
+    let mut urls = Vec::new();
+    for (_, certificate) in pki_data
+        .x509
+        .iter()
+        .filter_map(|x| X509Certificate::from_der(x).ok())
+    {
+        for url in crl_distribution_point_urls(&certificate) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// CRLs are served DER encoded (RFC 5280 4.2.1.13), some CAs serve PEM.
+pub fn parse_crl_response(
+    body: &[u8],
+) -> Result<CertificateRevocationListDer<'static>, CrlFetchError> {
+    let der = if body.starts_with(b"-----BEGIN") {
+        rustls_pemfile::crls(&mut Cursor::new(body))
+            .next()
+            .ok_or(CrlFetchError::InvalidCrl)?
+            .map_err(|_| CrlFetchError::InvalidCrl)?
+    } else {
+        CertificateRevocationListDer::from(body.to_vec())
+    };
+    CertificateRevocationList::from_der(&der).map_err(|_| CrlFetchError::InvalidCrl)?;
+    Ok(der)
+}
+
+/// Downloads the CRLs of the distribution points, caches them on disk, and refreshes them ahead of their nextUpdate.
+/// The current CRLs are published to subscribers, see [`PkiValidator::with_fetched_crls`](crate::validate::validate::PkiValidator::with_fetched_crls).
+pub struct CrlFetcher<F: HttpFetcher> {
+    fetcher: F,
+    cache_dir: Option<PathBuf>,
+    refresh_margin: Duration,
+    cache: RefreshCache<String, CertificateRevocationListDer<'static>>,
+    published: watch::Sender<Arc<Vec<CertificateRevocationListDer<'static>>>>,
+}
+
+impl<F: HttpFetcher> CrlFetcher<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            cache_dir: None,
+            refresh_margin: Duration::from_secs(15 * 60),
+            cache: Default::default(),
+            published: watch::Sender::new(Default::default()),
+        }
+    }
+
+    /// Keep downloaded CRLs in this directory, so they survive restarts and outages of the distribution point.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// How long before nextUpdate a CRL is refreshed.
+    pub fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Vec<CertificateRevocationListDer<'static>>>> {
+        self.published.subscribe()
+    }
+
+    pub fn crls(&self) -> Arc<Vec<CertificateRevocationListDer<'static>>> {
+        self.published.borrow().clone()
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        let digest = ring::digest::digest(&ring::digest::SHA256, url.as_bytes());
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.crl", const_hex::encode(digest))))
+    }
+
+    fn refresh_at(&self, der: &CertificateRevocationListDer, now: SystemTime) -> SystemTime {
+        let next_update = CertificateRevocationList::from_der(der)
+            .ok()
+            .and_then(|(_, crl)| crl.next_update());
+        refresh_at(next_update, self.refresh_margin, now)
+    }
+
+    async fn read_cache(&self, url: &str) -> Option<CertificateRevocationListDer<'static>> {
+        let body = tokio::fs::read(self.cache_path(url)?).await.ok()?;
+        parse_crl_response(&body).ok()
+    }
+
+    async fn write_cache(
+        &self,
+        url: &str,
+        der: &CertificateRevocationListDer<'_>,
+    ) -> Result<(), CrlFetchError> {
+        let Some(path) = self.cache_path(url) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let temporary = path.with_extension("crl.tmp");
+        tokio::fs::write(&temporary, der.as_ref()).await?;
+        tokio::fs::rename(&temporary, &path).await?;
+        Ok(())
+    }
+
+    /// Returns the CRL of the distribution point, downloading it when the cached copy is due for refresh.
+    /// When the download fails, the cached copy is returned if there is one.
+    pub async fn fetch(
+        &self,
+        url: &str,
+    ) -> Result<CertificateRevocationListDer<'static>, CrlFetchError> {
+        let now = SystemTime::now();
+        let cached = self.cache.get(url);
+        // A copy cached on disk is only a fallback, the distribution point is queried first.
+        let cached = match cached {
+            Some(cached) => Some(cached),
+            None => self.read_cache(url).await.map(|der| (der, now)),
+        };
+        if let Some((der, refresh_at)) = &cached {
+            if *refresh_at > now {
+                self.cache.store(url.to_string(), der.clone(), *refresh_at);
+                return Ok(der.clone());
+            }
+        }
+        tracing::debug!("Fetching CRL from {}", url);
+        let downloaded = match self.fetcher.get(url).await {
+            Ok(body) => parse_crl_response(&body),
+            Err(err) => Err(err.into()),
+        };
+        match (downloaded, cached) {
+            (Ok(der), _) => {
+                if let Err(err) = self.write_cache(url, &der).await {
+                    tracing::warn!("Failed to cache CRL of {}: {}", url, err);
+                }
+                self.cache
+                    .store(url.to_string(), der.clone(), self.refresh_at(&der, now));
+                Ok(der)
+            }
+            (Err(err), Some((der, _))) => {
+                tracing::warn!(
+                    "Failed to fetch CRL from {}, using the cached copy: {}",
+                    url,
+                    err
+                );
+                self.cache
+                    .store(url.to_string(), der.clone(), now + RETRY_INTERVAL);
+                Ok(der)
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    /// Fetches the CRLs of the URLs and publishes them, CRLs of URLs no longer listed are dropped.
+    /// Returns how long until the next refresh is due.
+    pub async fn refresh(&self, urls: &[String]) -> Duration {
+        let mut crls = Vec::new();
+        for url in urls {
+            match self.fetch(url).await {
+                Ok(der) => crls.push(der),
+                Err(err) => tracing::warn!("Failed to fetch CRL from {}: {}", url, err),
+            }
+        }
+        let next_refresh = self
+            .cache
+            .next_refresh(|url| urls.contains(url), crls.len() < urls.len());
+        self.published.send_replace(Arc::new(crls));
+        next_refresh
+    }
+
+    /// Background refresh loop, spawn it with `tokio::spawn`. Send the distribution points of every
+    /// new snapshot through `urls`, see [`distribution_point_urls`]. Returns once the sender is dropped.
+    pub async fn run(self: Arc<Self>, urls: watch::Receiver<Vec<String>>) {
+        run_refresh_loop(urls, |current| {
+            let fetcher = self.clone();
+            async move { fetcher.refresh(&current).await }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::fetch::crl::{crl_distribution_point_urls, CrlFetcher};
+    use crate::fetch::StubFetcher;
+
+    const URL: &str = "http://127.0.0.1:8081/pki/test.crl";
+
+    #[test]
+    fn test_crl_distribution_point_urls() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../../tests/data/ca.crt")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        assert_eq!(
+            crl_distribution_point_urls(&certificate),
+            vec![URL.to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_cache_on_disk() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("pki-watcher-crl-{}", std::process::id()));
+        let stub = StubFetcher::new([(
+            URL.to_string(),
+            include_bytes!("../../tests/data/test.crl").to_vec(),
+        )]);
+        let requests = stub.requests();
+        let fetcher = CrlFetcher::new(stub).with_cache_dir(&cache_dir);
+        let receiver = fetcher.subscribe();
+        fetcher.refresh(&[URL.to_string()]).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(receiver.borrow().len(), 1);
+
+        // The distribution point is down, the copy cached on disk is used.
+        let stub = StubFetcher::new([]);
+        let requests = stub.requests();
+        let offline = CrlFetcher::new(stub).with_cache_dir(&cache_dir);
+        let crl = offline.fetch(URL).await.unwrap();
+        assert_eq!(crl, fetcher.crls()[0]);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(cache_dir).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::fetch::{FetchError, HttpFetcher};
+
+/// Default cap of a response, large CRLs run into a few megabytes.
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+/// Redirects followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// Plain `http://` fetcher. AIA, CRL and OCSP URLs are served over plain HTTP (RFC 5280 4.2.2.1),
+/// the content is signed so there is no need for TLS.
+#[derive(Clone, Debug)]
+pub struct TcpHttpFetcher {
+    pub timeout: Duration,
+    /// Responses larger than this, headers included, fail with `ResponseTooLarge`.
+    pub max_response_size: usize,
+}
+
+impl Default for TcpHttpFetcher {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HttpUrl<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub path: &'a str,
+}
+
+impl HttpUrl<'_> {
+    /// The `Host` header value, IPv6 literals keep their brackets and non-default ports are kept.
+    fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.to_string()
+        };
+        match self.port {
+            80 => host,
+            port => format!("{}:{}", host, port),
+        }
+    }
+}
+
+pub(crate) fn parse_http_url(url: &str) -> Result<HttpUrl, FetchError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| FetchError::UnsupportedUrl(url.to_string()))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| FetchError::UnsupportedUrl(url.to_string()))?,
+        ),
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(FetchError::UnsupportedUrl(url.to_string()));
+    }
+    Ok(HttpUrl { host, port, path })
+}
+
+enum HttpResponse {
+    Body(Vec<u8>),
+    Redirect(String),
+}
+
+impl TcpHttpFetcher {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Sends the request, following `301` and `302` redirects with the same method and body.
+    pub(crate) async fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<Vec<u8>, FetchError> {
+        let mut url = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            match self.exchange(method, &url, headers, body).await? {
+                HttpResponse::Body(body) => return Ok(body),
+                HttpResponse::Redirect(location) => url = resolve_location(&url, &location)?,
+            }
+        }
+        Err(FetchError::TooManyRedirects)
+    }
+
+    async fn exchange(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<HttpResponse, FetchError> {
+        let target = parse_http_url(url)?;
+        let exchange = async {
+            let mut stream = TcpStream::connect((target.host, target.port)).await?;
+            let mut request = format!(
+                "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+                method,
+                target.path,
+                target.host_header(),
+                body.len()
+            );
+            for (name, value) in headers {
+                request.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            request.push_str("\r\n");
+            stream.write_all(request.as_bytes()).await?;
+            stream.write_all(body).await?;
+            let mut response = Vec::new();
+            // One byte past the limit tells an exact fit from a truncated response.
+            stream
+                .take(self.max_response_size as u64 + 1)
+                .read_to_end(&mut response)
+                .await?;
+            if response.len() > self.max_response_size {
+                return Err(FetchError::ResponseTooLarge(self.max_response_size));
+            }
+            Ok::<_, FetchError>(response)
+        };
+        let response = tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+        parse_http_response(&response)
+    }
+}
+
+#[async_trait]
+impl HttpFetcher for TcpHttpFetcher {
+    async fn get(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.request("GET", url, &[], &[]).await
+    }
+}
+
+/// Resolves a `Location` header, absolute `http://` URLs or paths on the same host.
+fn resolve_location(url: &str, location: &str) -> Result<String, FetchError> {
+    if location.starts_with('/') {
+        let target = parse_http_url(url)?;
+        Ok(format!("http://{}{}", target.host_header(), location))
+    } else {
+        parse_http_url(location)?;
+        Ok(location.to_string())
+    }
+}
+
+fn parse_http_response(response: &[u8]) -> Result<HttpResponse, FetchError> {
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(FetchError::InvalidResponse)?;
+    let head =
+        std::str::from_utf8(&response[..header_end]).map_err(|_| FetchError::InvalidResponse)?;
+    let body = &response[header_end + 4..];
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or(FetchError::InvalidResponse)?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect::<Vec<_>>();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    };
+    if status == 301 || status == 302 {
+        return header("location")
+            .map(|location| HttpResponse::Redirect(location.to_string()))
+            .ok_or(FetchError::InvalidResponse);
+    }
+    if !(200..300).contains(&status) {
+        return Err(FetchError::UnexpectedStatus(status));
+    }
+    if header("transfer-encoding").map_or(false, |value| value.eq_ignore_ascii_case("chunked")) {
+        decode_chunked(body).map(HttpResponse::Body)
+    } else {
+        Ok(HttpResponse::Body(body.to_vec()))
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, FetchError> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or(FetchError::InvalidResponse)?;
+        let size_line =
+            std::str::from_utf8(&body[..line_end]).map_err(|_| FetchError::InvalidResponse)?;
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)
+            .map_err(|_| FetchError::InvalidResponse)?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(decoded);
+        }
+        if body.len() < size + 2 {
+            return Err(FetchError::InvalidResponse);
+        }
+        decoded.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use crate::fetch::http::{
+        decode_chunked, parse_http_response, parse_http_url, HttpResponse, HttpUrl, TcpHttpFetcher,
+    };
+    use crate::fetch::{FetchError, HttpFetcher};
+
+    /// Serves the responses in order, one per connection, and returns the requests received.
+    async fn serve(responses: Vec<Vec<u8>>) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8; 1];
+                    stream.read_exact(&mut byte).await.unwrap();
+                    request.push(byte[0]);
+                }
+                requests.push(String::from_utf8(request).unwrap());
+                stream.write_all(&response).await.unwrap();
+            }
+            requests
+        });
+        (port, server)
+    }
+
+    fn body(response: Result<HttpResponse, FetchError>) -> Vec<u8> {
+        match response {
+            Ok(HttpResponse::Body(body)) => body,
+            _ => panic!("expected a body"),
+        }
+    }
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://crl.example.com/ca.crl").unwrap(),
+            HttpUrl {
+                host: "crl.example.com",
+                port: 80,
+                path: "/ca.crl"
+            }
+        );
+        let url = parse_http_url("http://[2001:db8::1]:8080").unwrap();
+        assert_eq!((url.host, url.port, url.path), ("2001:db8::1", 8080, "/"));
+        assert_eq!(url.host_header(), "[2001:db8::1]:8080");
+        assert_eq!(
+            parse_http_url("http://ocsp.example.com:80/")
+                .unwrap()
+                .host_header(),
+            "ocsp.example.com"
+        );
+        for url in [
+            "https://crl.example.com/ca.crl",
+            "http:///ca.crl",
+            "http://crl.example.com:http/",
+        ] {
+            assert!(matches!(
+                parse_http_url(url),
+                Err(FetchError::UnsupportedUrl(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked(b"4\r\nWiki\r\n5;name=value\r\npedia\r\n0\r\n\r\n").unwrap(),
+            b"Wikipedia"
+        );
+        assert!(decode_chunked(b"a\r\nshort\r\n").is_err());
+        assert!(decode_chunked(b"zz\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_http_response() {
+        assert_eq!(
+            body(parse_http_response(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody"
+            )),
+            b"body"
+        );
+        assert_eq!(
+            body(parse_http_response(
+                b"HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\n4\r\nbody\r\n0\r\n\r\n"
+            )),
+            b"body"
+        );
+        assert!(matches!(
+            parse_http_response(b"HTTP/1.1 302 Found\r\nLocation: /moved\r\n\r\n"),
+            Ok(HttpResponse::Redirect(location)) if location == "/moved"
+        ));
+        assert!(matches!(
+            parse_http_response(b"HTTP/1.1 404 Not Found\r\n\r\n"),
+            Err(FetchError::UnexpectedStatus(404))
+        ));
+        assert!(matches!(
+            parse_http_response(b"HTTP/1.1 200 OK\r\n"),
+            Err(FetchError::InvalidResponse)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody".to_vec()
+        ])
+        .await;
+        let fetcher = TcpHttpFetcher::new(Duration::from_secs(5));
+        let response = fetcher
+            .request(
+                "POST",
+                &format!("http://127.0.0.1:{}/ocsp", port),
+                &[("Content-Type", "application/ocsp-request")],
+                b"",
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, b"body");
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /ocsp HTTP/1.1\r\n"));
+        assert!(requests[0].contains(&format!("\r\nHost: 127.0.0.1:{}\r\n", port)));
+        assert!(requests[0].contains("\r\nContent-Type: application/ocsp-request\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_request_follows_redirects() {
+        let (port, server) = serve(vec![
+            b"HTTP/1.1 301 Moved Permanently\r\nLocation: /ca.crl\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\n\r\ncrl".to_vec(),
+        ])
+        .await;
+        let fetcher = TcpHttpFetcher::default();
+        let response = fetcher
+            .get(&format!("http://127.0.0.1:{}/old.crl", port))
+            .await
+            .unwrap();
+        assert_eq!(response, b"crl");
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("GET /ca.crl HTTP/1.1\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_request_too_many_redirects() {
+        let redirect = b"HTTP/1.1 302 Found\r\nLocation: /loop\r\n\r\n".to_vec();
+        let (port, _) = serve(vec![redirect; 6]).await;
+        let fetcher = TcpHttpFetcher::default();
+        assert!(matches!(
+            fetcher
+                .get(&format!("http://127.0.0.1:{}/loop", port))
+                .await,
+            Err(FetchError::TooManyRedirects)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_response_size_limit() {
+        let response = b"HTTP/1.1 200 OK\r\n\r\nbody".to_vec();
+        let (port, _) = serve(vec![response.clone(); 2]).await;
+        let url = format!("http://127.0.0.1:{}/", port);
+        let fetcher = TcpHttpFetcher::default().with_max_response_size(response.len());
+        assert_eq!(fetcher.get(&url).await.unwrap(), b"body");
+        let fetcher = fetcher.with_max_response_size(response.len() - 1);
+        assert!(matches!(
+            fetcher.get(&url).await,
+            Err(FetchError::ResponseTooLarge(_))
+        ));
+    }
+}
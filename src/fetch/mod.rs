@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+use x509_parser::time::ASN1Time;
+
+pub mod aia;
+pub mod crl;
+pub mod http;
+pub mod ocsp;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FetchError {
+    #[error("UnsupportedUrl: {0}")]
+    UnsupportedUrl(String),
+    #[error("InvalidResponse")]
+    InvalidResponse,
+    #[error("UnexpectedStatus: {0}")]
+    UnexpectedStatus(u16),
+    /// The response exceeds the fetcher's size limit.
+    #[error("ResponseTooLarge: more than {0} bytes")]
+    ResponseTooLarge(usize),
+    #[error("TooManyRedirects")]
+    TooManyRedirects,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Downloads PKI resources (issuer certificates, CRLs, ...) referenced by certificates.
+/// Implement this to plug in your own HTTP client, or a stub in tests.
+#[async_trait]
+pub trait HttpFetcher: Send + Sync {
+    async fn get(&self, url: &str) -> Result<Vec<u8>, FetchError>;
+}
+
+/// Refresh interval of resources without nextUpdate.
+pub(crate) const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Delay before retrying a failed download, or a resource that is already past its nextUpdate.
+pub(crate) const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// When a resource with this nextUpdate is due for refresh, `refresh_margin` ahead of nextUpdate.
+pub(crate) fn refresh_at(
+    next_update: Option<ASN1Time>,
+    refresh_margin: Duration,
+    now: SystemTime,
+) -> SystemTime {
+    let next_update = next_update
+        .and_then(|next_update| u64::try_from(next_update.timestamp()).ok())
+        .map(|timestamp| UNIX_EPOCH + Duration::from_secs(timestamp));
+    match next_update {
+        Some(next_update) => next_update
+            .checked_sub(refresh_margin)
+            .filter(|refresh_at| *refresh_at > now)
+            .unwrap_or(now + RETRY_INTERVAL),
+        None => now + DEFAULT_REFRESH_INTERVAL,
+    }
+}
+
+/// Downloaded resources and when they are due for refresh.
+pub(crate) struct RefreshCache<K, V> {
+    entries: Mutex<HashMap<K, (V, SystemTime)>>,
+}
+
+impl<K, V> Default for RefreshCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> RefreshCache<K, V> {
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<(V, SystemTime)>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub(crate) fn store(&self, key: K, value: V, refresh_at: SystemTime) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (value, refresh_at));
+    }
+
+    /// Drops the entries no longer `wanted`, and returns how long until the next refresh is due.
+    /// When a download `failed`, it is retried within [`RETRY_INTERVAL`].
+    pub(crate) fn next_refresh(&self, wanted: impl Fn(&K) -> bool, failed: bool) -> Duration {
+        let now = SystemTime::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|key, _| wanted(key));
+        let next_refresh = entries
+            .values()
+            .map(|(_, refresh_at)| *refresh_at)
+            .min()
+            .map(|refresh_at| refresh_at.duration_since(now).unwrap_or_default())
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+        if failed {
+            next_refresh.min(RETRY_INTERVAL)
+        } else {
+            next_refresh
+        }
+    }
+}
+
+/// Background refresh loop of the fetchers, calls `refresh` with the current input whenever it's due or the input changes.
+/// Returns once the sender is dropped.
+pub(crate) async fn run_refresh_loop<T, F, R>(mut inputs: watch::Receiver<T>, mut refresh: F)
+where
+    T: Clone,
+    F: FnMut(T) -> R,
+    R: Future<Output = Duration>,
+{
+    loop {
+        let current = inputs.borrow_and_update().clone();
+        let next_refresh = refresh(current).await;
+        tokio::select! {
+            _ = tokio::time::sleep(next_refresh) => {}
+            changed = inputs.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Serves canned responses, and counts the requests.
+#[cfg(test)]
+pub(crate) struct StubFetcher {
+    responses: HashMap<String, Vec<u8>>,
+    requests: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[cfg(test)]
+impl StubFetcher {
+    pub(crate) fn new(responses: impl IntoIterator<Item = (String, Vec<u8>)>) -> Self {
+        Self {
+            responses: responses.into_iter().collect(),
+            requests: Default::default(),
+        }
+    }
+
+    /// A handle on the request count, which stays readable once the stub is moved into a fetcher.
+    pub(crate) fn requests(&self) -> std::sync::Arc<std::sync::atomic::AtomicUsize> {
+        self.requests.clone()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl HttpFetcher for StubFetcher {
+    async fn get(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.requests
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.responses
+            .get(url)
+            .cloned()
+            .ok_or(FetchError::UnexpectedStatus(404))
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use base64::Engine;
+use rustls_pki_types::CertificateDer;
+use tokio::sync::{broadcast, watch};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+use crate::fetch::{
+    refresh_at, run_refresh_loop, FetchError, HttpFetcher, RefreshCache, RETRY_INTERVAL,
+};
+use crate::parser::parse::Identities;
+use crate::validate::crl::RevocationReason;
+use crate::validate::ocsp::{build_ocsp_request, verify_ocsp_response, OcspCertStatus, OcspError};
+
+#[derive(thiserror::Error, Debug)]
+pub enum OcspFetchError {
+    #[error(transparent)]
+    FetchError(#[from] FetchError),
+    #[error(transparent)]
+    OcspError(#[from] OcspError),
+    /// The certificate has no OCSP responder in its Authority Information Access extension.
+    #[error("MissingOcspResponder")]
+    MissingOcspResponder,
+    #[error("InvalidCertificate")]
+    InvalidCertificate,
+}
+
+/// Returns the OCSP responder URLs of the Authority Information Access extension.
+pub fn ocsp_responder_urls(certificate: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for extension in certificate.extensions() {
+        if let ParsedExtension::AuthorityInfoAccess(aia) = extension.parsed_extension() {
+            for access_description in &aia.accessdescs {
+                if access_description.access_method != OID_PKIX_ACCESS_DESCRIPTOR_OCSP {
+                    continue;
+                }
+                if let GeneralName::URI(uri) = access_description.access_location {
+                    urls.push(uri.to_string());
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// The GET form of an OCSP request, the URL encoded base64 of the request appended to the responder URL (RFC 6960 A.1).
+/// Requests of a single certificate are small enough for GET, and the responses can be cached by HTTP caches.
+pub fn ocsp_get_url(responder_url: &str, request: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD
+        .encode(request)
+        .replace('+', "%2B")
+        .replace('/', "%2F")
+        .replace('=', "%3D");
+    format!("{}/{}", responder_url.trim_end_matches('/'), encoded)
+}
+
+/// A served certificate and its issuer, the input of [`OcspStapler`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OcspTarget {
+    pub certificate: CertificateDer<'static>,
+    pub issuer: CertificateDer<'static>,
+}
+
+impl OcspTarget {
+    /// The targets of every identity with an issuer, i.e. every identity that is not self-signed.
+    pub fn from_identities(identities: &Identities) -> Vec<Self> {
+        let mut targets = Vec::new();
+        for identity in identities.iter() {
+            let issuer = identity
+                .intermediate_der
+                .first()
+                .or(identity.ca_certificate_der.as_ref());
+            let Some(issuer) = issuer else {
+                continue;
+            };
+            if issuer == &identity.certificate_der {
+                continue;
+            }
+            let target = OcspTarget {
+                certificate: identity.certificate_der.clone().into_owned(),
+                issuer: issuer.clone().into_owned(),
+            };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        targets
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OcspEvent {
+    /// The responder reports a served certificate as revoked, the identity should be replaced.
+    Revoked {
+        certificate: CertificateDer<'static>,
+        serial: String,
+        reason: Option<RevocationReason>,
+        revocation_time: ASN1Time,
+    },
+}
+
+/// Current OCSP responses, keyed by the DER of the certificate they are for.
+pub type OcspStaples = HashMap<Vec<u8>, Vec<u8>>;
+
+/// Queries the OCSP responders of the served certificates, verifies and caches the responses, and refreshes them ahead of their nextUpdate.
+/// The current responses are published for stapling, see [`IdentityResolver::watch_ocsp_staples`](crate::resolver::IdentityResolver::watch_ocsp_staples).
+pub struct OcspStapler<F: HttpFetcher> {
+    fetcher: F,
+    refresh_margin: Duration,
+    cache: RefreshCache<Vec<u8>, Vec<u8>>,
+    published: watch::Sender<Arc<OcspStaples>>,
+    events: broadcast::Sender<OcspEvent>,
+}
+
+impl<F: HttpFetcher> OcspStapler<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            refresh_margin: Duration::from_secs(60 * 60),
+            cache: Default::default(),
+            published: watch::Sender::new(Default::default()),
+            events: broadcast::Sender::new(16),
+        }
+    }
+
+    /// How long before nextUpdate a response is refreshed.
+    pub fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<OcspStaples>> {
+        self.published.subscribe()
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<OcspEvent> {
+        self.events.subscribe()
+    }
+
+    /// The current response for the certificate.
+    pub fn response(&self, certificate: &CertificateDer) -> Option<Vec<u8>> {
+        self.published.borrow().get(certificate.as_ref()).cloned()
+    }
+
+    /// Returns the verified response for the target, querying the responder when the cached response is due for refresh.
+    /// When the query fails, the cached response is returned as long as it's still valid.
+    pub async fn fetch(&self, target: &OcspTarget) -> Result<Vec<u8>, OcspFetchError> {
+        let now = SystemTime::now();
+        let cached = self.cache.get(target.certificate.as_ref());
+        if let Some((der, refresh_at)) = &cached {
+            if *refresh_at > now {
+                return Ok(der.clone());
+            }
+        }
+        let (_, certificate) = X509Certificate::from_der(&target.certificate)
+            .map_err(|_| OcspFetchError::InvalidCertificate)?;
+        let (_, issuer) = X509Certificate::from_der(&target.issuer)
+            .map_err(|_| OcspFetchError::InvalidCertificate)?;
+        match self.query(&certificate, &issuer).await {
+            Ok((der, status, next_update)) => {
+                if let OcspCertStatus::Revoked {
+                    reason,
+                    revocation_time,
+                } = status
+                {
+                    tracing::warn!(
+                        "OCSP responder reports certificate serial:{} as revoked",
+                        certificate.raw_serial_as_string()
+                    );
+                    // No receivers is not an error.
+                    let _ = self.events.send(OcspEvent::Revoked {
+                        certificate: target.certificate.clone(),
+                        serial: certificate.raw_serial_as_string(),
+                        reason,
+                        revocation_time,
+                    });
+                }
+                self.cache.store(
+                    target.certificate.to_vec(),
+                    der.clone(),
+                    refresh_at(next_update, self.refresh_margin, now),
+                );
+                Ok(der)
+            }
+            Err(err) => {
+                let still_valid = cached.as_ref().is_some_and(|(der, _)| {
+                    verify_ocsp_response(der, &certificate, &issuer, ASN1Time::now()).is_ok()
+                });
+                match cached {
+                    Some((der, _)) if still_valid => {
+                        tracing::warn!("OCSP query failed, stapling the cached response: {}", err);
+                        self.cache.store(
+                            target.certificate.to_vec(),
+                            der.clone(),
+                            now + RETRY_INTERVAL,
+                        );
+                        Ok(der)
+                    }
+                    _ => Err(err),
+                }
+            }
+        }
+    }
+
+    async fn query(
+        &self,
+        certificate: &X509Certificate<'_>,
+        issuer: &X509Certificate<'_>,
+    ) -> Result<(Vec<u8>, OcspCertStatus, Option<ASN1Time>), OcspFetchError> {
+        let request = build_ocsp_request(certificate, issuer)?;
+        let mut last_error = OcspFetchError::MissingOcspResponder;
+        for url in ocsp_responder_urls(certificate) {
+            tracing::debug!("Querying OCSP responder {}", url);
+            let result = match self.fetcher.get(&ocsp_get_url(&url, &request)).await {
+                Ok(der) => verify_ocsp_response(&der, certificate, issuer, ASN1Time::now())
+                    .map(|info| (der, info.status, info.next_update))
+                    .map_err(OcspFetchError::from),
+                Err(err) => Err(err.into()),
+            };
+            match result {
+                Ok(response) => return Ok(response),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Fetches the responses of the targets and publishes them, responses of certificates no longer served are dropped.
+    /// Returns how long until the next refresh is due.
+    pub async fn refresh(&self, targets: &[OcspTarget]) -> Duration {
+        let mut staples = OcspStaples::new();
+        let mut failed = false;
+        for target in targets {
+            match self.fetch(target).await {
+                Ok(der) => {
+                    staples.insert(target.certificate.to_vec(), der);
+                }
+                Err(OcspFetchError::MissingOcspResponder) => {}
+                Err(err) => {
+                    tracing::warn!("Failed to fetch OCSP response: {}", err);
+                    failed = true;
+                }
+            }
+        }
+        let next_refresh = self.cache.next_refresh(
+            |certificate| {
+                targets
+                    .iter()
+                    .any(|target| target.certificate.as_ref() == certificate.as_slice())
+            },
+            failed,
+        );
+        self.published.send_replace(Arc::new(staples));
+        next_refresh
+    }
+
+    /// Background refresh loop, spawn it with `tokio::spawn`. Send the targets of every
+    /// new snapshot through `targets`, see [`OcspTarget::from_identities`]. Returns once the sender is dropped.
+    pub async fn run(self: Arc<Self>, targets: watch::Receiver<Vec<OcspTarget>>) {
+        run_refresh_loop(targets, |current| {
+            let stapler = self.clone();
+            async move { stapler.refresh(&current).await }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use rustls_pki_types::CertificateDer;
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::fetch::ocsp::{ocsp_get_url, ocsp_responder_urls, OcspStapler, OcspTarget};
+    use crate::fetch::StubFetcher;
+
+    const RESPONDER: &str = "http://127.0.0.1:8082/ocsp";
+
+    fn der(pem: &[u8]) -> CertificateDer<'static> {
+        let (_, pem) = parse_x509_pem(pem).unwrap();
+        CertificateDer::from(pem.contents)
+    }
+
+    #[test]
+    fn test_ocsp_responder_urls() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../../tests/data/ocsp-leaf.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        assert_eq!(
+            ocsp_responder_urls(&certificate),
+            vec![RESPONDER.to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_caches_response() {
+        let request = include_bytes!("../../tests/data/ocsp-request.der");
+        let stub = StubFetcher::new([(
+            ocsp_get_url(RESPONDER, request),
+            include_bytes!("../../tests/data/ocsp-good.der").to_vec(),
+        )]);
+        let requests = stub.requests();
+        let stapler = OcspStapler::new(stub);
+        let target = OcspTarget {
+            certificate: der(include_bytes!("../../tests/data/ocsp-leaf.pem")),
+            issuer: der(include_bytes!("../../tests/data/ocsp-ca.pem")),
+        };
+        stapler.refresh(&[target.clone()]).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            stapler.response(&target.certificate).unwrap(),
+            include_bytes!("../../tests/data/ocsp-good.der")
+        );
+        // The cached response is not due for refresh yet, the responder is not queried again.
+        let response = stapler.fetch(&target).await.unwrap();
+        assert_eq!(response, include_bytes!("../../tests/data/ocsp-good.der"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        stapler.refresh(&[]).await;
+        assert!(stapler.response(&target.certificate).is_none());
+    }
+}
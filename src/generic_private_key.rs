@@ -1,29 +1,378 @@
+use const_oid::db::rfc5912::{SECP_256_R_1, SECP_384_R_1, SECP_521_R_1};
+use const_oid::db::rfc8410::{ID_ED_25519, ID_ED_448};
+use const_oid::ObjectIdentifier;
+use der::asn1::OctetStringRef;
 use der::Decode;
+use elliptic_curve::sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint};
+use elliptic_curve::{AffinePoint, CurveArithmetic, FieldBytesSize};
 use pkcs8::PrivateKeyInfo;
+use rustls_pki_types::PrivateKeyDer;
+use sec1::EcParameters;
+use x509_parser::oid_registry::{OID_SIG_ED25519, OID_SIG_ED448};
+use x509_parser::public_key::PublicKey;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+pub const ED25519_SEED_LENGTH: usize = 32;
+pub const ED448_SEED_LENGTH: usize = 57;
+
+/// Named curves supported for EC key matching.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EcCurve {
+    P256,
+    P384,
+    P521,
+    Secp256k1,
+}
+
+impl EcCurve {
+    /// secp256k1, SEC 2 A.2.1, missing from the OID registries.
+    pub const SECP256K1_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+
+    pub fn from_oid(oid: &ObjectIdentifier) -> Option<Self> {
+        match *oid {
+            SECP_256_R_1 => Some(EcCurve::P256),
+            SECP_384_R_1 => Some(EcCurve::P384),
+            SECP_521_R_1 => Some(EcCurve::P521),
+            Self::SECP256K1_OID => Some(EcCurve::Secp256k1),
+            _ => None,
+        }
+    }
+
+    pub fn oid(&self) -> ObjectIdentifier {
+        match self {
+            EcCurve::P256 => SECP_256_R_1,
+            EcCurve::P384 => SECP_384_R_1,
+            EcCurve::P521 => SECP_521_R_1,
+            EcCurve::Secp256k1 => Self::SECP256K1_OID,
+        }
+    }
+
+    /// The length in bytes of a field element, i.e. of the private scalar and each public coordinate.
+    pub fn field_size(&self) -> usize {
+        match self {
+            EcCurve::P256 | EcCurve::Secp256k1 => 32,
+            EcCurve::P384 => 48,
+            EcCurve::P521 => 66,
+        }
+    }
+
+    /// Reads the named curve OID from the certificate's subject public key algorithm parameters, the curve may be
+    /// unsupported.
+    pub fn curve_oid_of(public_key: &SubjectPublicKeyInfo) -> Option<ObjectIdentifier> {
+        let curve = public_key.algorithm.parameters.as_ref()?.as_oid().ok()?;
+        ObjectIdentifier::from_bytes(curve.as_bytes()).ok()
+    }
+
+    /// Derives the uncompressed SEC1 public point from the private scalar.
+    pub fn derive_public_point(&self, scalar: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            EcCurve::P256 => derive_public_point::<p256::NistP256>(scalar),
+            EcCurve::P384 => derive_public_point::<p384::NistP384>(scalar),
+            EcCurve::P521 => derive_public_point::<p521::NistP521>(scalar),
+            EcCurve::Secp256k1 => derive_public_point::<k256::Secp256k1>(scalar),
+        }
+    }
+
+    /// Decodes a compressed or uncompressed SEC1 point, returning it uncompressed.
+    pub fn normalize_public_point(&self, point: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            EcCurve::P256 => normalize_public_point::<p256::NistP256>(point),
+            EcCurve::P384 => normalize_public_point::<p384::NistP384>(point),
+            EcCurve::P521 => normalize_public_point::<p521::NistP521>(point),
+            EcCurve::Secp256k1 => normalize_public_point::<k256::Secp256k1>(point),
+        }
+    }
+}
+
+fn derive_public_point<C>(scalar: &[u8]) -> Option<Vec<u8>>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    let secret_key = elliptic_curve::SecretKey::<C>::from_slice(scalar).ok()?;
+    Some(
+        secret_key
+            .public_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec(),
+    )
+}
+
+fn normalize_public_point<C>(point: &[u8]) -> Option<Vec<u8>>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    let public_key = elliptic_curve::PublicKey::<C>::from_sec1_bytes(point).ok()?;
+    Some(public_key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Checks the EC private key against the certificate's public point. The curves must agree,
+/// and the public point is derived from the private scalar since `EcPrivateKey.public_key` is optional.
+fn ec_key_matches(
+    private_key: &sec1::EcPrivateKey,
+    public_key: &SubjectPublicKeyInfo,
+    public_point: &[u8],
+) -> bool {
+    let Some(certificate_curve) = EcCurve::curve_oid_of(public_key) else {
+        return false;
+    };
+    if let Some(key_curve) = private_key.parameters.and_then(|p| p.named_curve()) {
+        if key_curve != certificate_curve {
+            return false;
+        }
+    }
+    match EcCurve::from_oid(&certificate_curve) {
+        Some(curve) => {
+            let derived = curve.derive_public_point(private_key.private_key);
+            derived.is_some() && derived == curve.normalize_public_point(public_point)
+        }
+        // Unsupported curve, the embedded public key is all there is to compare.
+        None => private_key.public_key == Some(public_point),
+    }
+}
+
+/// An EdDSA private key as found in PKCS#8 (RFC 8410 7).
+pub struct EdPrivateKey<'a> {
+    pub seed: &'a [u8],
+    /// Only present in PKCS#8 v2 (OneAsymmetricKey) encodings.
+    pub public_key: Option<&'a [u8]>,
+}
 
 pub enum GenericPrivateKey<'a> {
     RsaKey(pkcs1::RsaPrivateKey<'a>),
     ECKey(sec1::EcPrivateKey<'a>),
+    Ed25519Key(EdPrivateKey<'a>),
+    Ed448Key(EdPrivateKey<'a>),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ParsePkcs8Error {
     #[error(transparent)]
     Error(#[from] pkcs8::Error),
+    #[error(transparent)]
+    DerError(#[from] der::Error),
     #[error("invalid oid {0}")]
     InvalidOid(const_oid::ObjectIdentifier),
+    #[error("UnsupportedPrivateKeyFormat")]
+    UnsupportedPrivateKeyFormat,
+    #[error("InvalidKeyLength")]
+    InvalidKeyLength,
+    /// The PKCS#8 v2 public key does not belong to the seed.
+    #[error("PublicKeyMismatch")]
+    PublicKeyMismatch,
+}
+
+/// Big-endian integers may carry a leading zero byte to stay positive.
+pub(crate) fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
 }
 
 impl<'a> GenericPrivateKey<'a> {
     pub fn form_private_key_info(ai: PrivateKeyInfo<'a>) -> Result<Self, ParsePkcs8Error> {
         return match ai.algorithm.oid {
             pkcs1::ALGORITHM_OID => Ok(GenericPrivateKey::RsaKey::<'a>(
-                pkcs1::RsaPrivateKey::from_der(ai.private_key).unwrap(),
+                pkcs1::RsaPrivateKey::from_der(ai.private_key)?,
+            )),
+            sec1::ALGORITHM_OID => {
+                let mut private_key = sec1::EcPrivateKey::from_der(ai.private_key)?;
+                // PKCS#8 carries the curve in the algorithm parameters, the inner key usually omits it.
+                if private_key.parameters.is_none() {
+                    private_key.parameters = ai
+                        .algorithm
+                        .parameters_oid()
+                        .ok()
+                        .map(EcParameters::NamedCurve);
+                }
+                Ok(GenericPrivateKey::ECKey::<'a>(private_key))
+            }
+            ID_ED_25519 => Ok(GenericPrivateKey::Ed25519Key::<'a>(
+                EdPrivateKey::from_private_key_info(&ai, ED25519_SEED_LENGTH)?,
             )),
-            sec1::ALGORITHM_OID => Ok(GenericPrivateKey::ECKey::<'a>(
-                sec1::EcPrivateKey::from_der(ai.private_key).unwrap(),
+            ID_ED_448 => Ok(GenericPrivateKey::Ed448Key::<'a>(
+                EdPrivateKey::from_private_key_info(&ai, ED448_SEED_LENGTH)?,
             )),
             _ => Err(ParsePkcs8Error::InvalidOid(ai.algorithm.oid)),
         };
     }
+
+    pub fn from_private_key_der(
+        private_key: &'a PrivateKeyDer<'a>,
+    ) -> Result<Self, ParsePkcs8Error> {
+        match private_key {
+            PrivateKeyDer::Pkcs1(pkcs1) => Ok(GenericPrivateKey::RsaKey(
+                pkcs1::RsaPrivateKey::from_der(pkcs1.secret_pkcs1_der())?,
+            )),
+            PrivateKeyDer::Sec1(sec1) => Ok(GenericPrivateKey::ECKey(
+                sec1::EcPrivateKey::from_der(sec1.secret_sec1_der())?,
+            )),
+            PrivateKeyDer::Pkcs8(pkcs8) => {
+                Self::form_private_key_info(PrivateKeyInfo::from_der(pkcs8.secret_pkcs8_der())?)
+            }
+            _ => Err(ParsePkcs8Error::UnsupportedPrivateKeyFormat),
+        }
+    }
+
+    /// Checks if this private key belongs to the certificate's subject public key.
+    pub fn matches_public_key(&self, public_key: &SubjectPublicKeyInfo) -> bool {
+        match (self, public_key.parsed()) {
+            (GenericPrivateKey::RsaKey(private_key), Ok(PublicKey::RSA(rsa))) => {
+                trim_leading_zeros(private_key.modulus.as_bytes())
+                    == trim_leading_zeros(rsa.modulus)
+                    && trim_leading_zeros(private_key.public_exponent.as_bytes())
+                        == trim_leading_zeros(rsa.exponent)
+            }
+            (GenericPrivateKey::ECKey(private_key), Ok(PublicKey::EC(ec))) => {
+                ec_key_matches(private_key, public_key, ec.data())
+            }
+            (GenericPrivateKey::Ed25519Key(private_key), _)
+                if public_key.algorithm.algorithm == OID_SIG_ED25519 =>
+            {
+                private_key.ed25519_public_key().as_deref()
+                    == Some(public_key.subject_public_key.data.as_ref())
+            }
+            (GenericPrivateKey::Ed448Key(private_key), _)
+                if public_key.algorithm.algorithm == OID_SIG_ED448 =>
+            {
+                private_key.ed448_public_key().as_deref()
+                    == Some(public_key.subject_public_key.data.as_ref())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a> EdPrivateKey<'a> {
+    fn from_private_key_info(
+        ai: &PrivateKeyInfo<'a>,
+        seed_length: usize,
+    ) -> Result<Self, ParsePkcs8Error> {
+        // CurvePrivateKey ::= OCTET STRING
+        let seed = OctetStringRef::from_der(ai.private_key)?.as_bytes();
+        if seed.len() != seed_length {
+            return Err(ParsePkcs8Error::InvalidKeyLength);
+        }
+        let key = Self {
+            seed,
+            public_key: ai.public_key,
+        };
+        // The embedded public key is only a convenience, it has to agree with the seed.
+        if let Some(public_key) = ai.public_key {
+            let derived = match ai.algorithm.oid {
+                ID_ED_448 => key.ed448_public_key(),
+                _ => key.ed25519_public_key(),
+            };
+            if derived.as_deref() != Some(public_key) {
+                return Err(ParsePkcs8Error::PublicKeyMismatch);
+            }
+        }
+        Ok(key)
+    }
+
+    /// Returns the Ed25519 public key derived from the seed.
+    pub fn ed25519_public_key(&self) -> Option<Vec<u8>> {
+        use ring::signature::KeyPair;
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(self.seed).ok()?;
+        Some(key_pair.public_key().as_ref().to_vec())
+    }
+
+    /// Returns the Ed448 public key derived from the seed.
+    pub fn ed448_public_key(&self) -> Option<Vec<u8>> {
+        crate::ed448::public_key(self.seed).map(|public_key| public_key.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use der::Decode;
+    use pkcs8::PrivateKeyInfo;
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::generic_private_key::{EcCurve, GenericPrivateKey, ParsePkcs8Error};
+
+    fn matches_ed448_certificate(pem: &[u8]) -> Result<bool, ParsePkcs8Error> {
+        let (_, certificate) = parse_x509_pem(include_bytes!("../tests/data/ed448.pem")).unwrap();
+        let certificate = certificate.parse_x509().unwrap();
+        let (_, key) = parse_x509_pem(pem).unwrap();
+        let key =
+            GenericPrivateKey::form_private_key_info(PrivateKeyInfo::from_der(&key.contents)?)?;
+        Ok(key.matches_public_key(certificate.public_key()))
+    }
+
+    #[test]
+    fn test_ed448_key_matches() {
+        // PKCS#8 v1 carries only the seed, the public key is derived.
+        assert!(matches_ed448_certificate(include_bytes!("../tests/data/ed448.key")).unwrap());
+        assert!(matches_ed448_certificate(include_bytes!("../tests/data/ed448-v2.key")).unwrap());
+    }
+
+    #[test]
+    fn test_ed_public_key_mismatch() {
+        // The PKCS#8 v2 public key is the trailing bit string, corrupt its last byte.
+        let (_, key) = parse_x509_pem(include_bytes!("../tests/data/ed448-v2.key")).unwrap();
+        let mut der = key.contents.clone();
+        *der.last_mut().unwrap() ^= 0x01;
+        assert!(matches!(
+            GenericPrivateKey::form_private_key_info(PrivateKeyInfo::from_der(&der).unwrap()),
+            Err(ParsePkcs8Error::PublicKeyMismatch)
+        ));
+    }
+
+    fn matches_ec_certificate(certificate: &[u8], key: &[u8]) -> bool {
+        let (_, certificate) = parse_x509_pem(certificate).unwrap();
+        let certificate = certificate.parse_x509().unwrap();
+        let (_, key) = parse_x509_pem(key).unwrap();
+        let key = GenericPrivateKey::ECKey(sec1::EcPrivateKey::from_der(&key.contents).unwrap());
+        key.matches_public_key(certificate.public_key())
+    }
+
+    #[test]
+    fn test_ec_key_matches_curves() {
+        for (certificate, key) in [
+            (
+                &include_bytes!("../tests/data/ec-p384.pem")[..],
+                &include_bytes!("../tests/data/ec-p384.key")[..],
+            ),
+            (
+                include_bytes!("../tests/data/ec-p521.pem"),
+                include_bytes!("../tests/data/ec-p521.key"),
+            ),
+            (
+                include_bytes!("../tests/data/ec-secp256k1.pem"),
+                include_bytes!("../tests/data/ec-secp256k1.key"),
+            ),
+            // The certificate carries the compressed public point.
+            (
+                include_bytes!("../tests/data/ec-compressed.pem"),
+                include_bytes!("../tests/data/ec-compressed.key"),
+            ),
+        ] {
+            assert!(matches_ec_certificate(certificate, key));
+        }
+    }
+
+    #[test]
+    fn test_ec_key_curve_mismatch() {
+        assert!(!matches_ec_certificate(
+            include_bytes!("../tests/data/ec-p256.pem"),
+            include_bytes!("../tests/data/ec-p384.key"),
+        ));
+        assert!(!matches_ec_certificate(
+            include_bytes!("../tests/data/ec-p384.pem"),
+            include_bytes!("../tests/data/ec-p521.key"),
+        ));
+    }
+
+    #[test]
+    fn test_curve_oid_of() {
+        let (_, certificate) =
+            parse_x509_pem(include_bytes!("../tests/data/ec-secp256k1.pem")).unwrap();
+        let certificate = certificate.parse_x509().unwrap();
+        let oid = EcCurve::curve_oid_of(certificate.public_key()).unwrap();
+        assert_eq!(EcCurve::from_oid(&oid), Some(EcCurve::Secp256k1));
+    }
 }
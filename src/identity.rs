@@ -1,29 +1,141 @@
+use crate::provenance::Provenance;
+use crate::validate::hostname::SubjectName;
 use crate::validate::validate::{is_certificate_expired, is_self_signed};
-use rustls_pki_types::{PrivateKeyDer, ServerName};
+use rustls::SignatureScheme;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName};
 use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::{
+    OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384, OID_NIST_EC_P521,
+    OID_PKCS1_RSAENCRYPTION, OID_PKCS1_RSASSAPSS, OID_SIG_ED25519, OID_SIG_ED448,
+};
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+/// The key algorithm of an identity, determines which TLS signature schemes it can sign with.
+/// Ordered by preference when several identities serve the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyAlgorithm {
+    EcdsaP256,
+    EcdsaP384,
+    EcdsaP521,
+    Ed25519,
+    Ed448,
+    Rsa,
+    Unknown,
+}
+
+impl KeyAlgorithm {
+    pub fn from_public_key(public_key: &SubjectPublicKeyInfo) -> Self {
+        let algorithm = &public_key.algorithm.algorithm;
+        if *algorithm == OID_PKCS1_RSAENCRYPTION || *algorithm == OID_PKCS1_RSASSAPSS {
+            KeyAlgorithm::Rsa
+        } else if *algorithm == OID_SIG_ED25519 {
+            KeyAlgorithm::Ed25519
+        } else if *algorithm == OID_SIG_ED448 {
+            KeyAlgorithm::Ed448
+        } else if *algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+            match public_key
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|parameters| parameters.as_oid().ok())
+            {
+                Some(curve) if curve == OID_EC_P256 => KeyAlgorithm::EcdsaP256,
+                Some(curve) if curve == OID_NIST_EC_P384 => KeyAlgorithm::EcdsaP384,
+                Some(curve) if curve == OID_NIST_EC_P521 => KeyAlgorithm::EcdsaP521,
+                _ => KeyAlgorithm::Unknown,
+            }
+        } else {
+            KeyAlgorithm::Unknown
+        }
+    }
+
+    /// Checks if a key of this algorithm can sign with any of the offered signature schemes.
+    pub fn is_supported_by(&self, signature_schemes: &[SignatureScheme]) -> bool {
+        signature_schemes.iter().any(|scheme| match self {
+            KeyAlgorithm::EcdsaP256 => *scheme == SignatureScheme::ECDSA_NISTP256_SHA256,
+            KeyAlgorithm::EcdsaP384 => *scheme == SignatureScheme::ECDSA_NISTP384_SHA384,
+            KeyAlgorithm::EcdsaP521 => *scheme == SignatureScheme::ECDSA_NISTP521_SHA512,
+            KeyAlgorithm::Ed25519 => *scheme == SignatureScheme::ED25519,
+            KeyAlgorithm::Ed448 => *scheme == SignatureScheme::ED448,
+            KeyAlgorithm::Rsa => matches!(
+                scheme,
+                SignatureScheme::RSA_PKCS1_SHA256
+                    | SignatureScheme::RSA_PKCS1_SHA384
+                    | SignatureScheme::RSA_PKCS1_SHA512
+                    | SignatureScheme::RSA_PSS_SHA256
+                    | SignatureScheme::RSA_PSS_SHA384
+                    | SignatureScheme::RSA_PSS_SHA512
+            ),
+            KeyAlgorithm::Unknown => false,
+        })
+    }
+}
+
+/// Picks the candidate with the most preferred key algorithm the client supports.
+/// Candidates are expected in lookup order, which breaks ties between equal algorithms.
+pub fn select_by_algorithm<T>(
+    candidates: impl IntoIterator<Item = (KeyAlgorithm, T)>,
+    signature_schemes: &[SignatureScheme],
+) -> Option<T> {
+    let mut selected: Option<(KeyAlgorithm, T)> = None;
+    for (algorithm, candidate) in candidates {
+        if !algorithm.is_supported_by(signature_schemes) {
+            continue;
+        }
+        match &selected {
+            Some((selected_algorithm, _)) if *selected_algorithm <= algorithm => {}
+            _ => selected = Some((algorithm, candidate)),
+        }
+    }
+    selected.map(|(_, candidate)| candidate)
+}
 
 // The server identity, used to prove that it's the server, must hold private key,
 #[derive(Debug)]
 pub struct Identity<'a> {
-    /// Server name, parsed from certificate subject. Used to identify and verify certificate target.
-    pub server_name: ServerName<'a>,
+    /// The names the certificate serves, parsed from the Subject Alternative Names. Used to identify and verify certificate target.
+    pub names: Vec<SubjectName>,
     /// This server certificate.
     pub certificate: X509Certificate<'a>,
+    /// The DER of `certificate` as it was loaded, which is what is served, stapled and hashed.
+    pub certificate_der: CertificateDer<'a>,
     /// The private key for the certificate.
     pub private_key: PrivateKeyDer<'a>,
     /// The intermediate certificate between certificate and ca_certificate
     pub intermediate: Vec<X509Certificate<'a>>,
-    /// CA's certificate is normally pre-installed on a client as a trust anchor.
-    pub ca_certificate: X509Certificate<'a>,
+    /// The DER of the `intermediate` certificates, in the same order.
+    pub intermediate_der: Vec<CertificateDer<'a>>,
+    /// CA's certificate is normally pre-installed on a client as a trust anchor, hence often not part of the bundle.
+    pub ca_certificate: Option<X509Certificate<'a>>,
+    /// The DER of `ca_certificate`.
+    pub ca_certificate_der: Option<CertificateDer<'a>>,
+    /// Where the certificate was loaded from.
+    pub certificate_provenance: Option<Provenance>,
+    /// Where the private key was loaded from.
+    pub private_key_provenance: Option<Provenance>,
 }
 
 pub trait Identities {
     /// Checks if any of the certificates in the chain are self-signed.
     fn is_any_self_signed(&self) -> bool;
-    /// Checks if any of the certificates in the chain are expired.
-    fn is_any_expired(&self) -> bool;
+    /// Checks if any of the certificates in the chain are expired at `now`.
+    fn is_any_expired(&self, now: ASN1Time) -> bool;
     /// Returns the server certificate, intermediate certificates and CA's certificate.
     fn get_certificate_chain(&self) -> Vec<X509Certificate>;
+    /// Returns the DER of the server certificate, intermediate certificates and CA's certificate, as loaded.
+    fn get_certificate_chain_der(&self) -> Vec<CertificateDer>;
+}
+
+impl Identity<'_> {
+    /// Checks if the identity serves the server name.
+    pub fn serves(&self, server_name: &ServerName) -> bool {
+        self.names.iter().any(|name| name.matches(server_name))
+    }
+
+    pub fn key_algorithm(&self) -> KeyAlgorithm {
+        KeyAlgorithm::from_public_key(self.certificate.public_key())
+    }
 }
 
 impl Identities for Identity<'_> {
@@ -36,9 +148,9 @@ impl Identities for Identity<'_> {
         false
     }
 
-    fn is_any_expired(&self) -> bool {
+    fn is_any_expired(&self, now: ASN1Time) -> bool {
         for cert in &self.get_certificate_chain() {
-            if is_certificate_expired(cert) {
+            if is_certificate_expired(cert, now) {
                 return true;
             }
         }
@@ -49,7 +161,47 @@ impl Identities for Identity<'_> {
         let mut certificate_chain = Vec::new();
         certificate_chain.push(self.certificate.clone());
         certificate_chain.append(&mut self.intermediate.clone());
-        certificate_chain.push(self.ca_certificate.clone());
+        if let Some(ca_certificate) = &self.ca_certificate {
+            certificate_chain.push(ca_certificate.clone());
+        }
+        certificate_chain
+    }
+
+    fn get_certificate_chain_der(&self) -> Vec<CertificateDer> {
+        let mut certificate_chain = Vec::new();
+        certificate_chain.push(self.certificate_der.clone());
+        certificate_chain.extend(self.intermediate_der.iter().cloned());
+        certificate_chain.extend(self.ca_certificate_der.clone());
         certificate_chain
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rustls::SignatureScheme;
+
+    use crate::identity::{select_by_algorithm, KeyAlgorithm};
+
+    #[test]
+    fn test_select_by_algorithm() {
+        let candidates = [
+            (KeyAlgorithm::Rsa, "rsa"),
+            (KeyAlgorithm::EcdsaP256, "ecdsa"),
+            (KeyAlgorithm::EcdsaP256, "ecdsa-2"),
+        ];
+        let both = [
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+        ];
+        // The first of the preferred algorithm wins.
+        assert_eq!(select_by_algorithm(candidates, &both), Some("ecdsa"));
+        assert_eq!(
+            select_by_algorithm(candidates, &[SignatureScheme::RSA_PKCS1_SHA256]),
+            Some("rsa")
+        );
+        assert_eq!(
+            select_by_algorithm(candidates, &[SignatureScheme::ED25519]),
+            None
+        );
+    }
+}
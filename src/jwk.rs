@@ -0,0 +1,725 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use const_oid::db::rfc8410::{ID_ED_25519, ID_ED_448};
+use der::asn1::{OctetStringRef, UintRef};
+use der::Encode;
+use pkcs8::PrivateKeyInfo;
+use rustls_pki_types::{
+    CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer,
+};
+use sec1::EcParameters;
+use serde::{Deserialize, Serialize};
+use spki::AlgorithmIdentifierRef;
+use tokio::sync::watch;
+use x509_parser::oid_registry::{OID_SIG_ED25519, OID_SIG_ED448};
+use x509_parser::public_key::PublicKey;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+use crate::generic_private_key::{
+    trim_leading_zeros, EcCurve, EdPrivateKey, GenericPrivateKey, ParsePkcs8Error,
+};
+use crate::identity::{Identities as _, Identity};
+use crate::parser::parse::Identities;
+use crate::ParsedPkiData;
+
+#[derive(thiserror::Error, Debug)]
+pub enum JwkError {
+    #[error("UnsupportedKeyType {0}")]
+    UnsupportedKeyType(String),
+    #[error("UnsupportedCurve {0}")]
+    UnsupportedCurve(String),
+    #[error("MissingParameter {0}")]
+    MissingParameter(&'static str),
+    #[error("InvalidBase64 {0}")]
+    InvalidBase64(&'static str),
+    #[error("InvalidKey")]
+    InvalidKey,
+    /// The private and public parameters of the JWK don't belong to the same key.
+    #[error("KeyMismatch")]
+    KeyMismatch,
+    /// EC private keys without named curve parameters can't be exported.
+    #[error("MissingCurve")]
+    MissingCurve,
+    #[error(transparent)]
+    DerError(#[from] der::Error),
+    #[error(transparent)]
+    ParsePkcs8Error(#[from] ParsePkcs8Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// A JSON Web Key (RFC 7517) of kty `RSA`, `EC` (RFC 7518 6) or `OKP` (RFC 8037).
+/// Private keys carry `d` and for RSA the CRT parameters, which are left out of `Debug`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dq: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qi: Option<String>,
+    /// The certificate chain, standard base64 DER with the key's certificate first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub x5c: Vec<String>,
+    /// base64url SHA-256 thumbprint of the DER of the key's certificate.
+    #[serde(rename = "x5t#S256", default, skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+}
+
+impl Debug for Jwk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jwk")
+            .field("kty", &self.kty)
+            .field("kid", &self.kid)
+            .field("alg", &self.alg)
+            .field("crv", &self.crv)
+            .field("private", &self.is_private())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A JWK Set document (RFC 7517 5).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+fn curve_name(curve: EcCurve) -> &'static str {
+    match curve {
+        EcCurve::P256 => "P-256",
+        EcCurve::P384 => "P-384",
+        EcCurve::P521 => "P-521",
+        EcCurve::Secp256k1 => "secp256k1",
+    }
+}
+
+fn curve_from_name(name: &str) -> Result<EcCurve, JwkError> {
+    match name {
+        "P-256" => Ok(EcCurve::P256),
+        "P-384" => Ok(EcCurve::P384),
+        "P-521" => Ok(EcCurve::P521),
+        "secp256k1" => Ok(EcCurve::Secp256k1),
+        _ => Err(JwkError::UnsupportedCurve(name.to_string())),
+    }
+}
+
+fn encode(bytes: &[u8]) -> Option<String> {
+    Some(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// JWK integers are unsigned big-endian without leading zeros (RFC 7518 2).
+fn encode_uint(bytes: &[u8]) -> Option<String> {
+    encode(trim_leading_zeros(bytes))
+}
+
+/// EC coordinates and scalars have the fixed length of the curve's field elements (RFC 7518 6.2.1.2).
+fn left_pad(bytes: &[u8], length: usize) -> Result<Vec<u8>, JwkError> {
+    let bytes = trim_leading_zeros(bytes);
+    if bytes.len() > length {
+        return Err(JwkError::InvalidKey);
+    }
+    let mut padded = vec![0; length - bytes.len()];
+    padded.extend_from_slice(bytes);
+    Ok(padded)
+}
+
+fn sha256_thumbprint(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(ring::digest::digest(&ring::digest::SHA256, bytes))
+}
+
+impl Jwk {
+    fn ec(curve: EcCurve, public_point: &[u8]) -> Result<Self, JwkError> {
+        let public_point = curve
+            .normalize_public_point(public_point)
+            .ok_or(JwkError::InvalidKey)?;
+        let (x, y) = public_point[1..].split_at(curve.field_size());
+        Ok(Self {
+            kty: "EC".to_string(),
+            crv: Some(curve_name(curve).to_string()),
+            x: encode(x),
+            y: encode(y),
+            ..Default::default()
+        })
+    }
+
+    fn okp(crv: &str, public_key: &[u8]) -> Self {
+        Self {
+            kty: "OKP".to_string(),
+            crv: Some(crv.to_string()),
+            x: encode(public_key),
+            ..Default::default()
+        }
+    }
+
+    /// Converts the certificate's subject public key.
+    pub fn from_public_key(public_key: &SubjectPublicKeyInfo) -> Result<Self, JwkError> {
+        let algorithm = &public_key.algorithm.algorithm;
+        if *algorithm == OID_SIG_ED25519 {
+            return Ok(Self::okp("Ed25519", &public_key.subject_public_key.data));
+        }
+        if *algorithm == OID_SIG_ED448 {
+            return Ok(Self::okp("Ed448", &public_key.subject_public_key.data));
+        }
+        match public_key.parsed() {
+            Ok(PublicKey::RSA(rsa)) => Ok(Self {
+                kty: "RSA".to_string(),
+                n: encode_uint(rsa.modulus),
+                e: encode_uint(rsa.exponent),
+                ..Default::default()
+            }),
+            Ok(PublicKey::EC(ec)) => {
+                let curve = EcCurve::curve_oid_of(public_key).ok_or(JwkError::MissingCurve)?;
+                let curve = EcCurve::from_oid(&curve)
+                    .ok_or_else(|| JwkError::UnsupportedCurve(curve.to_string()))?;
+                Self::ec(curve, ec.data())
+            }
+            _ => Err(JwkError::UnsupportedKeyType(algorithm.to_id_string())),
+        }
+    }
+
+    /// Converts the private key, including its public parameters.
+    pub fn from_private_key(private_key: &GenericPrivateKey) -> Result<Self, JwkError> {
+        match private_key {
+            GenericPrivateKey::RsaKey(key) => Ok(Self {
+                kty: "RSA".to_string(),
+                n: encode_uint(key.modulus.as_bytes()),
+                e: encode_uint(key.public_exponent.as_bytes()),
+                d: encode_uint(key.private_exponent.as_bytes()),
+                p: encode_uint(key.prime1.as_bytes()),
+                q: encode_uint(key.prime2.as_bytes()),
+                dp: encode_uint(key.exponent1.as_bytes()),
+                dq: encode_uint(key.exponent2.as_bytes()),
+                qi: encode_uint(key.coefficient.as_bytes()),
+                ..Default::default()
+            }),
+            GenericPrivateKey::ECKey(key) => {
+                let curve = key
+                    .parameters
+                    .and_then(|parameters| parameters.named_curve())
+                    .ok_or(JwkError::MissingCurve)?;
+                let curve = EcCurve::from_oid(&curve)
+                    .ok_or_else(|| JwkError::UnsupportedCurve(curve.to_string()))?;
+                let public_point = match key.public_key {
+                    Some(public_point) => public_point.to_vec(),
+                    None => curve
+                        .derive_public_point(key.private_key)
+                        .ok_or(JwkError::InvalidKey)?,
+                };
+                let mut jwk = Self::ec(curve, &public_point)?;
+                jwk.d = encode(&left_pad(key.private_key, curve.field_size())?);
+                Ok(jwk)
+            }
+            GenericPrivateKey::Ed25519Key(key) => {
+                let public_key = key.ed25519_public_key().ok_or(JwkError::InvalidKey)?;
+                let mut jwk = Self::okp("Ed25519", &public_key);
+                jwk.d = encode(key.seed);
+                Ok(jwk)
+            }
+            GenericPrivateKey::Ed448Key(key) => {
+                let public_key = key.ed448_public_key().ok_or(JwkError::InvalidKey)?;
+                let mut jwk = Self::okp("Ed448", &public_key);
+                jwk.d = encode(key.seed);
+                Ok(jwk)
+            }
+        }
+    }
+
+    /// The public JWK of the identity with its certificate chain in `x5c` and `x5t#S256`,
+    /// `kid` is the RFC 7638 thumbprint.
+    pub fn from_identity(identity: &Identity) -> Result<Self, JwkError> {
+        let mut jwk = Self::from_public_key(identity.certificate.public_key())?;
+        jwk.key_use = Some("sig".to_string());
+        jwk.alg = jwk.signature_algorithm().map(str::to_string);
+        jwk.kid = Some(jwk.thumbprint()?);
+        let mut chain = identity.get_certificate_chain_der();
+        // A self-signed certificate is its own CA certificate.
+        chain.dedup();
+        jwk.x5c = chain
+            .iter()
+            .map(|certificate| STANDARD.encode(certificate))
+            .collect();
+        jwk.x5t_s256 = Some(sha256_thumbprint(&identity.certificate_der));
+        Ok(jwk)
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.d.is_some()
+    }
+
+    /// Returns the JWK without its private parameters.
+    pub fn to_public(&self) -> Self {
+        Self {
+            d: None,
+            p: None,
+            q: None,
+            dp: None,
+            dq: None,
+            qi: None,
+            ..self.clone()
+        }
+    }
+
+    /// The JWS algorithm the key signs with by default.
+    pub fn signature_algorithm(&self) -> Option<&'static str> {
+        match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => Some("RS256"),
+            ("EC", Some("P-256")) => Some("ES256"),
+            ("EC", Some("P-384")) => Some("ES384"),
+            ("EC", Some("P-521")) => Some("ES512"),
+            ("EC", Some("secp256k1")) => Some("ES256K"),
+            ("OKP", Some("Ed25519" | "Ed448")) => Some("EdDSA"),
+            _ => None,
+        }
+    }
+
+    fn required(&self, name: &'static str, value: &Option<String>) -> Result<String, JwkError> {
+        value.clone().ok_or(JwkError::MissingParameter(name))
+    }
+
+    fn decode(&self, name: &'static str, value: &Option<String>) -> Result<Vec<u8>, JwkError> {
+        URL_SAFE_NO_PAD
+            .decode(self.required(name, value)?)
+            .map_err(|_| JwkError::InvalidBase64(name))
+    }
+
+    /// The RFC 7638 thumbprint: base64url SHA-256 of the required members in lexicographic order.
+    pub fn thumbprint(&self) -> Result<String, JwkError> {
+        let canonical = match self.kty.as_str() {
+            "RSA" => format!(
+                r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#,
+                self.required("e", &self.e)?,
+                self.required("n", &self.n)?
+            ),
+            "EC" => format!(
+                r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+                self.required("crv", &self.crv)?,
+                self.required("x", &self.x)?,
+                self.required("y", &self.y)?
+            ),
+            "OKP" => format!(
+                r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#,
+                self.required("crv", &self.crv)?,
+                self.required("x", &self.x)?
+            ),
+            kty => return Err(JwkError::UnsupportedKeyType(kty.to_string())),
+        };
+        Ok(sha256_thumbprint(canonical.as_bytes()))
+    }
+
+    /// Decodes the `x5c` certificate chain.
+    pub fn certificates(&self) -> Result<Vec<CertificateDer<'static>>, JwkError> {
+        self.x5c
+            .iter()
+            .map(|certificate| {
+                STANDARD
+                    .decode(certificate)
+                    .map(CertificateDer::from)
+                    .map_err(|_| JwkError::InvalidBase64("x5c"))
+            })
+            .collect()
+    }
+
+    /// Encodes the private key as PKCS#1 (RSA), SEC1 (EC) or PKCS#8 (OKP).
+    /// The public parameters are checked against the private ones.
+    pub fn to_private_key_der(&self) -> Result<PrivateKeyDer<'static>, JwkError> {
+        match self.kty.as_str() {
+            "RSA" => {
+                let (n, e, d) = (
+                    self.decode("n", &self.n)?,
+                    self.decode("e", &self.e)?,
+                    self.decode("d", &self.d)?,
+                );
+                // Keys without the CRT parameters (RFC 7518 6.3.2) can't be encoded as PKCS#1.
+                let (p, q, dp, dq, qi) = (
+                    self.decode("p", &self.p)?,
+                    self.decode("q", &self.q)?,
+                    self.decode("dp", &self.dp)?,
+                    self.decode("dq", &self.dq)?,
+                    self.decode("qi", &self.qi)?,
+                );
+                let private_key = pkcs1::RsaPrivateKey {
+                    modulus: UintRef::new(&n)?,
+                    public_exponent: UintRef::new(&e)?,
+                    private_exponent: UintRef::new(&d)?,
+                    prime1: UintRef::new(&p)?,
+                    prime2: UintRef::new(&q)?,
+                    exponent1: UintRef::new(&dp)?,
+                    exponent2: UintRef::new(&dq)?,
+                    coefficient: UintRef::new(&qi)?,
+                    other_prime_infos: None,
+                };
+                Ok(PrivateKeyDer::Pkcs1(PrivatePkcs1KeyDer::from(
+                    private_key.to_der()?,
+                )))
+            }
+            "EC" => {
+                let curve = curve_from_name(&self.required("crv", &self.crv)?)?;
+                let scalar = left_pad(&self.decode("d", &self.d)?, curve.field_size())?;
+                let mut public_point = vec![0x04];
+                public_point.extend(left_pad(&self.decode("x", &self.x)?, curve.field_size())?);
+                public_point.extend(left_pad(&self.decode("y", &self.y)?, curve.field_size())?);
+                let derived = curve
+                    .derive_public_point(&scalar)
+                    .ok_or(JwkError::InvalidKey)?;
+                if derived != public_point {
+                    return Err(JwkError::KeyMismatch);
+                }
+                let private_key = sec1::EcPrivateKey {
+                    private_key: &scalar,
+                    parameters: Some(EcParameters::NamedCurve(curve.oid())),
+                    public_key: Some(&public_point),
+                };
+                Ok(PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
+                    private_key.to_der()?,
+                )))
+            }
+            "OKP" => {
+                let crv = self.required("crv", &self.crv)?;
+                let seed = self.decode("d", &self.d)?;
+                let public_key = self.decode("x", &self.x)?;
+                let key = EdPrivateKey {
+                    seed: &seed,
+                    public_key: None,
+                };
+                let (oid, derived) = match crv.as_str() {
+                    "Ed25519" => (ID_ED_25519, key.ed25519_public_key()),
+                    "Ed448" => (ID_ED_448, key.ed448_public_key()),
+                    _ => return Err(JwkError::UnsupportedCurve(crv)),
+                };
+                if derived.ok_or(JwkError::InvalidKey)? != public_key {
+                    return Err(JwkError::KeyMismatch);
+                }
+                let private_key = OctetStringRef::new(&seed)?.to_der()?;
+                let private_key_info = PrivateKeyInfo {
+                    algorithm: AlgorithmIdentifierRef {
+                        oid,
+                        parameters: None,
+                    },
+                    private_key: &private_key,
+                    // Derivable, a PKCS#8 v1 encoding is the most widely accepted.
+                    public_key: None,
+                };
+                // Validates the seed length.
+                GenericPrivateKey::form_private_key_info(private_key_info.clone())?;
+                Ok(PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+                    private_key_info.to_der()?,
+                )))
+            }
+            kty => Err(JwkError::UnsupportedKeyType(kty.to_string())),
+        }
+    }
+}
+
+impl JwkSet {
+    /// The public keys of the identities, an identity whose key can't be converted is skipped.
+    /// Identities sharing a key and certificate are published once, a key served with several
+    /// certificates is published once per certificate under the same `kid`.
+    pub fn from_identities(identities: &Identities) -> Self {
+        let mut published = HashSet::new();
+        let mut keys = Vec::new();
+        for identity in identities.iter() {
+            match Jwk::from_identity(identity) {
+                Ok(jwk) => {
+                    if published.insert((jwk.kid.clone(), jwk.x5t_s256.clone())) {
+                        keys.push(jwk);
+                    }
+                }
+                Err(err) => tracing::warn!(
+                    "Failed to convert the key of {} to a JWK: {}",
+                    identity.certificate.subject(),
+                    err
+                ),
+            }
+        }
+        Self { keys }
+    }
+
+    pub fn to_json(&self) -> Result<String, JwkError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JwkDocument {
+    Set(JwkSet),
+    Key(Box<Jwk>),
+}
+
+/// The `x5c` chain and private key of a JWK, nothing is loaded when either is invalid.
+fn load_jwk(
+    key: &Jwk,
+) -> Result<(Vec<CertificateDer<'static>>, Option<PrivateKeyDer<'static>>), JwkError> {
+    let certificates = key.certificates()?;
+    if !key.is_private() {
+        if certificates.is_empty() {
+            tracing::warn!(
+                "Skipping public JWK {} without x5c, there is no certificate to load",
+                key.kid.as_deref().unwrap_or(&key.kty)
+            );
+        }
+        return Ok((certificates, None));
+    }
+    Ok((certificates, Some(key.to_private_key_der()?)))
+}
+
+/// Parses a JWK or JWK Set into `source`. Private keys go to `pkc1`, `sec1` or `pkcs8`
+/// and `x5c` chains to `x509`. Keys of unsupported types are skipped, and so is any key of a
+/// JWK Set that can't be loaded, e.g. an X25519 key or an RSA key without CRT parameters.
+pub fn parse_jwk(source: &mut ParsedPkiData, json: &[u8]) -> Result<(), JwkError> {
+    let (keys, is_set) = match serde_json::from_slice(json)? {
+        JwkDocument::Set(set) => (set.keys, true),
+        JwkDocument::Key(key) => (vec![*key], false),
+    };
+    for key in keys {
+        let (mut certificates, private_key) = match load_jwk(&key) {
+            Ok(loaded) => loaded,
+            Err(JwkError::UnsupportedKeyType(kty)) => {
+                tracing::warn!("Skipping JWK of unsupported key type {}", kty);
+                continue;
+            }
+            Err(err) if is_set => {
+                tracing::warn!(
+                    "Skipping JWK {}: {}",
+                    key.kid.as_deref().unwrap_or(&key.kty),
+                    err
+                );
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        source.x509.append(&mut certificates);
+        match private_key {
+            Some(PrivateKeyDer::Pkcs1(der)) => source.pkc1.push(der),
+            Some(PrivateKeyDer::Sec1(der)) => source.sec1.push(der),
+            Some(PrivateKeyDer::Pkcs8(der)) => source.pkcs8.push(der),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Publishes the public keys of the current identities as a JWKS, e.g. for an API gateway.
+/// Call [`JwksPublisher::update`] on every reload, subscribers are notified when the set changes.
+pub struct JwksPublisher {
+    sender: watch::Sender<Arc<JwkSet>>,
+}
+
+impl Default for JwksPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JwksPublisher {
+    pub fn new() -> Self {
+        Self {
+            sender: watch::Sender::new(Default::default()),
+        }
+    }
+
+    /// Replaces the published set, returns whether it changed.
+    pub fn update(&self, identities: &Identities) -> bool {
+        let jwk_set = JwkSet::from_identities(identities);
+        self.sender.send_if_modified(|current| {
+            if **current == jwk_set {
+                return false;
+            }
+            *current = Arc::new(jwk_set);
+            true
+        })
+    }
+
+    pub fn current(&self) -> Arc<JwkSet> {
+        self.sender.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<JwkSet>> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rustls_pki_types::PrivateKeyDer;
+    use x509_parser::prelude::{FromDer, X509Certificate};
+
+    use crate::generic_private_key::GenericPrivateKey;
+    use crate::jwk::{parse_jwk, Jwk, JwkSet, JwksPublisher};
+    use crate::parser::parse::{Identities, PkiParser};
+    use crate::parser::IdentityParser;
+    use crate::ParsedPkiData;
+
+    fn parse(files: &[&[u8]]) -> ParsedPkiData<'static> {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new().with_common_name_fallback(true);
+        for file in files {
+            pki_parser
+                .parse_pem(&mut parsed_pki_data, Cursor::new(*file))
+                .unwrap();
+        }
+        parsed_pki_data
+    }
+
+    fn assert_round_trip(key: &PrivateKeyDer, certificate: &[u8]) {
+        let jwk =
+            Jwk::from_private_key(&GenericPrivateKey::from_private_key_der(key).unwrap()).unwrap();
+        let json = serde_json::to_string(&jwk).unwrap();
+        let imported = serde_json::from_str::<Jwk>(&json)
+            .unwrap()
+            .to_private_key_der()
+            .unwrap();
+        let (_, certificate) = X509Certificate::from_der(certificate).unwrap();
+        assert!(GenericPrivateKey::from_private_key_der(&imported)
+            .unwrap()
+            .matches_public_key(certificate.public_key()));
+        assert_eq!(
+            Jwk::from_public_key(certificate.public_key()).unwrap(),
+            jwk.to_public()
+        );
+    }
+
+    #[test]
+    fn test_private_key_round_trip() {
+        let rsa = parse(&[
+            include_bytes!("../tests/data/cert.pem"),
+            include_bytes!("../tests/data/key.pem"),
+        ]);
+        assert_round_trip(
+            &PrivateKeyDer::Pkcs8(rsa.pkcs8[0].clone_key()),
+            &rsa.x509[0],
+        );
+
+        let ec = parse(&[
+            include_bytes!("../tests/data/ec-p256.pem"),
+            include_bytes!("../tests/data/ec-p256.key"),
+        ]);
+        assert_round_trip(&PrivateKeyDer::Sec1(ec.sec1[0].clone_key()), &ec.x509[0]);
+
+        let ed25519 = parse(&[
+            include_bytes!("../tests/data/ed25519.pem"),
+            include_bytes!("../tests/data/ed25519.key"),
+        ]);
+        assert_round_trip(
+            &PrivateKeyDer::Pkcs8(ed25519.pkcs8[0].clone_key()),
+            &ed25519.x509[0],
+        );
+    }
+
+    #[test]
+    fn test_jwks_publisher() {
+        let parsed_pki_data = parse(&[
+            include_bytes!("../tests/data/ec-p256.pem"),
+            include_bytes!("../tests/data/ec-p256.key"),
+        ]);
+        let pki_parser = PkiParser::new();
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        let publisher = JwksPublisher::new();
+        let receiver = publisher.subscribe();
+        assert!(publisher.update(&identities));
+        assert!(!publisher.update(&identities));
+        assert!(receiver.has_changed().unwrap());
+
+        let jwk_set = publisher.current();
+        assert_eq!(jwk_set.keys.len(), 1);
+        let jwk = &jwk_set.keys[0];
+        assert_eq!(jwk.alg.as_deref(), Some("ES256"));
+        assert_eq!(jwk.kid, Some(jwk.thumbprint().unwrap()));
+        assert_eq!(jwk.x5c.len(), 1);
+        assert!(!jwk.is_private());
+
+        // A published set imports its certificates back.
+        let mut imported = ParsedPkiData::default();
+        parse_jwk(&mut imported, jwk_set.to_json().unwrap().as_bytes()).unwrap();
+        assert_eq!(imported.x509, parsed_pki_data.x509);
+    }
+
+    #[test]
+    fn test_parse_jwk_set_skips_invalid_keys() {
+        let ec = parse(&[
+            include_bytes!("../tests/data/ec-p256.pem"),
+            include_bytes!("../tests/data/ec-p256.key"),
+        ]);
+        let key = PrivateKeyDer::Sec1(ec.sec1[0].clone_key());
+        let key = GenericPrivateKey::from_private_key_der(&key).unwrap();
+        let valid = serde_json::to_string(&Jwk::from_private_key(&key).unwrap()).unwrap();
+        // RFC 8037 A.6, and an RSA key without the CRT parameters.
+        let x25519 = r#"{"kty":"OKP","crv":"X25519","d":"dwdtCnMYpX08FsFyUbJmRd9ML4frwJkqsXf7pR25LCo","x":"hSDwCYkwp1R0i33ctD73Wg2_Og0mOBr066SpjqqbTmo"}"#;
+        let rsa = r#"{"kty":"RSA","n":"AQAB","e":"AQAB","d":"AQAB"}"#;
+        let public = r#"{"kty":"EC","crv":"P-256","x":"AQ","y":"AQ","kid":"public"}"#;
+        let json = format!(r#"{{"keys":[{},{},{},{}]}}"#, x25519, rsa, public, valid);
+
+        let mut parsed_pki_data = ParsedPkiData::default();
+        parse_jwk(&mut parsed_pki_data, json.as_bytes()).unwrap();
+        assert_eq!(parsed_pki_data.sec1.len(), 1);
+        assert!(parsed_pki_data.x509.is_empty());
+
+        // A single JWK is all the document holds, it fails as a whole.
+        let mut parsed_pki_data = ParsedPkiData::default();
+        assert!(parse_jwk(&mut parsed_pki_data, x25519.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_jwk_set_keeps_every_certificate_of_a_key() {
+        let parsed_pki_data = parse(&[
+            include_bytes!("../tests/data/ec-p256.pem"),
+            include_bytes!("../tests/data/ec-p256-renewed.pem"),
+            include_bytes!("../tests/data/ec-p256.key"),
+        ]);
+        let pki_parser = PkiParser::new();
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        assert_eq!(identities.len(), 2);
+        let jwk_set = JwkSet::from_identities(&identities);
+        assert_eq!(jwk_set.keys.len(), 2);
+        assert_eq!(jwk_set.keys[0].kid, jwk_set.keys[1].kid);
+        assert_ne!(jwk_set.keys[0].x5c, jwk_set.keys[1].x5c);
+    }
+
+    #[test]
+    fn test_thumbprint() {
+        // RFC 7638 3.1
+        let jwk: Jwk = serde_json::from_str(
+            r#"{"kty":"RSA","n":"0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw","e":"AQAB","alg":"RS256","kid":"2011-04-29"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            jwk.thumbprint().unwrap(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+}
@@ -7,16 +7,24 @@ k8s_openapi::k8s_if_le_1_26! {
 }
 
 use crate::identity::Identity;
+use crate::parser::pem::PemBlock;
+use crate::parser::pkcs12::{Pkcs12Attributes, Pkcs12Item};
+use crate::provenance::{ItemCounts, PkiItem, Provenance};
 use rustls_pki_types::{
-    CertificateDer, CertificateRevocationListDer, CertificateSigningRequestDer, PrivateKeyDer,
-    PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer, ServerName,
+    CertificateDer, CertificateRevocationListDer, CertificateSigningRequestDer, PrivatePkcs1KeyDer,
+    PrivatePkcs8KeyDer, PrivateSec1KeyDer,
 };
-use x509_parser::certificate::X509Certificate;
 
 pub mod configuration;
+mod ed448;
+pub mod fetch;
 pub mod generic_private_key;
 pub mod identity;
+pub mod jwk;
 pub mod parser;
+pub mod passphrase;
+pub mod provenance;
+pub mod resolver;
 pub mod store;
 pub mod validate;
 // Kubernetes cert-manager ask Let's Encrypt for pki for website domain.
@@ -32,7 +40,7 @@ pub trait IdentityValidator {
     fn is_valid(&self, identity: Identity) -> bool;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ParsedPkiData<'a> {
     pub x509: Vec<CertificateDer<'a>>,
     pub pkc1: Vec<PrivatePkcs1KeyDer<'a>>,
@@ -40,6 +48,17 @@ pub struct ParsedPkiData<'a> {
     pub crls: Vec<CertificateRevocationListDer<'a>>,
     pub csrs: Vec<CertificateSigningRequestDer<'a>>,
     pub pkcs8: Vec<PrivatePkcs8KeyDer<'a>>,
+    /// Bag attributes of items loaded from PKCS#12 archives.
+    pub pkcs12_attributes: Vec<Pkcs12Attributes>,
+    /// OpenSSH private keys (`openssh-key-v1`), decrypted.
+    pub ssh_private_keys: Vec<ssh_key::PrivateKey>,
+    /// OpenSSH user and host certificates.
+    pub ssh_certificates: Vec<ssh_key::Certificate>,
+    /// PEM blocks of unknown kinds, e.g. `DH PARAMETERS`, kept by the lenient parser.
+    /// `index` and `offset` are relative to the input the block was read from.
+    pub unknown: Vec<PemBlock>,
+    /// Where each item came from, recorded by the parser.
+    pub provenance: Vec<(PkiItem, Provenance)>,
 }
 
 impl<'a> ParsedPkiData<'a> {
@@ -48,24 +67,33 @@ impl<'a> ParsedPkiData<'a> {
         'a: 'b,
         'b: 'a,
     {
+        let counts = ItemCounts::of(self);
+        for (item, _) in &mut other.provenance {
+            *item = item.offset_by(&counts);
+        }
+        self.provenance.append(&mut other.provenance);
+        for attributes in &mut other.pkcs12_attributes {
+            attributes.item = match attributes.item {
+                Pkcs12Item::Certificate(index) => Pkcs12Item::Certificate(index + self.x509.len()),
+                Pkcs12Item::PrivateKey(index) => Pkcs12Item::PrivateKey(index + self.pkcs8.len()),
+            };
+        }
+        self.pkcs12_attributes.append(&mut other.pkcs12_attributes);
         self.crls.append(&mut other.crls);
         self.csrs.append(&mut other.csrs);
         self.x509.append(&mut other.x509);
         self.sec1.append(&mut other.sec1);
         self.pkc1.append(&mut other.pkc1);
         self.pkcs8.append(&mut other.pkcs8);
+        self.ssh_private_keys.append(&mut other.ssh_private_keys);
+        self.ssh_certificates.append(&mut other.ssh_certificates);
+        self.unknown.append(&mut other.unknown);
     }
-}
 
-impl Default for ParsedPkiData<'_> {
-    fn default() -> Self {
-        Self {
-            x509: vec![],
-            pkc1: vec![],
-            sec1: vec![],
-            crls: vec![],
-            csrs: vec![],
-            pkcs8: vec![],
-        }
+    pub fn provenance_of(&self, item: PkiItem) -> Option<&Provenance> {
+        self.provenance
+            .iter()
+            .find(|(x, _)| *x == item)
+            .map(|(_, provenance)| provenance)
     }
 }
@@ -0,0 +1,145 @@
+use aes::{Aes128, Aes192, Aes256};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockCipher, BlockDecryptMut, KeyInit, KeyIvInit};
+use der::Decode;
+use des::TdesEde3;
+use md5::{Digest, Md5};
+use pkcs8::EncryptedPrivateKeyInfo;
+use zeroize::Zeroizing;
+
+use crate::parser::pem::PemBlock;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DecryptPrivateKeyError {
+    #[error("MissingPassphrase")]
+    MissingPassphrase,
+    #[error("UnsupportedEncryptionAlgorithm {0}")]
+    UnsupportedEncryptionAlgorithm(String),
+    #[error("InvalidDekInfo")]
+    InvalidDekInfo,
+    /// Wrong passphrase or corrupted key, the two can not be told apart.
+    #[error("DecryptionFailed")]
+    DecryptionFailed,
+    #[error(transparent)]
+    DerError(#[from] der::Error),
+}
+
+/// Decrypts a PKCS#8 `ENCRYPTED PRIVATE KEY` (PBES2, RFC 8018) into a PKCS#8 `PRIVATE KEY`.
+pub fn decrypt_pkcs8(
+    der: &[u8],
+    passphrase: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, DecryptPrivateKeyError> {
+    let encrypted = EncryptedPrivateKeyInfo::from_der(der)?;
+    let document = encrypted
+        .decrypt(passphrase)
+        .map_err(|_| DecryptPrivateKeyError::DecryptionFailed)?;
+    Ok(Zeroizing::new(document.as_bytes().to_vec()))
+}
+
+/// Checks for the RFC 1421 `Proc-Type: 4,ENCRYPTED` header of legacy OpenSSL keys.
+pub fn is_legacy_encrypted(block: &PemBlock) -> bool {
+    block
+        .header("Proc-Type")
+        .map_or(false, |value| value.replace(' ', "") == "4,ENCRYPTED")
+}
+
+/// Decrypts a legacy OpenSSL encrypted PEM key (`DEK-Info` header), returning the DER of the inner key.
+pub fn decrypt_legacy_pem(
+    block: &PemBlock,
+    passphrase: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, DecryptPrivateKeyError> {
+    let (algorithm, iv) = block
+        .header("DEK-Info")
+        .and_then(|value| value.split_once(','))
+        .ok_or(DecryptPrivateKeyError::InvalidDekInfo)?;
+    let iv = const_hex::decode(iv.trim()).map_err(|_| DecryptPrivateKeyError::InvalidDekInfo)?;
+    if iv.len() < 8 {
+        return Err(DecryptPrivateKeyError::InvalidDekInfo);
+    }
+    let key_length = match algorithm.trim() {
+        "AES-128-CBC" => 16,
+        "AES-192-CBC" => 24,
+        "AES-256-CBC" => 32,
+        "DES-EDE3-CBC" => 24,
+        other => {
+            return Err(DecryptPrivateKeyError::UnsupportedEncryptionAlgorithm(
+                other.to_string(),
+            ))
+        }
+    };
+    let key = evp_bytes_to_key(passphrase, &iv[..8], key_length);
+    let decrypted = match algorithm.trim() {
+        "AES-128-CBC" => decrypt_cbc::<Aes128>(&key, &iv, &block.contents),
+        "AES-192-CBC" => decrypt_cbc::<Aes192>(&key, &iv, &block.contents),
+        "AES-256-CBC" => decrypt_cbc::<Aes256>(&key, &iv, &block.contents),
+        _ => decrypt_cbc::<TdesEde3>(&key, &iv, &block.contents),
+    }?;
+    Ok(decrypted)
+}
+
+fn decrypt_cbc<C>(
+    key: &[u8],
+    iv: &[u8],
+    data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, DecryptPrivateKeyError>
+where
+    C: BlockDecryptMut + BlockCipher + KeyInit,
+{
+    let decryptor = cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| DecryptPrivateKeyError::InvalidDekInfo)?;
+    decryptor
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map(Zeroizing::new)
+        .map_err(|_| DecryptPrivateKeyError::DecryptionFailed)
+}
+
+/// OpenSSL `EVP_BytesToKey` with MD5 and a single iteration, as used by legacy PEM encryption.
+fn evp_bytes_to_key(passphrase: &[u8], salt: &[u8], key_length: usize) -> Zeroizing<Vec<u8>> {
+    let mut key = Zeroizing::new(Vec::with_capacity(key_length + 16));
+    let mut previous: Option<Vec<u8>> = None;
+    while key.len() < key_length {
+        let mut hasher = Md5::new();
+        if let Some(previous) = &previous {
+            hasher.update(previous);
+        }
+        hasher.update(passphrase);
+        hasher.update(salt);
+        let digest = hasher.finalize().to_vec();
+        key.extend_from_slice(&digest);
+        previous = Some(digest);
+    }
+    key.truncate(key_length);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use der::Decode;
+
+    use crate::parser::encrypted::{
+        decrypt_legacy_pem, decrypt_pkcs8, is_legacy_encrypted, DecryptPrivateKeyError,
+    };
+    use crate::parser::pem::read_pem_blocks;
+
+    #[test]
+    fn test_decrypt_legacy_pem() {
+        let blocks = read_pem_blocks(include_bytes!("../../tests/data/rsa-encrypted.pem")).unwrap();
+        assert!(is_legacy_encrypted(&blocks[0]));
+        let der = decrypt_legacy_pem(&blocks[0], b"mypass").unwrap();
+        assert!(pkcs1::RsaPrivateKey::from_der(&der).is_ok());
+        assert!(matches!(
+            decrypt_legacy_pem(&blocks[0], b"wrong"),
+            Err(DecryptPrivateKeyError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_pkcs8() {
+        let der = decrypt_pkcs8(include_bytes!("../../tests/data/pkcs8.der"), b"mypass").unwrap();
+        assert!(pkcs8::PrivateKeyInfo::from_der(&der).is_ok());
+        assert!(matches!(
+            decrypt_pkcs8(include_bytes!("../../tests/data/pkcs8.der"), b"wrong"),
+            Err(DecryptPrivateKeyError::DecryptionFailed)
+        ));
+    }
+}
@@ -11,24 +11,24 @@ use x509_parser::prelude::X509Certificate;
 use crate::parser::parse::Identities;
 use crate::ParsedPkiData;
 
+pub mod encrypted;
+pub mod openssh;
 pub mod parse;
+pub mod pem;
+pub mod pkcs12;
+pub mod pkcs7;
+pub mod sniff;
 
 #[async_trait]
 pub trait PemParser {
     type Error;
     fn parse_pem(&mut self, reader: impl BufRead) -> Result<ParsedPkiData, Self::Error>;
 
-    fn parse_pkcs1_der<'a>(
-        &self,
-        der: PrivatePkcs1KeyDer<'a>,
-    ) -> Result<RsaPrivateKey, Self::Error>;
+    fn parse_pkcs1_der(&self, der: PrivatePkcs1KeyDer<'_>) -> Result<RsaPrivateKey, Self::Error>;
 
-    fn parse_sec1_der<'a>(&self, der: PrivateSec1KeyDer<'a>) -> Result<EcPrivateKey, Self::Error>;
+    fn parse_sec1_der(&self, der: PrivateSec1KeyDer<'_>) -> Result<EcPrivateKey, Self::Error>;
 
-    fn parse_pkcs8_der<'a>(
-        &self,
-        der: PrivatePkcs8KeyDer<'a>,
-    ) -> Result<PrivateKeyInfo, Self::Error>;
+    fn parse_pkcs8_der(&self, der: PrivatePkcs8KeyDer<'_>) -> Result<PrivateKeyInfo, Self::Error>;
 
     fn parse_x509_der<'a>(
         x509_parser: &'a mut X509CertificateParser,
@@ -39,26 +39,26 @@ pub trait PemParser {
 pub trait IdentityParser {
     type Error;
 
-    // Returns a list of intermediate certificates, from the po
-    fn intermediate_certificates(
+    /// Returns the intermediate certificates between `source` and its CA, ordered from the issuer of `source` upwards.
+    fn intermediate_certificates<'a>(
         &self,
-        source: &X509Certificate,
-        potential_intermediate_certificates: &Vec<X509Certificate>,
-    ) -> Result<Vec<X509Certificate>, Self::Error>;
-    // Returns the CA certificate.
-    fn ca_certificate(
+        source: &X509Certificate<'a>,
+        potential_intermediate_certificates: &[X509Certificate<'a>],
+    ) -> Result<Vec<X509Certificate<'a>>, Self::Error>;
+    /// Returns the CA certificate, if it is part of the potential CA certificates.
+    fn ca_certificate<'a>(
         &self,
-        source: &X509Certificate,
-        potential_ca_certificate: &Vec<X509Certificate>,
-    ) -> Result<X509Certificate, Self::Error>;
+        source: &X509Certificate<'a>,
+        potential_ca_certificate: &[X509Certificate<'a>],
+    ) -> Result<Option<X509Certificate<'a>>, Self::Error>;
 
-    // Returns a list of intermediate certificates and ca certificate.
-    fn certificate_chain(
+    /// Returns a list of intermediate certificates and ca certificate.
+    fn certificate_chain<'a>(
         &self,
-        source: &X509Certificate,
-        potential_intermediate_certificates: &Vec<CertificateDer>,
-        potential_ca_certificate: &Vec<CertificateDer>,
-    ) -> Result<(Vec<X509Certificate>, X509Certificate), Self::Error>;
+        source: &X509Certificate<'a>,
+        potential_intermediate_certificates: &[X509Certificate<'a>],
+        potential_ca_certificate: &[X509Certificate<'a>],
+    ) -> Result<(Vec<X509Certificate<'a>>, Option<X509Certificate<'a>>), Self::Error>;
 
     /// The name is the Domain or Ip Address of the certificate.
     fn parse_identity<'a>(
@@ -72,21 +72,175 @@ pub trait IdentityParser {
 mod tests {
     use std::io::Cursor;
 
-    use crate::configuration::FilePkiStoreConfiguration;
-    use crate::parser::parse::{Identities, PkiParser};
+    use rustls_pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+    use x509_parser::pem::{parse_x509_pem, Pem};
+
+    use crate::identity::{Identity, KeyAlgorithm};
+    use crate::parser::parse::{Identities, PemParseError, PkiParser, UnknownItemPolicy};
     use crate::parser::IdentityParser;
+    use crate::validate::hostname::SubjectName;
+    use crate::validate::validate::validate_signature;
     use crate::ParsedPkiData;
 
-    struct TestConfig {
-        pub file_path: String,
-        pub watch_file: bool,
+    #[test]
+    fn test_identities_lookup_by_subject_name() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../../tests/data/cert.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        let identity = |names: &[&str]| Identity {
+            names: names
+                .iter()
+                .map(|name| SubjectName::from_dns(name).unwrap())
+                .collect(),
+            certificate: certificate.clone(),
+            certificate_der: CertificateDer::from(pem.contents.as_slice()),
+            private_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(vec![])),
+            intermediate: vec![],
+            intermediate_der: vec![],
+            ca_certificate: None,
+            ca_certificate_der: None,
+            certificate_provenance: None,
+            private_key_provenance: None,
+        };
+        let mut identities = Identities::new(vec![identity(&["*.example.com", "example.com"])]);
+        identities.push(identity(&["www.example.com"]));
+
+        let www = ServerName::try_from("www.example.com").unwrap();
+        let api = ServerName::try_from("api.example.com").unwrap();
+        let nested = ServerName::try_from("a.api.example.com").unwrap();
+        assert_eq!(identities.get_identities(&www).len(), 2);
+        assert_eq!(
+            identities.get_identity(&www).unwrap().names[0],
+            SubjectName::from_dns("www.example.com").unwrap()
+        );
+        assert_eq!(identities.get_identity(&api).unwrap().names.len(), 2);
+        assert!(identities.get_identity(&nested).is_none());
+
+        identities.remove(0);
+        assert!(identities.get_identity(&api).is_none());
+        assert!(identities.get_identity(&www).is_some());
     }
-    impl FilePkiStoreConfiguration for TestConfig {
-        fn get_file_path(&self) -> String {
-            self.file_path.clone()
+
+    #[test]
+    fn test_identities_prefer_latest_not_after() {
+        let (_, early) =
+            parse_x509_pem(include_bytes!("../../tests/data/renewed-early.pem")).unwrap();
+        let (_, late) =
+            parse_x509_pem(include_bytes!("../../tests/data/renewed-late.pem")).unwrap();
+        fn identity<'a>(certificate: &'a Pem) -> Identity<'a> {
+            Identity {
+                names: vec![SubjectName::from_dns("renewed.foobar.com").unwrap()],
+                certificate: certificate.parse_x509().unwrap(),
+                certificate_der: CertificateDer::from(certificate.contents.as_slice()),
+                private_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(vec![])),
+                intermediate: vec![],
+                intermediate_der: vec![],
+                ca_certificate: None,
+                ca_certificate_der: None,
+                certificate_provenance: None,
+                private_key_provenance: None,
+            }
+        }
+        let name = ServerName::try_from("renewed.foobar.com").unwrap();
+        for order in [[&early, &late], [&late, &early]] {
+            let identities = Identities::new(order.into_iter().map(identity).collect());
+            assert_eq!(
+                identities
+                    .get_identity(&name)
+                    .unwrap()
+                    .certificate
+                    .validity()
+                    .not_after,
+                late.parse_x509().unwrap().validity().not_after
+            );
         }
     }
 
+    #[test]
+    fn test_parse_identity_pkcs8() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new().with_common_name_fallback(true);
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/cert.pem")),
+            )
+            .unwrap();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/key.pem")),
+            )
+            .unwrap();
+        assert_eq!(parsed_pki_data.pkcs8.len(), 1);
+
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        assert_eq!(identities.len(), 1);
+        let foobar = ServerName::try_from("foobar.com").unwrap();
+        let identity = identities.get_identity(&foobar).unwrap();
+        // The identity keeps the certificate as it was loaded.
+        assert_eq!(identity.certificate_der, parsed_pki_data.x509[0]);
+    }
+
+    #[test]
+    fn test_parse_identity_ed25519() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ed25519.pem")),
+            )
+            .unwrap();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ed25519.key")),
+            )
+            .unwrap();
+
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        let name = ServerName::try_from("ed25519.foobar.com").unwrap();
+        let identity = identities.get_identity(&name).unwrap();
+        assert_eq!(identity.key_algorithm(), KeyAlgorithm::Ed25519);
+        assert!(validate_signature(
+            &identity.certificate,
+            &identity.private_key
+        ));
+    }
+
+    #[test]
+    fn test_parse_identity_sec1_without_public_key() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ec-p256.pem")),
+            )
+            .unwrap();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ec-p256.key")),
+            )
+            .unwrap();
+        assert_eq!(parsed_pki_data.sec1.len(), 1);
+
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        let name = ServerName::try_from("ec.foobar.com").unwrap();
+        let identity = identities.get_identity(&name).unwrap();
+        assert_eq!(identity.key_algorithm(), KeyAlgorithm::EcdsaP256);
+    }
+
     #[test]
     fn test_parsing_sec() {
         let mut parsed_pki_data = ParsedPkiData::default();
@@ -106,8 +260,33 @@ mod tests {
         // TODO: Fix the test, currently no private keys are included, resulting in zero identities being parsed.
         let mut identities = Identities::default();
         pki_parser
-            .parse_identity(&mut parsed_pki_data, &mut identities)
+            .parse_identity(&parsed_pki_data, &mut identities)
             .unwrap();
         println!("{:?}", identities);
     }
+
+    #[test]
+    fn test_parse_unknown_pem_item() {
+        let mut bundle = include_bytes!("../../tests/data/cert.pem").to_vec();
+        bundle.extend_from_slice(include_bytes!("../../tests/data/dhparams.pem"));
+
+        let mut parsed_pki_data = ParsedPkiData::default();
+        PkiParser::new()
+            .with_unknown_item_policy(UnknownItemPolicy::Lenient)
+            .parse_pem(&mut parsed_pki_data, Cursor::new(&bundle))
+            .unwrap();
+        assert_eq!(parsed_pki_data.x509.len(), 1);
+        assert_eq!(parsed_pki_data.unknown.len(), 1);
+        assert_eq!(parsed_pki_data.unknown[0].label, "DH PARAMETERS");
+        assert_eq!(parsed_pki_data.unknown[0].index, 1);
+
+        // Strict by default.
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let result = PkiParser::new().parse_pem(&mut parsed_pki_data, Cursor::new(&bundle));
+        assert!(matches!(
+            result,
+            Err(PemParseError::UnknownPemItem { index: 1, offset, .. })
+                if offset == include_bytes!("../../tests/data/cert.pem").len()
+        ));
+    }
 }
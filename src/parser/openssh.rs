@@ -0,0 +1,113 @@
+use ssh_key::{Certificate, PrivateKey};
+
+use crate::ParsedPkiData;
+
+const CERTIFICATE_ALGORITHM_SUFFIX: &str = "-cert-v01@openssh.com";
+
+#[derive(thiserror::Error, Debug)]
+pub enum OpenSshParseError {
+    #[error(transparent)]
+    SshKeyError(#[from] ssh_key::Error),
+    #[error("MissingPassphrase")]
+    MissingPassphrase,
+    #[error("NonUtf8Certificate")]
+    NonUtf8Certificate,
+}
+
+/// Checks if the input starts with an OpenSSH certificate line, e.g. `ssh-ed25519-cert-v01@openssh.com AAAA...`.
+pub fn is_openssh_certificate(data: &[u8]) -> bool {
+    let algorithm = data
+        .split(|b| b.is_ascii_whitespace())
+        .find(|token| !token.is_empty())
+        .unwrap_or_default();
+    algorithm.ends_with(CERTIFICATE_ALGORITHM_SUFFIX.as_bytes())
+}
+
+/// Parses the contents of an `OPENSSH PRIVATE KEY` PEM block, encrypted keys are decrypted with the passphrase.
+pub fn parse_openssh_private_key(
+    source: &mut ParsedPkiData,
+    contents: &[u8],
+    passphrase: Option<&[u8]>,
+) -> Result<(), OpenSshParseError> {
+    let mut private_key = PrivateKey::from_bytes(contents)?;
+    if private_key.is_encrypted() {
+        let passphrase = passphrase.ok_or(OpenSshParseError::MissingPassphrase)?;
+        private_key = private_key.decrypt(passphrase)?;
+    }
+    source.ssh_private_keys.push(private_key);
+    Ok(())
+}
+
+/// Parses OpenSSH certificates (`*-cert.pub`), one per line. Empty lines and `#` comments are skipped.
+pub fn parse_openssh_certificates(
+    source: &mut ParsedPkiData,
+    data: &[u8],
+) -> Result<(), OpenSshParseError> {
+    let data = std::str::from_utf8(data).map_err(|_| OpenSshParseError::NonUtf8Certificate)?;
+    for line in data.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        source
+            .ssh_certificates
+            .push(Certificate::from_openssh(line)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::parser::openssh::is_openssh_certificate;
+    use crate::parser::parse::PkiParser;
+    use crate::passphrase::Passphrase;
+    use crate::ParsedPkiData;
+
+    #[test]
+    fn test_parse_openssh_private_keys() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ssh-host.key")),
+            )
+            .unwrap();
+        assert!(pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ssh-encrypted.key")),
+            )
+            .is_err());
+        pki_parser.set_passphrase(Some(Passphrase::new("mypass")));
+        pki_parser
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/ssh-encrypted.key")),
+            )
+            .unwrap();
+        assert_eq!(parsed_pki_data.ssh_private_keys.len(), 2);
+        assert!(parsed_pki_data
+            .ssh_private_keys
+            .iter()
+            .all(|key| !key.is_encrypted()));
+    }
+
+    #[test]
+    fn test_parse_openssh_certificate() {
+        let data = include_bytes!("../../tests/data/ssh-host-cert.pub");
+        assert!(is_openssh_certificate(data));
+        assert!(!is_openssh_certificate(include_bytes!(
+            "../../tests/data/ssh-ca.pub"
+        )));
+
+        let mut parsed_pki_data = ParsedPkiData::default();
+        PkiParser::new().parse(&mut parsed_pki_data, data).unwrap();
+        assert_eq!(parsed_pki_data.ssh_certificates.len(), 1);
+        assert_eq!(
+            parsed_pki_data.ssh_certificates[0].valid_principals(),
+            ["host.foobar.com", "foobar.com"]
+        );
+    }
+}
@@ -1,29 +1,44 @@
-use core::slice::SlicePattern;
 use std::collections::HashMap;
-use std::{
-    io::{BufRead, Cursor},
-    iter,
-};
+use std::io::{BufRead, Cursor};
 
-use der::{Decode, Encode};
+use der::Decode;
 use k8s_openapi::api::core::v1::Secret;
 use pkcs1::RsaPrivateKey;
-use rustls_pemfile::read_one;
+use pkcs8::PrivateKeyInfo;
+use rustls::SignatureScheme;
 use rustls_pki_types::{
-    CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivateSec1KeyDer, ServerName,
+    CertificateDer, CertificateRevocationListDer, CertificateSigningRequestDer, PrivateKeyDer,
+    PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer, ServerName,
 };
 use spiffe::svid::x509::X509Svid;
-use x509_parser::prelude::X509CertificateParser;
-use x509_parser::public_key::{PublicKey, RSAPublicKey};
-use x509_parser::{certificate::X509Certificate, error::X509Error, nom::Parser, x509::X509Version};
+use x509_parser::{certificate::X509Certificate, error::X509Error};
 
-use crate::parser::{IdentityParser, PemParser};
+use crate::generic_private_key::{GenericPrivateKey, ParsePkcs8Error};
+use crate::identity::select_by_algorithm;
+use crate::jwk::{parse_jwk, JwkError};
+use crate::parser::encrypted::{
+    decrypt_legacy_pem, decrypt_pkcs8, is_legacy_encrypted, DecryptPrivateKeyError,
+};
+use crate::parser::openssh::{
+    parse_openssh_certificates, parse_openssh_private_key, OpenSshParseError,
+};
+use crate::parser::pem::{read_pem_blocks, PemBlock, PemReadError};
+use crate::parser::pkcs12::{parse_pkcs12, Pkcs12ParseError};
+use crate::parser::pkcs7::{parse_pkcs7, Pkcs7ParseError};
+use crate::parser::sniff::{detect_format, DerKind, PkiFormat};
+use crate::parser::IdentityParser;
+use crate::passphrase::Passphrase;
+use crate::provenance::{ItemCounts, PkiItem, PkiSource, Provenance};
+use crate::validate::hostname::{certificate_subject_names, normalize_dns, SubjectName};
+use crate::validate::validate::is_issued_by;
 use crate::{Identity, ParsedPkiData};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ParseKubernetesPemSecreteError {
     #[error(transparent)]
     DecodePemError(#[from] PemParseError),
+    #[error(transparent)]
+    Pkcs12ParseError(#[from] Pkcs12ParseError),
     #[error("InvalidData")]
     InvalidData,
     #[error("InvalidKey")]
@@ -73,32 +88,95 @@ pub enum DecodePemError {
     FailedToReadDerEncodedItem,
 }
 
+/// How [`PkiParser::parse_pem`] handles PEM blocks of kinds it doesn't know.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownItemPolicy {
+    /// Keep them in `ParsedPkiData.unknown` and continue.
+    Lenient,
+    /// Fail with [`PemParseError::UnknownPemItem`], reporting the block's position.
+    #[default]
+    Strict,
+}
+
 #[derive(Clone, Debug)]
 pub struct PkiParser {
-    x509certificate_parser: X509CertificateParser,
+    allow_common_name_fallback: bool,
+    passphrase: Option<Passphrase>,
+    unknown_item_policy: UnknownItemPolicy,
+    source: Option<PkiSource>,
+}
+
+impl Default for PkiParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PkiParser {
     pub fn new() -> Self {
-        let x509certificate_parser = X509CertificateParser::new();
         Self {
-            x509certificate_parser,
+            allow_common_name_fallback: false,
+            passphrase: None,
+            unknown_item_policy: UnknownItemPolicy::default(),
+            source: None,
         }
     }
+
+    /// Derive the identity names from the subject common name when a certificate has no DNS or IP address Subject Alternative Name.
+    pub fn with_common_name_fallback(mut self, allow_common_name_fallback: bool) -> Self {
+        self.allow_common_name_fallback = allow_common_name_fallback;
+        self
+    }
+
+    pub fn with_unknown_item_policy(mut self, unknown_item_policy: UnknownItemPolicy) -> Self {
+        self.unknown_item_policy = unknown_item_policy;
+        self
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum PemParseError {
     #[error("UnsupportedX509Version")]
     UnsupportedX509Version,
-    #[error("UnknownDerEncodedItem")]
-    UnknownDerEncodedItem,
+    #[error("UnknownPemItem {label} at index {index}, offset {offset}")]
+    UnknownPemItem {
+        label: String,
+        index: usize,
+        offset: usize,
+    },
     #[error("FailedToReadDerEncodedItem")]
     FailedToReadDerEncodedItem,
     #[error("UnsupportedCertificateEncryptionScheme")]
     UnsupportedCertificateEncryptionScheme,
     #[error(transparent)]
     ParseX509Error(#[from] X509Error),
+    #[error(transparent)]
+    PemReadError(#[from] PemReadError),
+    #[error(transparent)]
+    DecryptPrivateKeyError(#[from] DecryptPrivateKeyError),
+    #[error(transparent)]
+    Pkcs7ParseError(#[from] Pkcs7ParseError),
+    #[error(transparent)]
+    OpenSshParseError(#[from] OpenSshParseError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+#[derive(thiserror::Error, Debug)]
+pub enum PkiParseError {
+    #[error("UnknownFormat")]
+    UnknownFormat,
+    #[error(transparent)]
+    PemParseError(#[from] PemParseError),
+    #[error(transparent)]
+    Pkcs12ParseError(#[from] Pkcs12ParseError),
+    #[error(transparent)]
+    Pkcs7ParseError(#[from] Pkcs7ParseError),
+    #[error(transparent)]
+    JwkError(#[from] JwkError),
+    #[error(transparent)]
+    OpenSshParseError(#[from] OpenSshParseError),
+    #[error(transparent)]
+    DecryptPrivateKeyError(#[from] DecryptPrivateKeyError),
 }
 #[derive(Debug, thiserror::Error)]
 pub enum SvidParsingError {
@@ -107,39 +185,176 @@ pub enum SvidParsingError {
 }
 //#[async_trait]
 impl PkiParser {
+    /// Sets the passphrase used for encrypted private keys, normally resolved by the store's [`PassphraseProvider`](crate::passphrase::PassphraseProvider) before parsing.
+    pub fn set_passphrase(&mut self, passphrase: Option<Passphrase>) {
+        self.passphrase = passphrase;
+    }
+
+    /// Sets the resource the following input is loaded from, recorded as the provenance of every parsed item.
+    pub fn set_source(&mut self, pki_source: Option<PkiSource>) {
+        self.source = pki_source;
+    }
+
+    /// Records the provenance of the items added since `since`, `position` is the PEM block index and offset.
+    fn record_provenance(
+        &self,
+        source: &mut ParsedPkiData,
+        since: ItemCounts,
+        position: Option<(usize, usize)>,
+    ) {
+        for item in since.added_items(source) {
+            source.provenance.push((
+                item,
+                Provenance {
+                    source: self.source.clone(),
+                    block_index: position.map(|(index, _)| index),
+                    offset: position.map(|(_, offset)| offset),
+                },
+            ));
+        }
+    }
+
+    /// Parses PEM, DER, PKCS#12, JWK or OpenSSH certificate input, the format is detected from the content.
+    pub fn parse(&mut self, source: &mut ParsedPkiData, data: &[u8]) -> Result<(), PkiParseError> {
+        let since = ItemCounts::of(source);
+        match detect_format(data).ok_or(PkiParseError::UnknownFormat)? {
+            PkiFormat::Pem => self.parse_pem(source, Cursor::new(data))?,
+            PkiFormat::Pkcs12 => self.parse_pkcs12(source, data)?,
+            PkiFormat::Der(kind) => self.parse_der(source, kind, data)?,
+            PkiFormat::Jwk => {
+                parse_jwk(source, data)?;
+                self.record_provenance(source, since, None);
+            }
+            PkiFormat::OpenSshCertificate => {
+                parse_openssh_certificates(source, data)?;
+                self.record_provenance(source, since, None);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parse_der(
+        &mut self,
+        source: &mut ParsedPkiData,
+        kind: DerKind,
+        der: &[u8],
+    ) -> Result<(), PkiParseError> {
+        let since = ItemCounts::of(source);
+        let der = der.to_vec();
+        match kind {
+            DerKind::Certificate => source.x509.push(CertificateDer::from(der)),
+            DerKind::Crl => source.crls.push(CertificateRevocationListDer::from(der)),
+            DerKind::Csr => source.csrs.push(CertificateSigningRequestDer::from(der)),
+            DerKind::Pkcs1 => source.pkc1.push(PrivatePkcs1KeyDer::from(der)),
+            DerKind::Pkcs8 => source.pkcs8.push(PrivatePkcs8KeyDer::from(der)),
+            DerKind::Sec1 => source.sec1.push(PrivateSec1KeyDer::from(der)),
+            DerKind::EncryptedPkcs8 => {
+                let decrypted = decrypt_pkcs8(&der, self.get_passphrase()?)?;
+                source
+                    .pkcs8
+                    .push(PrivatePkcs8KeyDer::from(decrypted.to_vec()));
+            }
+            DerKind::Pkcs7 => parse_pkcs7(source, &der)?,
+        }
+        self.record_provenance(source, since, None);
+        Ok(())
+    }
+
     pub fn parse_pem(
         &mut self,
         source: &mut ParsedPkiData,
         mut reader: impl BufRead,
     ) -> Result<(), PemParseError> {
-        let mut identities = Vec::<Identity>::new();
-        for item in iter::from_fn(|| read_one(&mut reader).transpose()) {
-            match item {
-                Ok(item) => match item {
-                    rustls_pemfile::Item::X509Certificate(x509) => {
-                        source.x509.push(x509);
-                    }
-                    rustls_pemfile::Item::Pkcs1Key(pkc1) => {
-                        source.pkc1.push(pkc1);
-                    }
-                    rustls_pemfile::Item::Pkcs8Key(pkcs8) => source.pkcs8.push(pkcs8),
-                    rustls_pemfile::Item::Sec1Key(sec1) => {
-                        source.sec1.push(sec1);
-                    }
-                    rustls_pemfile::Item::Crl(crl) => {
-                        source.crls.push(crl);
-                    }
-                    rustls_pemfile::Item::Csr(csr) => {
-                        source.csrs.push(csr);
-                    }
-                    _ => return Err(PemParseError::UnknownDerEncodedItem),
-                },
-                Err(_) => return Err(PemParseError::FailedToReadDerEncodedItem),
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        for block in read_pem_blocks(&data)? {
+            let since = ItemCounts::of(source);
+            let position = (block.index, block.offset);
+            self.parse_pem_block(source, block)?;
+            self.record_provenance(source, since, Some(position));
+        }
+        Ok(())
+    }
+
+    fn parse_pem_block(
+        &self,
+        source: &mut ParsedPkiData,
+        block: PemBlock,
+    ) -> Result<(), PemParseError> {
+        let contents = if is_legacy_encrypted(&block) {
+            decrypt_legacy_pem(&block, self.get_passphrase()?)?.to_vec()
+        } else {
+            block.contents
+        };
+        match block.label.as_str() {
+            "CERTIFICATE" | "X509 CERTIFICATE" => source.x509.push(CertificateDer::from(contents)),
+            "RSA PRIVATE KEY" => source.pkc1.push(PrivatePkcs1KeyDer::from(contents)),
+            "PRIVATE KEY" => source.pkcs8.push(PrivatePkcs8KeyDer::from(contents)),
+            "EC PRIVATE KEY" => source.sec1.push(PrivateSec1KeyDer::from(contents)),
+            "ENCRYPTED PRIVATE KEY" => {
+                let decrypted = decrypt_pkcs8(&contents, self.get_passphrase()?)?;
+                source
+                    .pkcs8
+                    .push(PrivatePkcs8KeyDer::from(decrypted.to_vec()));
             }
+            "X509 CRL" => source
+                .crls
+                .push(CertificateRevocationListDer::from(contents)),
+            "PKCS7" | "CMS" => parse_pkcs7(source, &contents)?,
+            "OPENSSH PRIVATE KEY" => parse_openssh_private_key(
+                source,
+                &contents,
+                self.passphrase
+                    .as_ref()
+                    .map(|passphrase| passphrase.as_bytes()),
+            )?,
+            "CERTIFICATE REQUEST" | "NEW CERTIFICATE REQUEST" => source
+                .csrs
+                .push(CertificateSigningRequestDer::from(contents)),
+            _ => match self.unknown_item_policy {
+                UnknownItemPolicy::Lenient => {
+                    tracing::debug!(
+                        "Keeping unknown PEM item {} at offset {}",
+                        block.label,
+                        block.offset
+                    );
+                    source.unknown.push(PemBlock { contents, ..block });
+                }
+                UnknownItemPolicy::Strict => {
+                    return Err(PemParseError::UnknownPemItem {
+                        label: block.label,
+                        index: block.index,
+                        offset: block.offset,
+                    })
+                }
+            },
         }
         Ok(())
     }
 
+    /// Parses a PKCS#12 / PFX archive, the passphrase defaults to the empty password.
+    pub fn parse_pkcs12(
+        &mut self,
+        source: &mut ParsedPkiData,
+        der: &[u8],
+    ) -> Result<(), Pkcs12ParseError> {
+        let password = self
+            .passphrase
+            .as_ref()
+            .map_or(&[][..], |passphrase| passphrase.as_bytes());
+        let since = ItemCounts::of(source);
+        parse_pkcs12(source, der, password)?;
+        self.record_provenance(source, since, None);
+        Ok(())
+    }
+
+    fn get_passphrase(&self) -> Result<&[u8], DecryptPrivateKeyError> {
+        self.passphrase
+            .as_ref()
+            .map(|passphrase| passphrase.as_bytes())
+            .ok_or(DecryptPrivateKeyError::MissingPassphrase)
+    }
+
     pub fn parse_x509_svid<'a, 'b: 'a>(
         &mut self,
         source: &mut Identities<'a>,
@@ -151,45 +366,50 @@ impl PkiParser {
             return Err(SvidParsingError::EmptyInput);
         }
 
-        let mut certificate: Option<X509Certificate> = None;
+        let mut certificate: Option<(X509Certificate, CertificateDer)> = None;
         let mut intermediate: Vec<X509Certificate> = vec![];
-        let mut ca_certificate: Option<X509Certificate> = None;
+        let mut intermediate_der: Vec<CertificateDer> = vec![];
+        let mut ca_certificate: Option<(X509Certificate, CertificateDer)> = None;
         for (index, cert) in cert_chain.iter().enumerate() {
-            let (bytes, x509) = X509Certificate::from_der(cert.content()).unwrap();
+            let (_, x509) = X509Certificate::from_der(cert.content()).unwrap();
+            let der = CertificateDer::from(cert.content());
             if index == 0 {
-                certificate = Some(x509.clone());
-            } else if index != cert_chain.len() {
-                ca_certificate = Some(x509.clone());
+                certificate = Some((x509, der));
+            } else if is_issued_by(&x509, &x509) {
+                ca_certificate = Some((x509, der));
             } else {
-                intermediate.push(x509.clone())
+                intermediate.push(x509);
+                intermediate_der.push(der);
             }
         }
+        let (certificate, certificate_der) = certificate.unwrap();
+        let (ca_certificate, ca_certificate_der) = ca_certificate.unzip();
 
         let prv = PrivateKeyDer::try_from(svid.private_key().content()).unwrap();
 
-        let server_name =
-            ServerName::try_from(certificate.clone().unwrap().subject.to_string()).unwrap();
+        // X509-SVIDs are identified by their SPIFFE ID URI, DNS names are optional.
+        let names = certificate_subject_names(&certificate, false);
+        let provenance = Provenance {
+            source: self.source.clone(),
+            block_index: None,
+            offset: None,
+        };
         let identity = Identity {
-            server_name,
-            certificate: certificate.unwrap(),
+            names,
+            certificate,
+            certificate_der,
             private_key: prv,
             intermediate,
-            ca_certificate: ca_certificate.unwrap(),
+            intermediate_der,
+            ca_certificate,
+            ca_certificate_der,
+            certificate_provenance: Some(provenance.clone()),
+            private_key_provenance: Some(provenance),
         };
-        source.inner.push(identity);
+        source.push(identity);
         Ok(())
     }
 }
-fn parse_x509_der<'a>(
-    x509_parser: &'a mut X509CertificateParser,
-    der: &'a CertificateDer<'a>,
-) -> Result<X509Certificate<'a>, PemParseError> {
-    let (raw, cert) = x509_parser.parse(der).unwrap();
-    if cert.tbs_certificate.version != X509Version::V3 {
-        return Err(PemParseError::UnsupportedX509Version);
-    }
-    Ok(cert)
-}
 #[derive(thiserror::Error, Debug)]
 pub enum IdentityParserError {
     #[error("UnsupportedCertificateEncryptionScheme")]
@@ -201,36 +421,44 @@ pub fn check_for_certificate_private_key() {}
 impl IdentityParser for PkiParser {
     type Error = IdentityParserError;
 
-    fn intermediate_certificates(
+    fn intermediate_certificates<'a>(
         &self,
-        source: &X509Certificate,
-        intermediate_certificates: &Vec<X509Certificate>,
-    ) -> Result<Vec<X509Certificate>, Self::Error> {
-        let mut target_intermidate_certificates = Vec::new();
-        for inter in intermediate_certificates {
-            if source == inter {
-                todo!();
-                target_intermidate_certificates.push(inter.clone());
-            }
+        source: &X509Certificate<'a>,
+        intermediate_certificates: &[X509Certificate<'a>],
+    ) -> Result<Vec<X509Certificate<'a>>, Self::Error> {
+        let mut target_intermediate_certificates: Vec<X509Certificate<'a>> = Vec::new();
+        let mut current = source.clone();
+        while let Some(issuer) = intermediate_certificates.iter().find(|inter| {
+            is_issued_by(&current, inter)
+                && !target_intermediate_certificates.iter().any(|x| x == *inter)
+        }) {
+            target_intermediate_certificates.push(issuer.clone());
+            current = issuer.clone();
         }
-        Ok(target_intermidate_certificates)
+        Ok(target_intermediate_certificates)
     }
 
-    fn ca_certificate(
+    fn ca_certificate<'a>(
         &self,
-        source: &X509Certificate,
-        potential_ca_certificate: &Vec<X509Certificate>,
-    ) -> Result<X509Certificate, Self::Error> {
-        todo!()
+        source: &X509Certificate<'a>,
+        potential_ca_certificate: &[X509Certificate<'a>],
+    ) -> Result<Option<X509Certificate<'a>>, Self::Error> {
+        Ok(potential_ca_certificate
+            .iter()
+            .find(|ca| is_issued_by(source, ca))
+            .cloned())
     }
 
-    fn certificate_chain(
+    fn certificate_chain<'a>(
         &self,
-        source: &X509Certificate,
-        intermediate_certificates: &Vec<CertificateDer>,
-        ca_certificate: &Vec<CertificateDer>,
-    ) -> Result<(Vec<X509Certificate>, X509Certificate), Self::Error> {
-        todo!()
+        source: &X509Certificate<'a>,
+        intermediate_certificates: &[X509Certificate<'a>],
+        ca_certificate: &[X509Certificate<'a>],
+    ) -> Result<(Vec<X509Certificate<'a>>, Option<X509Certificate<'a>>), Self::Error> {
+        let intermediate = self.intermediate_certificates(source, intermediate_certificates)?;
+        let top = intermediate.last().unwrap_or(source);
+        let ca = self.ca_certificate(top, ca_certificate)?;
+        Ok((intermediate, ca))
     }
 
     fn parse_identity<'a>(
@@ -240,141 +468,250 @@ impl IdentityParser for PkiParser {
     ) -> Result<(), Self::Error> {
         use x509_parser::prelude::FromDer;
 
-        let mut intermediate_certificates: Vec<CertificateDer> = Vec::new();
-        for (x, potential_intermediate_certificate) in pki_data_source
+        let certificates: Vec<(usize, X509Certificate, CertificateDer)> = pki_data_source
             .x509
             .iter()
-            .map(|x| X509Certificate::from_der(x).unwrap())
-        {
-            if potential_intermediate_certificate.is_ca() {
-                intermediate_certificates.push(CertificateDer::from(x));
+            .enumerate()
+            .filter_map(|(index, x)| {
+                X509Certificate::from_der(x)
+                    .ok()
+                    .map(|(_, certificate)| (index, certificate, CertificateDer::from(x.as_ref())))
+            })
+            .collect();
+        // The chain is built from parsed certificates, the identity keeps the DER they were parsed from.
+        let der_of = |certificate: &X509Certificate| {
+            certificates
+                .iter()
+                .find(|(_, parsed, _)| {
+                    parsed.tbs_certificate.as_ref() == certificate.tbs_certificate.as_ref()
+                })
+                .map(|(_, _, der)| der.clone())
+        };
+
+        let mut intermediate_certificates: Vec<X509Certificate> = Vec::new();
+        let mut ca_certificate: Vec<X509Certificate> = Vec::new();
+        for (_, potential_chain_certificate, _) in &certificates {
+            if !potential_chain_certificate.is_ca() {
+                continue;
+            }
+            if is_issued_by(potential_chain_certificate, potential_chain_certificate) {
+                ca_certificate.push(potential_chain_certificate.clone());
+            } else {
+                intermediate_certificates.push(potential_chain_certificate.clone());
             }
         }
-        let ca_certificate: Vec<CertificateDer> = Vec::new();
 
-        for (data, certificate) in pki_data_source
-            .x509
-            .iter()
-            .map(|x| X509Certificate::from_der(x).unwrap())
-        {
-            let alg = certificate.public_key().algorithm.clone();
-            //if alg.parameters.is_some() {
-            //    // Do not parse certificate with specific keys with additional parameters
-            //    continue;
-            //}
-            match certificate.public_key().parsed().unwrap() {
-                PublicKey::RSA(rsa) => {
-                    for private_key in pki_data_source
-                        .pkc1
-                        .iter()
-                        .map(|x| RsaPrivateKey::from_der(x.secret_pkcs1_der()).unwrap())
-                    {
-                        let temp_public =
-                            RsaPrivateKey::from_der(private_key.to_der().unwrap().as_slice())
-                                .unwrap()
-                                .public_key()
-                                .to_der()
-                                .unwrap();
-                        let (data, public_key) =
-                            RSAPublicKey::from_der(temp_public.as_slice()).unwrap();
-                        if rsa == public_key {
-                            let (intermediate, ca) = self.certificate_chain(
-                                &certificate,
-                                &intermediate_certificates,
-                                &ca_certificate,
-                            )?;
-
-                            let identity = Identity {
-                                server_name: ServerName::try_from(
-                                    certificate.subject.to_string().as_str(),
-                                )
-                                .unwrap()
-                                .to_owned(),
-                                certificate: certificate.clone(),
-                                private_key: PrivateKeyDer::try_from(PrivatePkcs1KeyDer::from(
-                                    private_key.to_der().unwrap(),
-                                ))
-                                .unwrap(),
-                                intermediate,
-                                ca_certificate: ca,
-                            };
-
-                            identities.push(identity);
-                        }
-                    }
-                }
-                PublicKey::EC(ec) => {
-                    for private_key in pki_data_source
-                        .sec1
-                        .iter()
-                        .map(|x| sec1::EcPrivateKey::from_der(x.secret_sec1_der()).unwrap())
-                    {
-                        //TODO: Fix
-                        //let temp_public = elliptic_curve::PublicKey::from_sec1_bytes(ec.data()).unwrap();
-                        //let temp_public = sec1::EcPrivateKey::from_der(ec.data()).unwrap().public_key.unwrap();
-                        let temp_public = private_key.public_key.unwrap();
-                        if ec.data() == temp_public {
-                            let (intermediate, ca) = self.certificate_chain(
-                                &certificate,
-                                &intermediate_certificates,
-                                &ca_certificate,
-                            )?;
-                            let identity = Identity {
-                                server_name: ServerName::try_from(
-                                    certificate.subject.to_string().as_str(),
-                                )
-                                .unwrap()
-                                .to_owned(),
-                                certificate: certificate.clone(),
-                                private_key: PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
-                                    private_key.to_der().unwrap(),
-                                )),
-                                intermediate,
-                                ca_certificate: ca,
-                            };
-                            identities.push(identity);
-                        }
-                    }
+        let private_keys = candidate_private_keys(pki_data_source);
+
+        for (index, certificate, certificate_der) in &certificates {
+            for (private_key, private_key_der, private_key_item) in &private_keys {
+                if !private_key.matches_public_key(certificate.public_key()) {
+                    continue;
                 }
-                PublicKey::DSA(dsa) => continue,
-                PublicKey::GostR3410(_) => continue,
-                PublicKey::GostR3410_2012(_) => continue,
-                PublicKey::Unknown(_) => continue,
-            };
+                let (intermediate, ca) = self.certificate_chain(
+                    certificate,
+                    &intermediate_certificates,
+                    &ca_certificate,
+                )?;
+                let identity = Identity {
+                    names: certificate_subject_names(certificate, self.allow_common_name_fallback),
+                    certificate: certificate.clone(),
+                    certificate_der: certificate_der.clone(),
+                    private_key: private_key_der.clone_key(),
+                    intermediate_der: intermediate.iter().filter_map(der_of).collect(),
+                    intermediate,
+                    ca_certificate_der: ca.as_ref().and_then(der_of),
+                    ca_certificate: ca,
+                    certificate_provenance: pki_data_source
+                        .provenance_of(PkiItem::Certificate(*index))
+                        .cloned(),
+                    private_key_provenance: pki_data_source
+                        .provenance_of(*private_key_item)
+                        .cloned(),
+                };
+                identities.push(identity);
+            }
         }
         Ok(())
     }
 }
 
+/// Collects every private key of the parsed data, PKCS#1, SEC1 and PKCS#8 alike.
+/// Keys that fail to decode are skipped.
+fn candidate_private_keys<'a>(
+    pki_data_source: &'a ParsedPkiData<'a>,
+) -> Vec<(GenericPrivateKey<'a>, PrivateKeyDer<'a>, PkiItem)> {
+    let mut private_keys = Vec::new();
+    for (index, pkcs1) in pki_data_source.pkc1.iter().enumerate() {
+        match RsaPrivateKey::from_der(pkcs1.secret_pkcs1_der()) {
+            Ok(key) => private_keys.push((
+                GenericPrivateKey::RsaKey(key),
+                PrivateKeyDer::Pkcs1(PrivatePkcs1KeyDer::from(pkcs1.secret_pkcs1_der())),
+                PkiItem::Pkcs1(index),
+            )),
+            Err(err) => tracing::warn!("Skipping invalid PKCS#1 private key: {}", err),
+        }
+    }
+    for (index, sec1) in pki_data_source.sec1.iter().enumerate() {
+        match sec1::EcPrivateKey::from_der(sec1.secret_sec1_der()) {
+            Ok(key) => private_keys.push((
+                GenericPrivateKey::ECKey(key),
+                PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(sec1.secret_sec1_der())),
+                PkiItem::Sec1(index),
+            )),
+            Err(err) => tracing::warn!("Skipping invalid SEC1 private key: {}", err),
+        }
+    }
+    for (index, pkcs8) in pki_data_source.pkcs8.iter().enumerate() {
+        match PrivateKeyInfo::from_der(pkcs8.secret_pkcs8_der())
+            .map_err(ParsePkcs8Error::from)
+            .and_then(GenericPrivateKey::form_private_key_info)
+        {
+            Ok(key) => private_keys.push((
+                key,
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8.secret_pkcs8_der())),
+                PkiItem::Pkcs8(index),
+            )),
+            Err(err) => tracing::warn!("Skipping unsupported PKCS#8 private key: {}", err),
+        }
+    }
+    private_keys
+}
+
+/// The parsed identities, indexed by every name they serve.
 #[derive(Default, Debug)]
 pub struct Identities<'a> {
-    pub inner: Vec<Identity<'a>>,
-    pub map: HashMap<ServerName<'a>, &'a Identity<'a>>,
+    inner: Vec<Identity<'a>>,
+    /// Exact DNS names and IP addresses to indices in `inner`.
+    exact: HashMap<SubjectName, Vec<usize>>,
+    /// Wildcard names, keyed by the suffix following the wildcard label (`.example.com`), to indices in `inner`.
+    wildcard: HashMap<String, Vec<usize>>,
 }
 
 impl<'a> Identities<'a> {
     pub fn new(identities: Vec<Identity<'a>>) -> Self {
-        let mut map = HashMap::<ServerName<'a>, &'a Identity<'a>>::new();
-        for identity in &identities {
-            // Use the same lifetime as the identities vector for the references
-            let identity_ref: &'a Identity<'a> = unsafe { &*(identity as *const _) };
-            map.insert(identity.server_name.clone(), identity_ref);
-        }
-        Self {
+        let mut this = Self {
             inner: identities,
-            map,
+            exact: Default::default(),
+            wildcard: Default::default(),
+        };
+        this.rebuild_index();
+        this
+    }
+
+    fn index(&mut self, index: usize) {
+        for name in &self.inner[index].names {
+            match name {
+                SubjectName::WildcardDns(pattern) => self
+                    .wildcard
+                    .entry(pattern[1..].to_string())
+                    .or_default()
+                    .push(index),
+                _ => self.exact.entry(name.clone()).or_default().push(index),
+            }
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        self.exact.clear();
+        self.wildcard.clear();
+        for index in 0..self.inner.len() {
+            self.index(index);
+        }
+    }
+
+    /// Returns the identities serving the server name, the exact matches and then the wildcard matches. Within each
+    /// group the latest notAfter comes first, ties are kept in insertion order.
+    fn matching_groups(&self, key: &ServerName) -> [Vec<&Identity<'a>>; 2] {
+        let (exact_key, wildcard_key) = match key {
+            ServerName::DnsName(dns) => {
+                let name = normalize_dns(dns.as_ref());
+                let wildcard_key = name
+                    .split_once('.')
+                    .filter(|(label, _)| !label.starts_with("xn--"))
+                    .map(|(_, rest)| format!(".{}", rest));
+                (SubjectName::Dns(name), wildcard_key)
+            }
+            ServerName::IpAddress(ip) => (SubjectName::IpAddress((*ip).into()), None),
+            _ => return [vec![], vec![]],
+        };
+        let group = |indices: Option<&Vec<usize>>| {
+            let mut matched: Vec<&Identity<'a>> = indices
+                .into_iter()
+                .flatten()
+                .map(|i| &self.inner[*i])
+                .collect();
+            // Stable sort, insertion order breaks ties.
+            matched.sort_by(|a, b| {
+                b.certificate
+                    .validity()
+                    .not_after
+                    .cmp(&a.certificate.validity().not_after)
+            });
+            matched
+        };
+        [
+            group(self.exact.get(&exact_key)),
+            group(wildcard_key.and_then(|suffix| self.wildcard.get(&suffix))),
+        ]
+    }
+
+    /// Returns every identity serving the server name. Exact matches come before wildcard matches,
+    /// and within each the latest notAfter comes first, ties are kept in insertion order.
+    pub fn get_identities(&self, key: &ServerName) -> Vec<&Identity<'a>> {
+        let mut identities: Vec<&Identity<'a>> = Vec::new();
+        for identity in self.matching_groups(key).into_iter().flatten() {
+            if !identities.iter().any(|x| std::ptr::eq(*x, identity)) {
+                identities.push(identity);
+            }
         }
+        identities
     }
 
-    pub fn get_identity(&self, key: &ServerName<'a>) -> Option<&&Identity<'a>> {
-        self.map.get(key)
+    /// Returns the identity for the server name (e.g. the SNI of a client hello),
+    /// preferring the most specific match, then the latest notAfter.
+    pub fn get_identity(&self, key: &ServerName) -> Option<&Identity<'a>> {
+        self.get_identities(key).into_iter().next()
+    }
+
+    /// Returns the identity for the server name whose key can sign with one of the client's
+    /// signature schemes, preferring ECDSA, then EdDSA, then RSA. Wildcard matches are only
+    /// considered when no exact match is supported by the client.
+    pub fn resolve(
+        &self,
+        key: &ServerName,
+        signature_schemes: &[SignatureScheme],
+    ) -> Option<&Identity<'a>> {
+        self.matching_groups(key).into_iter().find_map(|group| {
+            select_by_algorithm(
+                group
+                    .into_iter()
+                    .map(|identity| (identity.key_algorithm(), identity)),
+                signature_schemes,
+            )
+        })
     }
 
     pub fn push(&mut self, identity: Identity<'a>) {
-        self.inner.push(identity)
+        self.inner.push(identity);
+        self.index(self.inner.len() - 1);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Identity<'a> {
+        let identity = self.inner.remove(index);
+        self.rebuild_index();
+        identity
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Identity<'a>> {
+        self.inner.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
     }
 
-    pub fn remove(&mut self, index: usize) {
-        self.inner.remove(index);
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
     }
 }
@@ -0,0 +1,100 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// A PEM encapsulated block, RFC 7468, including the RFC 1421 headers used by legacy encrypted keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PemBlock {
+    pub label: String,
+    pub headers: Vec<(String, String)>,
+    pub contents: Vec<u8>,
+    /// Position of the block among the blocks of the input.
+    pub index: usize,
+    /// Byte offset of the `-----BEGIN` line in the input.
+    pub offset: usize,
+}
+
+impl PemBlock {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum PemReadError {
+    #[error("MissingEndLine for {label} at offset {offset}")]
+    MissingEndLine { label: String, offset: usize },
+    #[error("InvalidBase64 for {label} at offset {offset}")]
+    InvalidBase64 { label: String, offset: usize },
+}
+
+/// Reads every PEM block of the input, text outside of blocks is ignored.
+pub fn read_pem_blocks(data: &[u8]) -> Result<Vec<PemBlock>, PemReadError> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    let mut lines = data.split_inclusive(|b| *b == b'\n');
+    while let Some(line) = lines.next() {
+        let line_offset = offset;
+        offset += line.len();
+        let Some(label) = trim_line(line)
+            .strip_prefix("-----BEGIN ")
+            .and_then(|rest| rest.strip_suffix("-----"))
+        else {
+            continue;
+        };
+        let label = label.to_string();
+        let end_line = format!("-----END {}-----", label);
+        let mut headers = Vec::new();
+        let mut base64 = String::new();
+        let mut in_headers = true;
+        let mut closed = false;
+        for line in lines.by_ref() {
+            offset += line.len();
+            let line = trim_line(line);
+            if line == end_line {
+                closed = true;
+                break;
+            }
+            if in_headers {
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                    continue;
+                }
+                in_headers = false;
+                if line.is_empty() {
+                    continue;
+                }
+            }
+            base64.push_str(line.trim());
+        }
+        if !closed {
+            return Err(PemReadError::MissingEndLine {
+                label,
+                offset: line_offset,
+            });
+        }
+        let contents =
+            STANDARD
+                .decode(base64.as_bytes())
+                .map_err(|_| PemReadError::InvalidBase64 {
+                    label: label.clone(),
+                    offset: line_offset,
+                })?;
+        blocks.push(PemBlock {
+            label,
+            headers,
+            contents,
+            index: blocks.len(),
+            offset: line_offset,
+        });
+    }
+    Ok(blocks)
+}
+
+fn trim_line(line: &[u8]) -> &str {
+    std::str::from_utf8(line)
+        .unwrap_or("")
+        .trim_end_matches(['\r', '\n'])
+}
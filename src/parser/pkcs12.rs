@@ -0,0 +1,314 @@
+use der::Decode;
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::{Mac, SimpleHmac};
+use p12::{
+    AlgorithmIdentifier, CertBag, ContentInfo, MacData, PKCS12Attribute, SafeBag, SafeBagKind, PFX,
+};
+use rustls_pki_types::{CertificateDer, PrivatePkcs8KeyDer};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::ParsedPkiData;
+
+/// id-PBES2, RFC 8018 A.4
+const OID_PBES2: &[u64] = &[1, 2, 840, 113549, 1, 5, 13];
+/// id-sha256, id-sha384 and id-sha512, RFC 5754 2
+const OID_SHA256: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+const OID_SHA384: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 2];
+const OID_SHA512: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 3];
+
+/// The item of `ParsedPkiData` a set of PKCS#12 bag attributes belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pkcs12Item {
+    /// Index into `ParsedPkiData.x509`.
+    Certificate(usize),
+    /// Index into `ParsedPkiData.pkcs8`.
+    PrivateKey(usize),
+}
+
+/// The `friendlyName` and `localKeyId` bag attributes, the latter pairs a certificate with its private key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pkcs12Attributes {
+    pub item: Pkcs12Item,
+    pub friendly_name: Option<String>,
+    pub local_key_id: Option<Vec<u8>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Pkcs12ParseError {
+    #[error("InvalidPkcs12: {0}")]
+    InvalidPkcs12(String),
+    #[error("InvalidPassword")]
+    InvalidPassword,
+    #[error("NonUtf8Password")]
+    NonUtf8Password,
+    #[error("UnsupportedMac: {0}")]
+    UnsupportedMac(String),
+    #[error("UnsupportedEncryption: {0}")]
+    UnsupportedEncryption(String),
+    #[error("UnsupportedContentType: {0}")]
+    UnsupportedContentType(String),
+}
+
+fn invalid(err: impl std::fmt::Debug) -> Pkcs12ParseError {
+    Pkcs12ParseError::InvalidPkcs12(format!("{:?}", err))
+}
+
+/// The password as a null terminated big-endian UTF-16 string, RFC 7292 B.1.
+fn bmp_string(password: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = password.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    bytes.extend([0, 0]);
+    bytes
+}
+
+/// The PKCS#12 key derivation function, RFC 7292 B.2, for the MAC key (ID 3). A single block of output is needed as
+/// the key is as long as the digest.
+fn pkcs12_mac_key<D: Digest + BlockSizeUser>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Vec<u8> {
+    let v = D::block_size();
+    let repeat = |data: &[u8]| -> Vec<u8> {
+        let len = v * ((data.len() + v - 1) / v);
+        data.iter().copied().cycle().take(len).collect()
+    };
+    let mut i = repeat(salt);
+    i.extend(repeat(password));
+    let mut a = D::new()
+        .chain_update(vec![3u8; v])
+        .chain_update(&i)
+        .finalize();
+    for _ in 1..iterations {
+        a = D::digest(&a);
+    }
+    a.to_vec()
+}
+
+fn mac_matches<D: Digest + BlockSizeUser>(
+    password: &[u8],
+    mac_data: &MacData,
+    data: &[u8],
+) -> bool {
+    let key = pkcs12_mac_key::<D>(password, &mac_data.salt, mac_data.iterations);
+    match SimpleHmac::<D>::new_from_slice(&key) {
+        Ok(mut hmac) => {
+            hmac.update(data);
+            hmac.verify_slice(&mac_data.mac.digest).is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+/// Verifies the password integrity MAC over the authenticated safe, with SHA-1 or SHA-2 (OpenSSL 3 defaults to SHA-256).
+fn verify_mac(pfx: &PFX, data: &[u8], password: &str) -> Result<bool, Pkcs12ParseError> {
+    let Some(mac_data) = &pfx.mac_data else {
+        return Ok(true);
+    };
+    let password = bmp_string(password);
+    match &mac_data.mac.digest_algorithm {
+        AlgorithmIdentifier::Sha1 => Ok(mac_matches::<Sha1>(&password, mac_data, data)),
+        AlgorithmIdentifier::OtherAlg(other) => {
+            match other.algorithm_type.components().as_slice() {
+                OID_SHA256 => Ok(mac_matches::<Sha256>(&password, mac_data, data)),
+                OID_SHA384 => Ok(mac_matches::<Sha384>(&password, mac_data, data)),
+                OID_SHA512 => Ok(mac_matches::<Sha512>(&password, mac_data, data)),
+                _ => Err(Pkcs12ParseError::UnsupportedMac(
+                    other.algorithm_type.to_string(),
+                )),
+            }
+        }
+        other => Err(Pkcs12ParseError::UnsupportedMac(format!("{:?}", other))),
+    }
+}
+
+/// Decrypts with PBES2 (OpenSSL 3 defaults to AES-256-CBC) or the legacy PKCS#12 PBE schemes.
+fn decrypt(
+    algorithm: &AlgorithmIdentifier,
+    ciphertext: &[u8],
+    password: &str,
+) -> Result<Vec<u8>, Pkcs12ParseError> {
+    match algorithm {
+        AlgorithmIdentifier::OtherAlg(other)
+            if other.algorithm_type.components().as_slice() == OID_PBES2 =>
+        {
+            let params = other
+                .params
+                .as_deref()
+                .ok_or_else(|| invalid("Missing PBES2 parameters"))?;
+            let params = pkcs5::pbes2::Parameters::from_der(params)
+                .map_err(|err| Pkcs12ParseError::UnsupportedEncryption(err.to_string()))?;
+            // PBES2 takes the password as is, unlike the legacy schemes.
+            pkcs5::EncryptionScheme::Pbes2(params)
+                .decrypt(password.as_bytes(), ciphertext)
+                .map_err(|_| Pkcs12ParseError::InvalidPassword)
+        }
+        AlgorithmIdentifier::OtherAlg(other) => Err(Pkcs12ParseError::UnsupportedEncryption(
+            other.algorithm_type.to_string(),
+        )),
+        legacy => legacy
+            .decrypt_pbe(ciphertext, &bmp_string(password))
+            .ok_or(Pkcs12ParseError::InvalidPassword),
+    }
+}
+
+/// Parses a PKCS#12 / PFX archive into `source`. Shrouded keys are decrypted into PKCS#8.
+pub fn parse_pkcs12(
+    source: &mut ParsedPkiData,
+    der: &[u8],
+    password: &[u8],
+) -> Result<(), Pkcs12ParseError> {
+    let password = std::str::from_utf8(password).map_err(|_| Pkcs12ParseError::NonUtf8Password)?;
+    let pfx = PFX::parse(der).map_err(invalid)?;
+    let ContentInfo::Data(auth_safe) = &pfx.auth_safe else {
+        // Public-key integrity mode.
+        return Err(Pkcs12ParseError::UnsupportedContentType(
+            pfx.auth_safe.oid().to_string(),
+        ));
+    };
+    if !verify_mac(&pfx, auth_safe, password)? {
+        return Err(Pkcs12ParseError::InvalidPassword);
+    }
+    let contents = yasna::parse_der(auth_safe, |r| r.collect_sequence_of(ContentInfo::parse))
+        .map_err(invalid)?;
+    let mut bags = Vec::new();
+    for content in contents {
+        let data = match content {
+            ContentInfo::Data(data) => data,
+            ContentInfo::EncryptedData(encrypted) => decrypt(
+                &encrypted
+                    .encrypted_content_info
+                    .content_encryption_algorithm,
+                &encrypted.encrypted_content_info.encrypted_content,
+                password,
+            )?,
+            ContentInfo::OtherContext(other) => {
+                return Err(Pkcs12ParseError::UnsupportedContentType(
+                    other.content_type.to_string(),
+                ))
+            }
+        };
+        bags.extend(
+            yasna::parse_der(&data, |r| r.collect_sequence_of(SafeBag::parse)).map_err(invalid)?,
+        );
+    }
+    for bag in bags {
+        let item = match bag.bag {
+            SafeBagKind::CertBag(CertBag::X509(certificate)) => {
+                source.x509.push(CertificateDer::from(certificate));
+                Pkcs12Item::Certificate(source.x509.len() - 1)
+            }
+            SafeBagKind::Pkcs8ShroudedKeyBag(encrypted) => {
+                let private_key = decrypt(
+                    &encrypted.encryption_algorithm,
+                    &encrypted.encrypted_data,
+                    password,
+                )?;
+                source.pkcs8.push(PrivatePkcs8KeyDer::from(private_key));
+                Pkcs12Item::PrivateKey(source.pkcs8.len() - 1)
+            }
+            _ => continue,
+        };
+        let mut attributes = Pkcs12Attributes {
+            item,
+            friendly_name: None,
+            local_key_id: None,
+        };
+        for attribute in bag.attributes {
+            match attribute {
+                PKCS12Attribute::FriendlyName(name) => attributes.friendly_name = Some(name),
+                PKCS12Attribute::LocalKeyId(id) => attributes.local_key_id = Some(id),
+                _ => {}
+            }
+        }
+        source.pkcs12_attributes.push(attributes);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::pkcs12::{parse_pkcs12, Pkcs12Item, Pkcs12ParseError};
+    use crate::ParsedPkiData;
+
+    #[test]
+    fn test_parse_identity_p12() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        parse_pkcs12(
+            &mut parsed_pki_data,
+            include_bytes!("../../tests/data/identity.p12"),
+            b"mypass",
+        )
+        .unwrap();
+        assert_eq!(parsed_pki_data.pkcs8.len(), 1);
+        assert!(!parsed_pki_data.x509.is_empty());
+        let key_attributes = parsed_pki_data
+            .pkcs12_attributes
+            .iter()
+            .find(|x| x.item == Pkcs12Item::PrivateKey(0))
+            .unwrap();
+        assert_eq!(key_attributes.friendly_name.as_deref(), Some("foobar.com"));
+        assert!(parsed_pki_data.pkcs12_attributes.iter().any(|x| matches!(
+            x.item,
+            Pkcs12Item::Certificate(_)
+        ) && x.local_key_id
+            == key_attributes.local_key_id));
+    }
+
+    #[test]
+    fn test_parse_pbes2_p12() {
+        // OpenSSL 3 defaults: PBES2 with AES-256-CBC and a SHA-256 MAC.
+        let mut parsed_pki_data = ParsedPkiData::default();
+        parse_pkcs12(
+            &mut parsed_pki_data,
+            include_bytes!("../../tests/data/identity-pbes2.p12"),
+            b"mypass",
+        )
+        .unwrap();
+        assert_eq!(parsed_pki_data.pkcs8.len(), 1);
+        assert_eq!(parsed_pki_data.x509.len(), 2);
+        let key_attributes = parsed_pki_data
+            .pkcs12_attributes
+            .iter()
+            .find(|x| x.item == Pkcs12Item::PrivateKey(0))
+            .unwrap();
+        assert_eq!(
+            key_attributes.friendly_name.as_deref(),
+            Some("ocsp.foobar.com")
+        );
+
+        assert!(matches!(
+            parse_pkcs12(
+                &mut ParsedPkiData::default(),
+                include_bytes!("../../tests/data/identity-pbes2.p12"),
+                b"not-the-password",
+            ),
+            Err(Pkcs12ParseError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn test_parse_keystore_p12() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        parse_pkcs12(
+            &mut parsed_pki_data,
+            include_bytes!("../../tests/data/keystore-empty-chain.p12"),
+            b"cassandra",
+        )
+        .unwrap();
+        assert_eq!(parsed_pki_data.pkcs8.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_p12_wrong_password() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        assert!(matches!(
+            parse_pkcs12(
+                &mut parsed_pki_data,
+                include_bytes!("../../tests/data/keystore-empty-chain.p12"),
+                b"not-the-password",
+            ),
+            Err(Pkcs12ParseError::InvalidPassword)
+        ));
+    }
+}
@@ -0,0 +1,108 @@
+use cms::cert::CertificateChoices;
+use cms::content_info::ContentInfo;
+use cms::revocation::RevocationInfoChoice;
+use cms::signed_data::SignedData;
+use const_oid::ObjectIdentifier;
+use der::{Decode, Encode};
+use rustls_pki_types::{CertificateDer, CertificateRevocationListDer};
+
+use crate::ParsedPkiData;
+
+/// id-signedData, RFC 5652 5.1
+pub const ID_SIGNED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.2");
+
+#[derive(thiserror::Error, Debug)]
+pub enum Pkcs7ParseError {
+    #[error(transparent)]
+    DerError(#[from] der::Error),
+    #[error("UnsupportedContentType {0}")]
+    UnsupportedContentType(ObjectIdentifier),
+}
+
+/// Certificates and CRLs of a degenerate (certificates only, RFC 5652 5.2) PKCS#7 SignedData structure,
+/// as published in `.p7b`/`.p7c` bundles. The signed content and signer infos are ignored.
+pub struct Pkcs7Bundle {
+    pub certificates: Vec<CertificateDer<'static>>,
+    pub crls: Vec<CertificateRevocationListDer<'static>>,
+}
+
+pub fn parse_pkcs7_bundle(der: &[u8]) -> Result<Pkcs7Bundle, Pkcs7ParseError> {
+    let content_info = ContentInfo::from_der(der)?;
+    if content_info.content_type != ID_SIGNED_DATA {
+        return Err(Pkcs7ParseError::UnsupportedContentType(
+            content_info.content_type,
+        ));
+    }
+    let signed_data: SignedData = content_info.content.decode_as()?;
+    let mut bundle = Pkcs7Bundle {
+        certificates: Vec::new(),
+        crls: Vec::new(),
+    };
+    if let Some(certificate_set) = signed_data.certificates {
+        for choice in certificate_set.0.iter() {
+            if let CertificateChoices::Certificate(certificate) = choice {
+                bundle
+                    .certificates
+                    .push(CertificateDer::from(certificate.to_der()?));
+            }
+        }
+    }
+    if let Some(revocation_info_choices) = signed_data.crls {
+        for choice in revocation_info_choices.0.iter() {
+            if let RevocationInfoChoice::Crl(crl) = choice {
+                bundle
+                    .crls
+                    .push(CertificateRevocationListDer::from(crl.to_der()?));
+            }
+        }
+    }
+    Ok(bundle)
+}
+
+/// Extracts the certificates of a degenerate PKCS#7 SignedData structure.
+pub fn parse_pkcs7_certificates(
+    der: &[u8],
+) -> Result<Vec<CertificateDer<'static>>, Pkcs7ParseError> {
+    Ok(parse_pkcs7_bundle(der)?.certificates)
+}
+
+/// Parses a PKCS#7 bundle into `source`, certificates into `x509` and CRLs into `crls`.
+pub fn parse_pkcs7(source: &mut ParsedPkiData, der: &[u8]) -> Result<(), Pkcs7ParseError> {
+    let bundle = parse_pkcs7_bundle(der)?;
+    source.x509.extend(bundle.certificates);
+    source.crls.extend(bundle.crls);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::parser::parse::PkiParser;
+    use crate::ParsedPkiData;
+
+    #[test]
+    fn test_parse_pem_pkcs7() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        PkiParser::new()
+            .parse_pem(
+                &mut parsed_pki_data,
+                Cursor::new(include_bytes!("../../tests/data/chain.p7b")),
+            )
+            .unwrap();
+        assert_eq!(parsed_pki_data.x509.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_der_pkcs7_with_crl() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        PkiParser::new()
+            .parse(
+                &mut parsed_pki_data,
+                include_bytes!("../../tests/data/crl-bundle.p7c"),
+            )
+            .unwrap();
+        assert_eq!(parsed_pki_data.x509.len(), 1);
+        assert_eq!(parsed_pki_data.crls.len(), 1);
+    }
+}
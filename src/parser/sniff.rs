@@ -0,0 +1,133 @@
+use cms::content_info::ContentInfo;
+use der::Decode;
+use pkcs8::{EncryptedPrivateKeyInfo, PrivateKeyInfo};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::certification_request::X509CertificationRequest;
+use x509_parser::prelude::FromDer;
+use x509_parser::revocation_list::CertificateRevocationList;
+
+use crate::parser::openssh::is_openssh_certificate;
+
+/// The kind of item held by a DER input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DerKind {
+    Certificate,
+    Crl,
+    Csr,
+    Pkcs1,
+    Pkcs8,
+    EncryptedPkcs8,
+    Sec1,
+    Pkcs7,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PkiFormat {
+    Pem,
+    Der(DerKind),
+    Pkcs12,
+    /// A JSON Web Key or JWK Set.
+    Jwk,
+    /// OpenSSH certificates, `*-cert.pub`.
+    OpenSshCertificate,
+}
+
+const PEM_BOUNDARY: &[u8] = b"-----BEGIN ";
+
+/// Detects the format of the input by its content.
+/// DER inputs are recognized by attempting each structure, most common first.
+/// Text with a PEM boundary anywhere is PEM, openssl `-text` output and PKCS#12 exports
+/// (`Bag Attributes`) put human readable text before the blocks.
+pub fn detect_format(data: &[u8]) -> Option<PkiFormat> {
+    let trimmed = data
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map_or(&[][..], |start| &data[start..]);
+    if trimmed.starts_with(PEM_BOUNDARY) {
+        return Some(PkiFormat::Pem);
+    }
+    if trimmed.starts_with(b"{") {
+        return Some(PkiFormat::Jwk);
+    }
+    if is_openssh_certificate(trimmed) {
+        return Some(PkiFormat::OpenSshCertificate);
+    }
+    // Every supported DER structure is a SEQUENCE.
+    if data.first() == Some(&0x30) {
+        if let Some(format) = detect_der_format(data) {
+            return Some(format);
+        }
+    }
+    data.windows(PEM_BOUNDARY.len())
+        .any(|window| window == PEM_BOUNDARY)
+        .then_some(PkiFormat::Pem)
+}
+
+fn detect_der_format(data: &[u8]) -> Option<PkiFormat> {
+    if X509Certificate::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Certificate));
+    }
+    if CertificateRevocationList::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Crl));
+    }
+    if X509CertificationRequest::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Csr));
+    }
+    if PrivateKeyInfo::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Pkcs8));
+    }
+    if pkcs1::RsaPrivateKey::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Pkcs1));
+    }
+    if sec1::EcPrivateKey::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Sec1));
+    }
+    if EncryptedPrivateKeyInfo::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::EncryptedPkcs8));
+    }
+    if ContentInfo::from_der(data).is_ok() {
+        return Some(PkiFormat::Der(DerKind::Pkcs7));
+    }
+    if p12::PFX::parse(data).is_ok() {
+        return Some(PkiFormat::Pkcs12);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::sniff::{detect_format, DerKind, PkiFormat};
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/cert.pem")),
+            Some(PkiFormat::Pem)
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/key.der")),
+            Some(PkiFormat::Der(DerKind::Pkcs1))
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/pkcs8-nocrypt.der")),
+            Some(PkiFormat::Der(DerKind::Pkcs8))
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/pkcs8.der")),
+            Some(PkiFormat::Der(DerKind::EncryptedPkcs8))
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/identity.p12")),
+            Some(PkiFormat::Pkcs12)
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/ssh-host-cert.pub")),
+            Some(PkiFormat::OpenSshCertificate)
+        );
+        assert_eq!(
+            detect_format(include_bytes!("../../tests/data/ca.crt")),
+            Some(PkiFormat::Pem)
+        );
+        assert_eq!(detect_format(b"not pki data"), None);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use zeroize::Zeroizing;
+
+/// Passphrase used to decrypt encrypted private keys, zeroed on drop.
+#[derive(Clone)]
+pub struct Passphrase(Arc<Zeroizing<Vec<u8>>>);
+
+impl Passphrase {
+    pub fn new(passphrase: impl Into<Vec<u8>>) -> Self {
+        Self(Arc::new(Zeroizing::new(passphrase.into())))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl Debug for Passphrase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Passphrase(<redacted>)")
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PassphraseError {
+    #[error("MissingEnvironmentVariable {0}")]
+    MissingEnvironmentVariable(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "kube-store")]
+    #[error(transparent)]
+    KubeError(#[from] kube::Error),
+    #[error("MissingSecretKey {0}")]
+    MissingSecretKey(String),
+}
+
+/// Supplies the passphrase of encrypted private keys, configured per store.
+#[async_trait]
+pub trait PassphraseProvider: Send + Sync {
+    async fn get_passphrase(&self) -> Result<Passphrase, PassphraseError>;
+}
+
+pub struct StaticPassphraseProvider {
+    passphrase: Passphrase,
+}
+
+impl StaticPassphraseProvider {
+    pub fn new(passphrase: impl Into<Vec<u8>>) -> Self {
+        Self {
+            passphrase: Passphrase::new(passphrase),
+        }
+    }
+}
+
+#[async_trait]
+impl PassphraseProvider for StaticPassphraseProvider {
+    async fn get_passphrase(&self) -> Result<Passphrase, PassphraseError> {
+        Ok(self.passphrase.clone())
+    }
+}
+
+/// Reads the passphrase from an environment variable on every reload.
+pub struct EnvPassphraseProvider {
+    pub variable: String,
+}
+
+#[async_trait]
+impl PassphraseProvider for EnvPassphraseProvider {
+    async fn get_passphrase(&self) -> Result<Passphrase, PassphraseError> {
+        std::env::var(&self.variable)
+            .map(|value| Passphrase::new(value.into_bytes()))
+            .map_err(|_| PassphraseError::MissingEnvironmentVariable(self.variable.clone()))
+    }
+}
+
+/// Reads the passphrase from a file, such as a mounted secret, a trailing newline is ignored.
+pub struct FilePassphraseProvider {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl PassphraseProvider for FilePassphraseProvider {
+    async fn get_passphrase(&self) -> Result<Passphrase, PassphraseError> {
+        let mut contents = Zeroizing::new(tokio::fs::read(&self.path).await?);
+        while matches!(contents.last(), Some(b'\n') | Some(b'\r')) {
+            contents.pop();
+        }
+        Ok(Passphrase::new(contents.to_vec()))
+    }
+}
+
+/// Reads the passphrase from a key of a Kubernetes Secret.
+#[cfg(feature = "kube-store")]
+pub struct KubernetesSecretPassphraseProvider {
+    pub client: kube::Client,
+    pub namespace: String,
+    pub secret_name: String,
+    pub key: String,
+}
+
+#[cfg(feature = "kube-store")]
+#[async_trait]
+impl PassphraseProvider for KubernetesSecretPassphraseProvider {
+    async fn get_passphrase(&self) -> Result<Passphrase, PassphraseError> {
+        use k8s_openapi::api::core::v1::Secret;
+        let secrets: kube::Api<Secret> =
+            kube::Api::namespaced(self.client.clone(), self.namespace.as_str());
+        let secret = secrets.get(self.secret_name.as_str()).await?;
+        secret
+            .data
+            .as_ref()
+            .and_then(|data| data.get(&self.key))
+            .map(|value| Passphrase::new(value.0.clone()))
+            .ok_or_else(|| PassphraseError::MissingSecretKey(self.key.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::passphrase::{
+        EnvPassphraseProvider, FilePassphraseProvider, PassphraseError, PassphraseProvider,
+    };
+
+    #[tokio::test]
+    async fn test_env_passphrase_provider() {
+        let provider = EnvPassphraseProvider {
+            variable: "PKI_WATCHER_TEST_PASSPHRASE".to_string(),
+        };
+        assert!(matches!(
+            provider.get_passphrase().await,
+            Err(PassphraseError::MissingEnvironmentVariable(variable)) if variable == "PKI_WATCHER_TEST_PASSPHRASE"
+        ));
+        std::env::set_var("PKI_WATCHER_TEST_PASSPHRASE", "mypass");
+        assert_eq!(
+            provider.get_passphrase().await.unwrap().as_bytes(),
+            b"mypass"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_passphrase_provider() {
+        let path =
+            std::env::temp_dir().join(format!("pki-watcher-passphrase-{}", std::process::id()));
+        let provider = FilePassphraseProvider { path: path.clone() };
+        assert!(matches!(
+            provider.get_passphrase().await,
+            Err(PassphraseError::Io(_))
+        ));
+        tokio::fs::write(&path, b"mypass\r\n").await.unwrap();
+        let passphrase = provider.get_passphrase().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(passphrase.unwrap().as_bytes(), b"mypass");
+    }
+
+    #[cfg(feature = "kube-store")]
+    #[tokio::test]
+    async fn test_kubernetes_secret_passphrase_provider() {
+        use kube::client::Body;
+
+        use crate::passphrase::KubernetesSecretPassphraseProvider;
+
+        let service = tower::service_fn(|request: http::Request<Body>| async move {
+            assert_eq!(
+                request.uri().path(),
+                "/api/v1/namespaces/pki/secrets/keystore"
+            );
+            // "mypass", base64 encoded.
+            let secret = r#"{"apiVersion":"v1","kind":"Secret","metadata":{"name":"keystore"},"data":{"passphrase":"bXlwYXNz"}}"#;
+            Ok::<_, std::convert::Infallible>(http::Response::new(Body::from(
+                secret.as_bytes().to_vec(),
+            )))
+        });
+        let client = kube::Client::new(service, "pki");
+        let provider = |key: &str| KubernetesSecretPassphraseProvider {
+            client: client.clone(),
+            namespace: "pki".to_string(),
+            secret_name: "keystore".to_string(),
+            key: key.to_string(),
+        };
+        assert_eq!(
+            provider("passphrase")
+                .get_passphrase()
+                .await
+                .unwrap()
+                .as_bytes(),
+            b"mypass"
+        );
+        assert!(matches!(
+            provider("password").get_passphrase().await,
+            Err(PassphraseError::MissingSecretKey(key)) if key == "password"
+        ));
+    }
+}
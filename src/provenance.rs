@@ -0,0 +1,169 @@
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+use crate::ParsedPkiData;
+
+/// The SPIFFE store is not implemented yet, SVIDs carry no provenance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreKind {
+    File,
+    Kubernetes,
+}
+
+/// The version of the resource at load time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceVersion {
+    /// The Kubernetes `metadata.resourceVersion`.
+    ResourceVersion(String),
+    /// The file modification time.
+    Modified(SystemTime),
+}
+
+/// The resource parsed data was loaded from, set on the parser by the store before parsing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PkiSource {
+    pub store: StoreKind,
+    /// The file path or the `namespace/name` of the Secret.
+    pub resource: String,
+    /// The Secret data key.
+    pub key: Option<String>,
+    pub version: Option<SourceVersion>,
+    pub loaded_at: SystemTime,
+}
+
+impl PkiSource {
+    pub fn new(store: StoreKind, resource: impl Into<String>) -> Self {
+        Self {
+            store,
+            resource: resource.into(),
+            key: None,
+            version: None,
+            loaded_at: SystemTime::now(),
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn with_version(mut self, version: Option<SourceVersion>) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+/// Where a parsed item came from. `block_index` and `offset` locate the PEM block within the resource,
+/// they are absent for DER, PKCS#12, JWK and OpenSSH certificate inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// Absent when the data was parsed outside of a store.
+    pub source: Option<PkiSource>,
+    pub block_index: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            Some(source) => {
+                match source.store {
+                    StoreKind::File => write!(f, "file {}", source.resource)?,
+                    StoreKind::Kubernetes => write!(f, "secret {}", source.resource)?,
+                }
+                if let Some(key) = &source.key {
+                    write!(f, " key {}", key)?;
+                }
+                if let Some(SourceVersion::ResourceVersion(version)) = &source.version {
+                    write!(f, " (resourceVersion {})", version)?;
+                }
+            }
+            None => f.write_str("unknown source")?,
+        }
+        if let (Some(index), Some(offset)) = (self.block_index, self.offset) {
+            write!(f, ", PEM block {} at offset {}", index, offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// An item of `ParsedPkiData`, by index into its vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PkiItem {
+    Certificate(usize),
+    Pkcs1(usize),
+    Sec1(usize),
+    Pkcs8(usize),
+    Crl(usize),
+    Csr(usize),
+    SshPrivateKey(usize),
+    SshCertificate(usize),
+    Unknown(usize),
+}
+
+/// The lengths of the item vectors of `ParsedPkiData`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ItemCounts {
+    x509: usize,
+    pkc1: usize,
+    sec1: usize,
+    pkcs8: usize,
+    crls: usize,
+    csrs: usize,
+    ssh_private_keys: usize,
+    ssh_certificates: usize,
+    unknown: usize,
+}
+
+impl ItemCounts {
+    pub fn of(data: &ParsedPkiData) -> Self {
+        Self {
+            x509: data.x509.len(),
+            pkc1: data.pkc1.len(),
+            sec1: data.sec1.len(),
+            pkcs8: data.pkcs8.len(),
+            crls: data.crls.len(),
+            csrs: data.csrs.len(),
+            ssh_private_keys: data.ssh_private_keys.len(),
+            ssh_certificates: data.ssh_certificates.len(),
+            unknown: data.unknown.len(),
+        }
+    }
+
+    /// The items of `data` added after these counts were taken.
+    pub fn added_items(&self, data: &ParsedPkiData) -> Vec<PkiItem> {
+        let now = Self::of(data);
+        let mut items = Vec::new();
+        items.extend((self.x509..now.x509).map(PkiItem::Certificate));
+        items.extend((self.pkc1..now.pkc1).map(PkiItem::Pkcs1));
+        items.extend((self.sec1..now.sec1).map(PkiItem::Sec1));
+        items.extend((self.pkcs8..now.pkcs8).map(PkiItem::Pkcs8));
+        items.extend((self.crls..now.crls).map(PkiItem::Crl));
+        items.extend((self.csrs..now.csrs).map(PkiItem::Csr));
+        items.extend((self.ssh_private_keys..now.ssh_private_keys).map(PkiItem::SshPrivateKey));
+        items.extend((self.ssh_certificates..now.ssh_certificates).map(PkiItem::SshCertificate));
+        items.extend((self.unknown..now.unknown).map(PkiItem::Unknown));
+        items
+    }
+}
+
+impl PkiItem {
+    /// Shifts the index past the items already counted, used when appending in `ParsedPkiData::merge`.
+    pub fn offset_by(self, counts: &ItemCounts) -> Self {
+        match self {
+            PkiItem::Certificate(index) => PkiItem::Certificate(index + counts.x509),
+            PkiItem::Pkcs1(index) => PkiItem::Pkcs1(index + counts.pkc1),
+            PkiItem::Sec1(index) => PkiItem::Sec1(index + counts.sec1),
+            PkiItem::Pkcs8(index) => PkiItem::Pkcs8(index + counts.pkcs8),
+            PkiItem::Crl(index) => PkiItem::Crl(index + counts.crls),
+            PkiItem::Csr(index) => PkiItem::Csr(index + counts.csrs),
+            PkiItem::SshPrivateKey(index) => {
+                PkiItem::SshPrivateKey(index + counts.ssh_private_keys)
+            }
+            PkiItem::SshCertificate(index) => {
+                PkiItem::SshCertificate(index + counts.ssh_certificates)
+            }
+            PkiItem::Unknown(index) => PkiItem::Unknown(index + counts.unknown),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+use rustls::crypto::CryptoProvider;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::SignatureScheme;
+use rustls_pki_types::{CertificateDer, ServerName};
+use tokio::sync::watch;
+use x509_parser::time::ASN1Time;
+
+use crate::fetch::ocsp::OcspStaples;
+use crate::identity::{select_by_algorithm, Identities as _, KeyAlgorithm};
+use crate::parser::parse::Identities;
+use crate::validate::hostname::SubjectName;
+
+struct ResolvedIdentity {
+    names: Vec<SubjectName>,
+    algorithm: KeyAlgorithm,
+    not_after: ASN1Time,
+    certified_key: Arc<CertifiedKey>,
+}
+
+/// rustls server certificate resolver serving the watched identities.
+/// Picks the identity by SNI and the signature schemes offered by the client, see [`Identities::resolve`].
+pub struct IdentityResolver {
+    provider: Arc<CryptoProvider>,
+    identities: RwLock<Vec<ResolvedIdentity>>,
+    ocsp_staples: RwLock<Arc<OcspStaples>>,
+}
+
+impl Debug for IdentityResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentityResolver").finish_non_exhaustive()
+    }
+}
+
+impl IdentityResolver {
+    pub fn new(provider: Arc<CryptoProvider>) -> Self {
+        Self {
+            provider,
+            identities: Default::default(),
+            ocsp_staples: Default::default(),
+        }
+    }
+
+    /// Replaces the served identities, call this on every reload.
+    /// Identities whose private key the crypto provider cannot load are skipped, the others are still served.
+    pub fn update(&self, identities: &Identities) {
+        // Held throughout, so staples published meanwhile are not lost.
+        let mut current = self.identities.write().unwrap();
+        let ocsp_staples = self.ocsp_staples.read().unwrap().clone();
+        let mut resolved = Vec::with_capacity(identities.len());
+        for identity in identities.iter() {
+            let cert_chain: Vec<CertificateDer<'static>> = identity
+                .get_certificate_chain_der()
+                .into_iter()
+                .map(CertificateDer::into_owned)
+                .collect();
+            let signing_key = match self
+                .provider
+                .key_provider
+                .load_private_key(identity.private_key.clone_key())
+            {
+                Ok(signing_key) => signing_key,
+                Err(err) => {
+                    tracing::warn!(
+                        "Skipping identity {}, its private key cannot be loaded: {}",
+                        identity.certificate.subject(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            resolved.push(ResolvedIdentity {
+                names: identity.names.clone(),
+                algorithm: identity.key_algorithm(),
+                not_after: identity.certificate.validity().not_after,
+                certified_key: Arc::new(CertifiedKey {
+                    ocsp: ocsp_staples.get(cert_chain[0].as_ref()).cloned(),
+                    ..CertifiedKey::new(cert_chain, signing_key)
+                }),
+            });
+        }
+        // Same ordering as `Identities::get_identities`.
+        resolved.sort_by(|a, b| b.not_after.cmp(&a.not_after));
+        *current = resolved;
+    }
+
+    /// Staples the OCSP responses to the served certificates, responses are kept across `update`.
+    pub fn update_ocsp_staples(&self, ocsp_staples: Arc<OcspStaples>) {
+        let mut identities = self.identities.write().unwrap();
+        *self.ocsp_staples.write().unwrap() = ocsp_staples.clone();
+        for identity in identities.iter_mut() {
+            let ocsp = identity
+                .certified_key
+                .end_entity_cert()
+                .ok()
+                .and_then(|certificate| ocsp_staples.get(certificate.as_ref()).cloned());
+            if ocsp != identity.certified_key.ocsp {
+                identity.certified_key = Arc::new(CertifiedKey {
+                    ocsp,
+                    ..(*identity.certified_key).clone()
+                });
+            }
+        }
+    }
+
+    /// Keeps the staples up to date with the responses published by an [`OcspStapler`](crate::fetch::ocsp::OcspStapler),
+    /// spawn it with `tokio::spawn`. Returns once the stapler is dropped.
+    pub async fn watch_ocsp_staples(
+        self: Arc<Self>,
+        mut ocsp_staples: watch::Receiver<Arc<OcspStaples>>,
+    ) {
+        loop {
+            let current = ocsp_staples.borrow_and_update().clone();
+            self.update_ocsp_staples(current);
+            if ocsp_staples.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl IdentityResolver {
+    /// Selects among the exact matches first, wildcard matches are only served when no exact match is supported
+    /// by the client.
+    fn select(
+        &self,
+        server_name: &ServerName,
+        signature_schemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        let identities = self.identities.read().unwrap();
+        [false, true].into_iter().find_map(|wildcard| {
+            let candidates = identities
+                .iter()
+                .filter(|identity| {
+                    identity
+                        .names
+                        .iter()
+                        .any(|name| name.is_wildcard() == wildcard && name.matches(server_name))
+                })
+                .map(|identity| (identity.algorithm, identity.certified_key.clone()));
+            select_by_algorithm(candidates, signature_schemes)
+        })
+    }
+}
+
+impl ResolvesServerCert for IdentityResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let server_name = ServerName::try_from(client_hello.server_name()?).ok()?;
+        self.select(&server_name, client_hello.signature_schemes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    use rustls::sign::CertifiedKey;
+    use rustls::SignatureScheme;
+    use rustls_pki_types::ServerName;
+
+    use crate::fetch::ocsp::OcspStaples;
+    use crate::parser::parse::{Identities, PkiParser};
+    use crate::parser::IdentityParser;
+    use crate::resolver::IdentityResolver;
+    use crate::validate::hostname::SubjectName;
+    use crate::ParsedPkiData;
+
+    const RSA: [&[u8]; 2] = [
+        include_bytes!("../tests/data/cert.pem"),
+        include_bytes!("../tests/data/key.pem"),
+    ];
+    const ECDSA: [&[u8]; 2] = [
+        include_bytes!("../tests/data/ocsp-leaf.pem"),
+        include_bytes!("../tests/data/ocsp-leaf.key"),
+    ];
+    // ring cannot load Ed448 keys.
+    const ED448: [&[u8]; 2] = [
+        include_bytes!("../tests/data/ed448.pem"),
+        include_bytes!("../tests/data/ed448-v2.key"),
+    ];
+
+    fn pki_data(inputs: [&[u8]; 2]) -> ParsedPkiData<'static> {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        for input in inputs {
+            pki_parser
+                .parse_pem(&mut parsed_pki_data, Cursor::new(input.to_vec()))
+                .unwrap();
+        }
+        parsed_pki_data
+    }
+
+    /// Serves the identity of each parsed data under the given name.
+    fn update(resolver: &IdentityResolver, served: &[(&ParsedPkiData, &str)]) {
+        let pki_parser = PkiParser::new();
+        let mut identities = Identities::default();
+        for (parsed_pki_data, name) in served {
+            let mut parsed = Identities::default();
+            pki_parser
+                .parse_identity(parsed_pki_data, &mut parsed)
+                .unwrap();
+            let mut identity = parsed.remove(0);
+            identity.names = vec![SubjectName::from_dns(name).unwrap()];
+            identities.push(identity);
+        }
+        resolver.update(&identities);
+    }
+
+    fn resolver() -> IdentityResolver {
+        IdentityResolver::new(Arc::new(rustls::crypto::ring::default_provider()))
+    }
+
+    fn served_certificate(certified_key: &CertifiedKey) -> &[u8] {
+        certified_key.end_entity_cert().unwrap().as_ref()
+    }
+
+    const BOTH: [SignatureScheme; 2] = [
+        SignatureScheme::RSA_PSS_SHA256,
+        SignatureScheme::ECDSA_NISTP256_SHA256,
+    ];
+
+    #[test]
+    fn test_resolve_prefers_ecdsa() {
+        let (rsa, ecdsa) = (pki_data(RSA), pki_data(ECDSA));
+        let resolver = resolver();
+        update(
+            &resolver,
+            &[(&rsa, "www.foobar.com"), (&ecdsa, "www.foobar.com")],
+        );
+        let name = ServerName::try_from("www.foobar.com").unwrap();
+        let selected = resolver.select(&name, &BOTH).unwrap();
+        assert_eq!(served_certificate(&selected), ecdsa.x509[0].as_ref());
+        let selected = resolver
+            .select(&name, &[SignatureScheme::RSA_PSS_SHA256])
+            .unwrap();
+        assert_eq!(served_certificate(&selected), rsa.x509[0].as_ref());
+        assert!(resolver
+            .select(&name, &[SignatureScheme::ED25519])
+            .is_none());
+    }
+
+    #[test]
+    fn test_resolve_prefers_exact_over_wildcard() {
+        let (rsa, ecdsa) = (pki_data(RSA), pki_data(ECDSA));
+        let resolver = resolver();
+        update(
+            &resolver,
+            &[(&ecdsa, "*.foobar.com"), (&rsa, "www.foobar.com")],
+        );
+        let name = ServerName::try_from("www.foobar.com").unwrap();
+        // The exact RSA identity wins over the better algorithm of the wildcard.
+        let selected = resolver.select(&name, &BOTH).unwrap();
+        assert_eq!(served_certificate(&selected), rsa.x509[0].as_ref());
+        // The wildcard is served when the client cannot use the exact identity.
+        let selected = resolver
+            .select(&name, &[SignatureScheme::ECDSA_NISTP256_SHA256])
+            .unwrap();
+        assert_eq!(served_certificate(&selected), ecdsa.x509[0].as_ref());
+        let other = ServerName::try_from("api.foobar.com").unwrap();
+        assert!(resolver
+            .select(&other, &[SignatureScheme::RSA_PSS_SHA256])
+            .is_none());
+    }
+
+    #[test]
+    fn test_update_skips_unloadable_keys() {
+        let (ed448, ecdsa) = (pki_data(ED448), pki_data(ECDSA));
+        let resolver = resolver();
+        update(
+            &resolver,
+            &[(&ed448, "www.foobar.com"), (&ecdsa, "www.foobar.com")],
+        );
+        let name = ServerName::try_from("www.foobar.com").unwrap();
+        let selected = resolver
+            .select(
+                &name,
+                &[
+                    SignatureScheme::ED448,
+                    SignatureScheme::ECDSA_NISTP256_SHA256,
+                ],
+            )
+            .unwrap();
+        assert_eq!(served_certificate(&selected), ecdsa.x509[0].as_ref());
+        assert!(resolver.select(&name, &[SignatureScheme::ED448]).is_none());
+    }
+
+    #[test]
+    fn test_ocsp_staples_follow_updates() {
+        let (rsa, ecdsa) = (pki_data(RSA), pki_data(ECDSA));
+        let resolver = resolver();
+        update(&resolver, &[(&ecdsa, "www.foobar.com")]);
+        let name = ServerName::try_from("www.foobar.com").unwrap();
+        assert_eq!(resolver.select(&name, &BOTH).unwrap().ocsp, None);
+
+        let mut staples = OcspStaples::new();
+        staples.insert(ecdsa.x509[0].to_vec(), b"response".to_vec());
+        resolver.update_ocsp_staples(Arc::new(staples));
+        let selected = resolver.select(&name, &BOTH).unwrap();
+        assert_eq!(selected.ocsp.as_deref(), Some(&b"response"[..]));
+
+        // The staple is kept across reloads, and only served with its certificate.
+        update(
+            &resolver,
+            &[(&ecdsa, "www.foobar.com"), (&rsa, "foobar.com")],
+        );
+        let selected = resolver.select(&name, &BOTH).unwrap();
+        assert_eq!(selected.ocsp.as_deref(), Some(&b"response"[..]));
+        let other = ServerName::try_from("foobar.com").unwrap();
+        assert_eq!(resolver.select(&other, &BOTH).unwrap().ocsp, None);
+    }
+}
@@ -1,29 +1,37 @@
-use crate::parser::parse::PkiParser;
-use crate::store::{PkiStore, PkiWatcherEventHandler};
-use crate::ParsedPkiData;
-use notify::event::{EventAttributes, ModifyKind};
-use notify::{
-    Config, Event, EventHandler, EventKind, INotifyWatcher, RecommendedWatcher, RecursiveMode,
-    Watcher,
+use crate::configuration::FilePkiStoreConfiguration;
+use crate::parser::parse::{
+    Identities, IdentityParserError, PkiParseError, PkiParser, UnknownItemPolicy,
 };
-use std::borrow::BorrowMut;
-use std::cell::RefCell;
+use crate::parser::IdentityParser;
+use crate::passphrase::{PassphraseError, PassphraseProvider};
+use crate::provenance::{PkiSource, SourceVersion, StoreKind};
+use crate::store::PkiWatcherEventHandler;
+use crate::validate::lint::{LintError, Linter};
+use crate::ParsedPkiData;
 use std::fmt::Debug;
-use std::io::Cursor;
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, BufReader};
 use tokio::sync::Mutex;
 
-
 #[derive(thiserror::Error, Debug)]
-pub enum FileStoreError {}
+pub enum FileStoreError {
+    #[error(transparent)]
+    PassphraseError(#[from] PassphraseError),
+    #[error(transparent)]
+    LintError(#[from] LintError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    PkiParseError(#[from] PkiParseError),
+    #[error(transparent)]
+    IdentityParserError(#[from] IdentityParserError),
+}
 
 pub trait PkiFileStoreWatchers {
     type Error;
     type Event;
+    #[allow(async_fn_in_trait)]
     async fn watch(
         &mut self,
         event_handler: &mut dyn PkiWatcherEventHandler<Self::Event>,
@@ -32,26 +40,96 @@ pub trait PkiFileStoreWatchers {
 
 pub trait PkiFileStoreRetrievers {
     type Error;
+    #[allow(async_fn_in_trait)]
     async fn retrieve(&mut self) -> Result<(), Self::Error>;
 }
 
 pub struct FileStore<'a> {
     path: PathBuf,
-    watcher: Option<INotifyWatcher>,
     parser: PkiParser,
     parsed_pki_data: Arc<Mutex<ParsedPkiData<'a>>>,
+    passphrase_provider: Option<Arc<dyn PassphraseProvider>>,
+    linter: Option<Arc<Linter>>,
+}
+
+impl FileStore<'_> {
+    pub fn new(config: &impl FilePkiStoreConfiguration) -> Self {
+        Self {
+            path: PathBuf::from(config.get_file_path()),
+            parser: PkiParser::new(),
+            parsed_pki_data: Default::default(),
+            passphrase_provider: None,
+            linter: None,
+        }
+    }
+
+    /// Decrypt encrypted private keys of the file with the passphrase of the provider.
+    pub fn with_passphrase_provider(mut self, provider: Arc<dyn PassphraseProvider>) -> Self {
+        self.passphrase_provider = Some(provider);
+        self
+    }
+
+    /// Derive identity names from the subject common name when a certificate has no DNS or IP address
+    /// Subject Alternative Name. Applies to the identities the linter checks before loading the file.
+    pub fn with_common_name_fallback(mut self, allow_common_name_fallback: bool) -> Self {
+        self.parser = self
+            .parser
+            .with_common_name_fallback(allow_common_name_fallback);
+        self
+    }
+
+    /// How PEM blocks of unknown kinds are handled, strict by default.
+    pub fn with_unknown_item_policy(mut self, unknown_item_policy: UnknownItemPolicy) -> Self {
+        self.parser = self.parser.with_unknown_item_policy(unknown_item_policy);
+        self
+    }
+
+    /// Lint the file before loading it, a file with lint errors is not loaded.
+    pub fn with_linter(mut self, linter: Arc<Linter>) -> Self {
+        self.linter = Some(linter);
+        self
+    }
+}
+
+impl<'a> FileStore<'a> {
+    pub fn get_parsed_pki_data(&self) -> Arc<Mutex<ParsedPkiData<'a>>> {
+        self.parsed_pki_data.clone()
+    }
 }
 
 impl PkiFileStoreRetrievers for FileStore<'_> {
     type Error = FileStoreError;
     async fn retrieve(&mut self) -> Result<(), Self::Error> {
-        let f = File::open(self.path.clone()).await.unwrap();
-        let reader = BufReader::new(f);
-        let cursor = Cursor::new(reader.buffer());
-        let mut temp_parsed_pki = self.parsed_pki_data.deref().lock().await;
-        self.parser
-            .parse_pem(temp_parsed_pki.deref_mut(), cursor)
-            .unwrap();
+        if let Some(provider) = &self.passphrase_provider {
+            self.parser
+                .set_passphrase(Some(provider.get_passphrase().await?));
+        }
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        self.parser.set_source(Some(
+            PkiSource::new(StoreKind::File, self.path.display().to_string())
+                .with_version(modified.map(SourceVersion::Modified)),
+        ));
+        let data = tokio::fs::read(self.path.clone()).await?;
+        let mut temp_parsed_pki = ParsedPkiData::default();
+        self.parser.parse(&mut temp_parsed_pki, &data)?;
+        if let Some(linter) = &self.linter {
+            let mut identities = Identities::default();
+            self.parser
+                .parse_identity(&temp_parsed_pki, &mut identities)?;
+            let report = linter.lint_reload(&temp_parsed_pki, &identities);
+            for finding in &report.findings {
+                tracing::warn!("{}", finding);
+            }
+            report.check()?;
+        }
+        self.parsed_pki_data
+            .deref()
+            .lock()
+            .await
+            .merge(&mut temp_parsed_pki);
         Ok(())
     }
 }
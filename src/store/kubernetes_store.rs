@@ -1,12 +1,18 @@
-use core::slice::SlicePattern;
 use std::io::Cursor;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
 
 use crate::configuration::KubernetesPkiStoreConfiguration;
-use crate::parser::parse::{parse_kubernetes_secret, KubernetesError, PkiParser};
-use crate::parser::PemParser;
+use crate::parser::parse::{
+    parse_kubernetes_secret, Identities, IdentityParserError, KubernetesError,
+    ParseKubernetesPemSecreteError, PkiParseError, PkiParser, UnknownItemPolicy,
+};
+use crate::parser::sniff::{detect_format, PkiFormat};
+use crate::parser::IdentityParser;
+use crate::passphrase::{PassphraseError, PassphraseProvider};
+use crate::provenance::{PkiSource, SourceVersion, StoreKind};
+use crate::validate::lint::{LintError, Linter};
 use crate::ParsedPkiData;
 use futures::stream::TryStreamExt;
 use k8s_openapi::api::core::v1::Secret;
@@ -16,7 +22,6 @@ use kube::Api;
 use kube::Client;
 use kube::ResourceExt;
 use tokio::sync::{mpsc, Mutex};
-use tokio::task::JoinHandle;
 
 pub trait KubernetesSecreteWatchers {}
 
@@ -34,6 +39,8 @@ pub struct KubernetesSecreteWatcher<'a> {
     config: KubernetesSecreteWatcherConfigurationInner,
     parser: PkiParser,
     parsed_pki_data: Arc<Mutex<ParsedPkiData<'a>>>,
+    passphrase_provider: Option<Arc<dyn PassphraseProvider>>,
+    linter: Option<Arc<Linter>>,
 }
 
 #[derive(Debug)]
@@ -44,6 +51,17 @@ pub struct ResourceInformation<'a> {
     pub parsed_pki_data: ParsedPkiData<'a>,
 }
 
+/// Finds the PKCS#12 keystore of the secret by its content, cert-manager names it `keystore.p12`
+/// but other issuers pick their own keys.
+fn find_keystore(secret: &Secret) -> Option<(String, Vec<u8>)> {
+    secret
+        .data
+        .as_ref()?
+        .iter()
+        .find(|(_, value)| detect_format(&value.0) == Some(PkiFormat::Pkcs12))
+        .map(|(key, keystore)| (key.clone(), keystore.0.clone()))
+}
+
 /// .
 /// namespace and secret_name are what you set it in the yaml file. hence up to the user
 /// # Errors
@@ -77,13 +95,52 @@ impl KubernetesSecreteWatcher<'_> {
             },
             parser: PkiParser::new(),
             parsed_pki_data: Default::default(),
+            passphrase_provider: None,
+            linter: None,
         }
     }
+
+    /// Decrypt encrypted private keys of the secret with the passphrase of the provider.
+    pub fn with_passphrase_provider(mut self, provider: Arc<dyn PassphraseProvider>) -> Self {
+        self.passphrase_provider = Some(provider);
+        self
+    }
+
+    /// Derive identity names from the subject common name when a certificate has no DNS or IP address
+    /// Subject Alternative Name. Applies to the identities the linter checks before loading the secret.
+    pub fn with_common_name_fallback(mut self, allow_common_name_fallback: bool) -> Self {
+        self.parser = self
+            .parser
+            .with_common_name_fallback(allow_common_name_fallback);
+        self
+    }
+
+    /// How PEM blocks of unknown kinds are handled, strict by default.
+    pub fn with_unknown_item_policy(mut self, unknown_item_policy: UnknownItemPolicy) -> Self {
+        self.parser = self.parser.with_unknown_item_policy(unknown_item_policy);
+        self
+    }
+
+    /// Lint the secret before loading it, a secret with lint errors is not loaded.
+    pub fn with_linter(mut self, linter: Arc<Linter>) -> Self {
+        self.linter = Some(linter);
+        self
+    }
 }
 #[derive(thiserror::Error, Debug)]
 pub enum KubernetesSecretWatcherError {
     #[error(transparent)]
     WatcherError(#[from] kube::runtime::watcher::Error),
+    #[error(transparent)]
+    PassphraseError(#[from] PassphraseError),
+    #[error(transparent)]
+    LintError(#[from] LintError),
+    #[error(transparent)]
+    ParseKubernetesPemSecreteError(#[from] ParseKubernetesPemSecreteError),
+    #[error(transparent)]
+    PkiParseError(#[from] PkiParseError),
+    #[error(transparent)]
+    IdentityParserError(#[from] IdentityParserError),
 }
 
 //#[async_trait]
@@ -118,9 +175,10 @@ impl<'a> KubernetesSecreteWatcher<'a> {
             self.config.pki_kubernetes_namespace.as_str(),
         );
         let watcher_config = self.watcher_config.clone();
-        let client = self.client.clone();
         let parser = self.parser.clone();
         let parsed_pki_data = self.parsed_pki_data.clone();
+        let passphrase_provider = self.passphrase_provider.clone();
+        let linter = self.linter.clone();
 
         let watcher = kube::runtime::watcher(api, watcher_config)
             .applied_objects()
@@ -128,16 +186,63 @@ impl<'a> KubernetesSecreteWatcher<'a> {
                 let mut parser = parser.clone();
                 let notify_tx = notify_tx.clone();
                 let parsed_pki_data = parsed_pki_data.clone();
+                let passphrase_provider = passphrase_provider.clone();
+                let linter = linter.clone();
                 async move {
+                    if let Some(provider) = passphrase_provider {
+                        match provider.get_passphrase().await {
+                            Ok(passphrase) => parser.set_passphrase(Some(passphrase)),
+                            Err(err) => tracing::warn!("Failed to get passphrase: {}", err),
+                        }
+                    }
                     tracing::info!(
                         "PKI resource: {}, in namespace: {}",
                         p.name_any(),
                         p.namespace().unwrap_or_default()
                     );
-                    if let Some(data) = p.data.and_then(|d| d.get("data").cloned()) {
-                        let reader = Cursor::new(data.0);
+                    let pki_source = PkiSource::new(
+                        StoreKind::Kubernetes,
+                        format!("{}/{}", p.namespace().unwrap_or_default(), p.name_any()),
+                    )
+                    .with_version(p.resource_version().map(SourceVersion::ResourceVersion));
+                    let keystore = find_keystore(&p);
+                    let data = p.data.as_ref().and_then(|d| d.get("data").cloned());
+                    if data.is_some() || keystore.is_some() {
                         let mut temp_parsed_pki_data = ParsedPkiData::default();
-                        parser.parse_pem(&mut temp_parsed_pki_data, reader).unwrap();
+                        if let Some(data) = data {
+                            parser.set_source(Some(pki_source.clone().with_key("data")));
+                            let reader = Cursor::new(data.0);
+                            if let Err(err) = parser.parse_pem(&mut temp_parsed_pki_data, reader) {
+                                tracing::error!("Rejected update of {}: {}", p.name_any(), err);
+                                return Ok(());
+                            }
+                        }
+                        if let Some((key, keystore)) = keystore {
+                            parser.set_source(Some(pki_source.with_key(key)));
+                            if let Err(err) =
+                                parser.parse_pkcs12(&mut temp_parsed_pki_data, &keystore)
+                            {
+                                tracing::error!("Rejected update of {}: {}", p.name_any(), err);
+                                return Ok(());
+                            }
+                        }
+                        if let Some(linter) = linter {
+                            let mut identities = Identities::default();
+                            if let Err(err) =
+                                parser.parse_identity(&temp_parsed_pki_data, &mut identities)
+                            {
+                                tracing::error!("Rejected update of {}: {}", p.name_any(), err);
+                                return Ok(());
+                            }
+                            let report = linter.lint_reload(&temp_parsed_pki_data, &identities);
+                            for finding in &report.findings {
+                                tracing::warn!("{}", finding);
+                            }
+                            if let Err(err) = report.check() {
+                                tracing::error!("Rejected update of {}: {}", p.name_any(), err);
+                                return Ok(());
+                            }
+                        }
                         notify_tx
                             .send((temp_parsed_pki_data, parsed_pki_data.clone()))
                             .await
@@ -160,13 +265,45 @@ impl<'a> KubernetesSecreteWatcher<'a> {
         )
         .await
         .unwrap();
+        if let Some(provider) = &self.passphrase_provider {
+            self.parser
+                .set_passphrase(Some(provider.get_passphrase().await?));
+        }
+        let pki_source = PkiSource::new(
+            StoreKind::Kubernetes,
+            format!(
+                "{}/{}",
+                self.config.pki_kubernetes_namespace, self.config.get_pki_kubernetes_secret_name
+            ),
+        )
+        .with_version(
+            secret
+                .resource_version()
+                .map(SourceVersion::ResourceVersion),
+        );
+        let mut temp_parsed_pki = ParsedPkiData::default();
         for key in &self.config.get_pki_kubernetes_resource_keys {
-            let cursor = parse_kubernetes_secret(&secret, key.as_str()).unwrap();
-            let mut temp_parsed_pki = self.parsed_pki_data.deref().lock().await;
             self.parser
-                .parse_pem(temp_parsed_pki.borrow_mut(), cursor)
-                .unwrap()
+                .set_source(Some(pki_source.clone().with_key(key.as_str())));
+            let cursor = parse_kubernetes_secret(&secret, key.as_str())?;
+            self.parser
+                .parse(temp_parsed_pki.borrow_mut(), cursor.get_ref())?;
         }
+        if let Some(linter) = &self.linter {
+            let mut identities = Identities::default();
+            self.parser
+                .parse_identity(&temp_parsed_pki, &mut identities)?;
+            let report = linter.lint_reload(&temp_parsed_pki, &identities);
+            for finding in &report.findings {
+                tracing::warn!("{}", finding);
+            }
+            report.check()?;
+        }
+        self.parsed_pki_data
+            .deref()
+            .lock()
+            .await
+            .merge(&mut temp_parsed_pki);
         Ok(())
     }
 
@@ -174,3 +311,40 @@ impl<'a> KubernetesSecreteWatcher<'a> {
         self.parsed_pki_data.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::core::v1::Secret;
+    use k8s_openapi::ByteString;
+
+    use crate::store::kubernetes_store::find_keystore;
+
+    fn secret(data: &[(&str, &[u8])]) -> Secret {
+        Secret {
+            data: Some(
+                data.iter()
+                    .map(|(key, value)| (key.to_string(), ByteString(value.to_vec())))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_keystore_by_content() {
+        let keystore = include_bytes!("../../tests/data/cms.p12");
+        let bundle = secret(&[
+            ("data", include_bytes!("../../tests/data/cert.pem")),
+            ("bundle", keystore),
+        ]);
+        assert_eq!(
+            find_keystore(&bundle),
+            Some(("bundle".to_string(), keystore.to_vec()))
+        );
+        // The key name alone does not make a keystore.
+        let named = secret(&[("keystore.p12", include_bytes!("../../tests/data/cert.pem"))]);
+        assert_eq!(find_keystore(&named), None);
+    }
+}
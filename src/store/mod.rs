@@ -1,10 +1,4 @@
-use crate::store::kubernetes_store::KubernetesSecretWatcherError;
-use crate::ParsedPkiData;
 use std::fmt::Debug;
-use std::io::BufRead;
-use std::pin::Pin;
-use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
 
 #[cfg(feature = "file-store")]
 pub mod file_store;
@@ -12,7 +6,7 @@ pub mod file_store;
 pub mod kubernetes_store;
 #[cfg(feature = "spiffe-store")]
 pub mod spiffe_store;
-pub trait PkiWatcherEventHandler<E> : Send {
+pub trait PkiWatcherEventHandler<E>: Send {
     fn handle_event(&mut self, event: E);
 }
 
@@ -22,31 +16,27 @@ pub trait PkiStore {
     fn get(&self) -> Result<Self::PkiData, Self::Error>;
 }
 
-
-
 pub trait PkiWatchers<'a> {
     type Error: Debug;
 
+    #[allow(async_fn_in_trait)]
     async fn watch<E>(
-        self: &mut Self,
+        &mut self,
         watcher_event: &mut impl PkiWatcherEventHandler<E>,
     ) -> Result<(), Self::Error>;
 }
 
 pub trait PkiRetrievers {
     type Error: Debug;
+    #[allow(async_fn_in_trait)]
     async fn retrieve(&mut self) -> Result<(), Self::Error>;
 }
 
 #[cfg(test)]
 mod tests {
-    use std::pin::pin;
-
-    use kube::runtime::watcher::Config;
     //use kubernetes_mock::make_mocker;
 
     use crate::configuration::KubernetesPkiStoreConfiguration;
-    use crate::store::kubernetes_store::KubernetesSecreteWatcher;
 
     pub struct StoreConfiguration {
         pub pki_kubernetes_namespace: String,
@@ -0,0 +1,141 @@
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rustls_pki_types::UnixTime;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::time::ASN1Time;
+
+/// The source of the current time for validation, injectable to test expiry deterministically.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The wall clock of the host.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FixedClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+fn unix_timestamp(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    }
+}
+
+/// 0000-01-01T00:00:00Z and 9999-12-31T23:59:59Z, the range of [`ASN1Time`].
+const MIN_ASN1_TIMESTAMP: i64 = -62_167_219_200;
+const MAX_ASN1_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Times outside the range of [`ASN1Time`] are clamped to it.
+pub fn to_asn1_time(time: SystemTime) -> ASN1Time {
+    let timestamp = unix_timestamp(time).clamp(MIN_ASN1_TIMESTAMP, MAX_ASN1_TIMESTAMP);
+    ASN1Time::from_timestamp(timestamp).expect("timestamp within the range of ASN1Time")
+}
+
+pub fn to_unix_time(time: SystemTime) -> UnixTime {
+    UnixTime::since_unix_epoch(time.duration_since(UNIX_EPOCH).unwrap_or_default())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidityStatus {
+    Valid,
+    NotYetValid,
+    Expired,
+}
+
+/// The validity of the certificate at `now`, accepting a clock skew of `tolerance` at both ends of the validity period.
+pub fn validity_status(
+    certificate: &X509Certificate,
+    now: ASN1Time,
+    tolerance: Duration,
+) -> ValidityStatus {
+    let validity = certificate.validity();
+    let now = now.timestamp();
+    let tolerance = tolerance.as_secs() as i64;
+    if now + tolerance < validity.not_before.timestamp() {
+        ValidityStatus::NotYetValid
+    } else if now - tolerance > validity.not_after.timestamp() {
+        ValidityStatus::Expired
+    } else {
+        ValidityStatus::Valid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::validate::clock::{
+        to_asn1_time, validity_status, Clock, FixedClock, ValidityStatus,
+    };
+
+    #[test]
+    fn test_validity_with_skew_tolerance() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../../tests/data/ct-leaf.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        let not_before = certificate.validity().not_before.timestamp() as u64;
+        let not_after = certificate.validity().not_after.timestamp() as u64;
+
+        let clock = FixedClock::new(UNIX_EPOCH + Duration::from_secs(not_before - 60));
+        let status = |tolerance| {
+            validity_status(
+                &certificate,
+                to_asn1_time(clock.now()),
+                Duration::from_secs(tolerance),
+            )
+        };
+        assert_eq!(status(0), ValidityStatus::NotYetValid);
+        assert_eq!(status(300), ValidityStatus::Valid);
+
+        clock.set(UNIX_EPOCH + Duration::from_secs(not_after));
+        assert_eq!(status(0), ValidityStatus::Valid);
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(status(0), ValidityStatus::Expired);
+        assert_eq!(status(300), ValidityStatus::Valid);
+    }
+
+    #[test]
+    fn test_to_asn1_time_clamps() {
+        let far_future = UNIX_EPOCH + Duration::from_secs(400_000_000_000);
+        assert_eq!(to_asn1_time(far_future).timestamp(), 253_402_300_799);
+        let far_past = UNIX_EPOCH - Duration::from_secs(100_000_000_000);
+        assert_eq!(to_asn1_time(far_past).timestamp(), -62_167_219_200);
+        assert_eq!(to_asn1_time(UNIX_EPOCH).timestamp(), 0);
+    }
+}
@@ -0,0 +1,358 @@
+use std::cmp::Ordering;
+
+use der::asn1::UintRef;
+use der::Decode;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::Oid;
+use x509_parser::revocation_list::CertificateRevocationList;
+use x509_parser::time::ASN1Time;
+
+use crate::generic_private_key::trim_leading_zeros;
+
+/// id-ce-cRLNumber, RFC 5280 5.2.3
+const CRL_NUMBER_OID: &[u64] = &[2, 5, 29, 20];
+/// id-ce-deltaCRLIndicator, RFC 5280 5.2.4
+const DELTA_CRL_INDICATOR_OID: &[u64] = &[2, 5, 29, 27];
+
+/// CRLReason, RFC 5280 5.3.1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    /// Only found in delta CRLs, the certificate was taken off hold.
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+    Other(u8),
+}
+
+impl From<u8> for RevocationReason {
+    fn from(code: u8) -> Self {
+        match code {
+            0 => RevocationReason::Unspecified,
+            1 => RevocationReason::KeyCompromise,
+            2 => RevocationReason::CaCompromise,
+            3 => RevocationReason::AffiliationChanged,
+            4 => RevocationReason::Superseded,
+            5 => RevocationReason::CessationOfOperation,
+            6 => RevocationReason::CertificateHold,
+            8 => RevocationReason::RemoveFromCrl,
+            9 => RevocationReason::PrivilegeWithdrawn,
+            10 => RevocationReason::AaCompromise,
+            code => RevocationReason::Other(code),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RevocationStatus {
+    Good,
+    Revoked {
+        reason: Option<RevocationReason>,
+        revocation_date: ASN1Time,
+    },
+    /// No complete CRL of the issuer is available.
+    Unknown,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum CrlError {
+    #[error("InvalidCrl")]
+    InvalidCrl,
+    /// The CRL issuer's key usage doesn't allow signing CRLs.
+    #[error("IssuerNotAuthorized")]
+    IssuerNotAuthorized,
+    #[error("InvalidCrlSignature")]
+    InvalidCrlSignature,
+    #[error("CrlNotYetValid")]
+    CrlNotYetValid,
+    /// The CRL's nextUpdate has passed.
+    #[error("CrlExpired")]
+    CrlExpired,
+    /// There are CRLs of the issuer, but not its certificate to verify them with.
+    #[error("IssuerNotFound")]
+    IssuerNotFound,
+}
+
+fn extension_uint<'a>(crl: &'a CertificateRevocationList, oid: &[u64]) -> Option<&'a [u8]> {
+    let oid = Oid::from(oid).ok()?;
+    let extension = crl.extensions().iter().find(|x| x.oid == oid)?;
+    UintRef::from_der(extension.value)
+        .ok()
+        .map(|value| trim_leading_zeros(value.as_bytes()))
+}
+
+/// Compares unsigned big-endian integers without leading zeros.
+fn compare_uint(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+pub fn crl_number<'a>(crl: &'a CertificateRevocationList) -> Option<&'a [u8]> {
+    extension_uint(crl, CRL_NUMBER_OID)
+}
+
+/// The BaseCRLNumber of a delta CRL, `None` for complete CRLs.
+pub fn delta_crl_base<'a>(crl: &'a CertificateRevocationList) -> Option<&'a [u8]> {
+    extension_uint(crl, DELTA_CRL_INDICATOR_OID)
+}
+
+/// Checks that `issuer` signed the CRL and may sign CRLs, and that `now` is within thisUpdate..nextUpdate.
+pub fn verify_crl(
+    crl: &CertificateRevocationList,
+    issuer: &X509Certificate,
+    now: ASN1Time,
+) -> Result<(), CrlError> {
+    if let Ok(Some(key_usage)) = issuer.key_usage() {
+        if !key_usage.value.crl_sign() {
+            return Err(CrlError::IssuerNotAuthorized);
+        }
+    }
+    x509_parser::verify::verify_signature(
+        issuer.public_key(),
+        &crl.signature_algorithm,
+        &crl.signature_value,
+        crl.tbs_cert_list.as_ref(),
+    )
+    .map_err(|_| CrlError::InvalidCrlSignature)?;
+    if crl.last_update() > now {
+        return Err(CrlError::CrlNotYetValid);
+    }
+    if crl
+        .next_update()
+        .is_some_and(|next_update| next_update < now)
+    {
+        return Err(CrlError::CrlExpired);
+    }
+    Ok(())
+}
+
+fn revoked_entry(
+    crl: &CertificateRevocationList,
+    certificate: &X509Certificate,
+) -> Option<RevocationStatus> {
+    crl.iter_revoked_certificates()
+        .find(|revoked| revoked.raw_serial() == certificate.raw_serial())
+        .map(|revoked| RevocationStatus::Revoked {
+            reason: revoked
+                .reason_code()
+                .map(|(_, reason)| RevocationReason::from(reason.0)),
+            revocation_date: revoked.revocation_date,
+        })
+}
+
+/// Checks the certificate against the CRLs of its issuer. CRLs that don't verify, e.g. stale ones kept next to
+/// their successor, are skipped. The complete CRL with the highest CRL number is used, together with the newest
+/// delta CRL based on it. Fails with the verification error only if no CRL of the issuer verifies.
+/// Indirect CRLs are not supported, only CRLs issued by the certificate's issuer are considered.
+pub fn check_revocation(
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+    crls: &[CertificateRevocationList],
+    now: ASN1Time,
+) -> Result<RevocationStatus, CrlError> {
+    let mut complete: Option<&CertificateRevocationList> = None;
+    let mut deltas = Vec::new();
+    let mut error = None;
+    for crl in crls
+        .iter()
+        .filter(|crl| crl.issuer() == certificate.issuer())
+    {
+        if let Err(err) = verify_crl(crl, issuer, now) {
+            error = Some(err);
+            continue;
+        }
+        if delta_crl_base(crl).is_some() {
+            deltas.push(crl);
+            continue;
+        }
+        let newer = match complete {
+            Some(current) => compare_uint(
+                crl_number(crl).unwrap_or_default(),
+                crl_number(current).unwrap_or_default(),
+            )
+            .then_with(|| crl.last_update().cmp(&current.last_update()))
+            .is_gt(),
+            None => true,
+        };
+        if newer {
+            complete = Some(crl);
+        }
+    }
+    let Some(complete) = complete else {
+        return match error {
+            Some(err) if deltas.is_empty() => Err(err),
+            _ => Ok(RevocationStatus::Unknown),
+        };
+    };
+    let complete_number = crl_number(complete).unwrap_or_default();
+    // A delta CRL applies if its base is no newer than the complete CRL, RFC 5280 5.2.4.
+    let delta = deltas
+        .into_iter()
+        .filter(|delta| {
+            delta_crl_base(delta).is_some_and(|base| compare_uint(base, complete_number).is_le())
+                && compare_uint(crl_number(delta).unwrap_or_default(), complete_number).is_gt()
+        })
+        .max_by(|a, b| {
+            compare_uint(
+                crl_number(a).unwrap_or_default(),
+                crl_number(b).unwrap_or_default(),
+            )
+        });
+    if let Some(status) = delta.and_then(|delta| revoked_entry(delta, certificate)) {
+        return Ok(match status {
+            RevocationStatus::Revoked {
+                reason: Some(RevocationReason::RemoveFromCrl),
+                ..
+            } => RevocationStatus::Good,
+            status => status,
+        });
+    }
+    Ok(revoked_entry(complete, certificate).unwrap_or(RevocationStatus::Good))
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_parser::pem::Pem;
+    use x509_parser::prelude::FromDer;
+    use x509_parser::revocation_list::CertificateRevocationList;
+    use x509_parser::time::ASN1Time;
+
+    use crate::validate::crl::{
+        check_revocation, crl_number, delta_crl_base, verify_crl, CrlError, RevocationReason,
+        RevocationStatus,
+    };
+
+    fn pems(data: &[u8]) -> Vec<Pem> {
+        Pem::iter_from_buffer(data)
+            .map(|pem| pem.unwrap())
+            .collect()
+    }
+
+    fn time(timestamp: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_check_revocation() {
+        let ca = pems(include_bytes!("../../tests/data/revocation-ca.pem"));
+        let ca = ca[0].parse_x509().unwrap();
+        let leaves = pems(include_bytes!("../../tests/data/revocation-leaves.pem"));
+        let leaves: Vec<_> = leaves.iter().map(|pem| pem.parse_x509().unwrap()).collect();
+        let crls = pems(include_bytes!("../../tests/data/revocation-base.crl"))
+            .into_iter()
+            .chain(pems(include_bytes!(
+                "../../tests/data/revocation-delta.crl"
+            )))
+            .collect::<Vec<_>>();
+        let crls: Vec<_> = crls
+            .iter()
+            .map(|pem| {
+                CertificateRevocationList::from_der(&pem.contents)
+                    .unwrap()
+                    .1
+            })
+            .collect();
+        // 2024-01-15
+        let now = time(1_705_276_800);
+
+        assert!(matches!(
+            check_revocation(&leaves[0], &ca, &crls, now),
+            Ok(RevocationStatus::Revoked {
+                reason: Some(RevocationReason::KeyCompromise),
+                ..
+            })
+        ));
+        // On hold in the complete CRL, removed by the delta CRL.
+        assert_eq!(
+            check_revocation(&leaves[1], &ca, &crls, now),
+            Ok(RevocationStatus::Good)
+        );
+        assert!(matches!(
+            check_revocation(&leaves[1], &ca, &crls[..1], now),
+            Ok(RevocationStatus::Revoked {
+                reason: Some(RevocationReason::CertificateHold),
+                ..
+            })
+        ));
+        assert_eq!(
+            check_revocation(&leaves[2], &ca, &crls, now),
+            Ok(RevocationStatus::Good)
+        );
+        assert_eq!(
+            check_revocation(&leaves[2], &ca, &crls[1..], now),
+            Ok(RevocationStatus::Unknown)
+        );
+        // 2024-03-01, past nextUpdate.
+        assert_eq!(
+            check_revocation(&leaves[0], &ca, &crls, time(1_709_251_200)),
+            Err(CrlError::CrlExpired)
+        );
+    }
+
+    #[test]
+    fn test_check_revocation_skips_invalid_crls() {
+        let ca = pems(include_bytes!("../../tests/data/revocation-ca.pem"));
+        let ca = ca[0].parse_x509().unwrap();
+        let leaves = pems(include_bytes!("../../tests/data/revocation-leaves.pem"));
+        let leaf = leaves[0].parse_x509().unwrap();
+        let base = pems(include_bytes!("../../tests/data/revocation-base.crl"));
+        let mut tampered = base[0].contents.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let (_, tampered) = CertificateRevocationList::from_der(&tampered).unwrap();
+        let (_, base) = CertificateRevocationList::from_der(&base[0].contents).unwrap();
+        // 2024-01-15
+        let now = time(1_705_276_800);
+
+        assert!(matches!(
+            check_revocation(&leaf, &ca, &[tampered.clone(), base], now),
+            Ok(RevocationStatus::Revoked {
+                reason: Some(RevocationReason::KeyCompromise),
+                ..
+            })
+        ));
+        assert_eq!(
+            check_revocation(&leaf, &ca, &[tampered], now),
+            Err(CrlError::InvalidCrlSignature)
+        );
+    }
+
+    #[test]
+    fn test_crl_entry_extensions() {
+        let crl = pems(include_bytes!("../../tests/data/entry_extensions.crl"));
+        let (_, crl) = CertificateRevocationList::from_der(&crl[0].contents).unwrap();
+        assert_eq!(crl_number(&crl), Some(&[20u8][..]));
+        assert_eq!(delta_crl_base(&crl), None);
+        let reasons: Vec<_> = crl
+            .iter_revoked_certificates()
+            .map(|revoked| {
+                revoked
+                    .reason_code()
+                    .map(|(_, reason)| RevocationReason::from(reason.0))
+            })
+            .collect();
+        assert_eq!(reasons, vec![Some(RevocationReason::KeyCompromise)]);
+    }
+
+    #[test]
+    fn test_verify_crl_signature() {
+        let ca = pems(include_bytes!("../../tests/data/crl-ca.crt"));
+        let ca = ca[0].parse_x509().unwrap();
+        let other_ca = pems(include_bytes!("../../tests/data/revocation-ca.pem"));
+        let other_ca = other_ca[0].parse_x509().unwrap();
+        let (_, crl) =
+            CertificateRevocationList::from_der(include_bytes!("../../tests/data/test.crl"))
+                .unwrap();
+        // 2019-07-01
+        let now = time(1_561_939_200);
+        assert_eq!(verify_crl(&crl, &ca, now), Ok(()));
+        assert_eq!(
+            verify_crl(&crl, &other_ca, now),
+            Err(CrlError::InvalidCrlSignature)
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+
+use rustls_pki_types::ServerName;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+
+/// A name a certificate is valid for, as presented in the Subject Alternative Name extension
+/// (or the subject common name when falling back to it).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SubjectName {
+    /// Lower-case DNS name without trailing dot.
+    Dns(String),
+    /// Lower-case wildcard DNS name such as `*.example.com`, only the left-most label can be a wildcard.
+    WildcardDns(String),
+    IpAddress(IpAddr),
+}
+
+impl SubjectName {
+    /// Parses a DNS-ID as presented in a certificate. Returns `None` for names that can never match
+    /// a reference identifier, such as partial wildcards (`f*.example.com`) or wildcards over a single-label
+    /// suffix like `*.com`. The public suffix list is not consulted, `*.co.uk` is accepted.
+    pub fn from_dns(name: &str) -> Option<Self> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        if name.is_empty() || name.split('.').any(|label| label.is_empty()) {
+            return None;
+        }
+        match name.strip_prefix("*.") {
+            Some(suffix) => {
+                if suffix.contains('*') || suffix.split('.').count() < 2 {
+                    return None;
+                }
+                Some(SubjectName::WildcardDns(name))
+            }
+            None if name.contains('*') => None,
+            None => Some(SubjectName::Dns(name)),
+        }
+    }
+
+    /// RFC 6125 section 6.4 matching of this presented identifier against a reference identifier.
+    pub fn matches(&self, server_name: &ServerName) -> bool {
+        match (self, server_name) {
+            (SubjectName::Dns(name), ServerName::DnsName(reference)) => {
+                *name == normalize_dns(reference.as_ref())
+            }
+            (SubjectName::WildcardDns(pattern), ServerName::DnsName(reference)) => {
+                let reference = normalize_dns(reference.as_ref());
+                let suffix = &pattern[1..];
+                match reference.split_once('.') {
+                    // The wildcard covers exactly one, non-empty, label. Internationalized labels are excluded (RFC 6125 6.4.3).
+                    Some((label, rest)) => {
+                        !label.is_empty()
+                            && !label.starts_with("xn--")
+                            && format!(".{}", rest) == suffix
+                    }
+                    None => false,
+                }
+            }
+            (SubjectName::IpAddress(ip), ServerName::IpAddress(reference)) => {
+                *ip == IpAddr::from(*reference)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        matches!(self, SubjectName::WildcardDns(_))
+    }
+
+    /// Returns the reference identifier for exact names, wildcards have none.
+    pub fn to_server_name(&self) -> Option<ServerName<'static>> {
+        match self {
+            SubjectName::Dns(name) => ServerName::try_from(name.clone()).ok(),
+            SubjectName::WildcardDns(_) => None,
+            SubjectName::IpAddress(ip) => Some(ServerName::IpAddress((*ip).into())),
+        }
+    }
+}
+
+impl Display for SubjectName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubjectName::Dns(name) | SubjectName::WildcardDns(name) => write!(f, "{}", name),
+            SubjectName::IpAddress(ip) => write!(f, "{}", ip),
+        }
+    }
+}
+
+pub(crate) fn normalize_dns(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+pub(crate) fn ip_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
+/// Returns the DNS and IP address names of the Subject Alternative Name extension.
+/// When the certificate has neither and `allow_common_name_fallback` is set, the subject common name is used (RFC 6125 6.4.4).
+pub fn certificate_subject_names(
+    certificate: &X509Certificate,
+    allow_common_name_fallback: bool,
+) -> Vec<SubjectName> {
+    let mut names = Vec::new();
+    if let Ok(Some(san)) = certificate.subject_alternative_name() {
+        for general_name in &san.value.general_names {
+            let name = match general_name {
+                GeneralName::DNSName(dns) => SubjectName::from_dns(dns),
+                GeneralName::IPAddress(ip) => ip_from_bytes(ip).map(SubjectName::IpAddress),
+                _ => None,
+            };
+            if let Some(name) = name {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    if names.is_empty() && allow_common_name_fallback {
+        if let Some(common_name) = certificate
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+        {
+            let name = match common_name.parse::<IpAddr>() {
+                Ok(ip) => Some(SubjectName::IpAddress(ip)),
+                Err(_) => SubjectName::from_dns(common_name),
+            };
+            names.extend(name);
+        }
+    }
+    names
+}
+
+/// Checks that the certificate is valid for the server name.
+pub fn matches_server_name(
+    certificate: &X509Certificate,
+    server_name: &ServerName,
+    allow_common_name_fallback: bool,
+) -> bool {
+    certificate_subject_names(certificate, allow_common_name_fallback)
+        .iter()
+        .any(|name| name.matches(server_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls_pki_types::ServerName;
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::validate::hostname::{certificate_subject_names, matches_server_name, SubjectName};
+
+    fn server_name(name: &str) -> ServerName<'static> {
+        ServerName::try_from(name.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_matching() {
+        let wildcard = SubjectName::from_dns("*.Example.com.").unwrap();
+        assert!(wildcard.matches(&server_name("www.example.com")));
+        assert!(wildcard.matches(&server_name("WWW.example.com.")));
+        assert!(!wildcard.matches(&server_name("example.com")));
+        assert!(!wildcard.matches(&server_name("a.b.example.com")));
+        assert!(!wildcard.matches(&server_name("xn--bcher-kva.example.com")));
+        assert_eq!(SubjectName::from_dns("*.com"), None);
+        assert_eq!(SubjectName::from_dns("f*.example.com"), None);
+        assert_eq!(SubjectName::from_dns("www.*.example.com"), None);
+    }
+
+    #[test]
+    fn test_subject_alternative_names() {
+        let (_, pem) =
+            parse_x509_pem(include_bytes!("../../tests/data/alt_name_cert.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        assert!(matches_server_name(
+            &certificate,
+            &server_name("example.com"),
+            false
+        ));
+        assert!(matches_server_name(
+            &certificate,
+            &server_name("127.0.0.1"),
+            false
+        ));
+        assert!(matches_server_name(
+            &certificate,
+            &server_name("::1"),
+            false
+        ));
+        assert!(!matches_server_name(
+            &certificate,
+            &server_name("www.example.com"),
+            true
+        ));
+    }
+
+    #[test]
+    fn test_common_name_fallback() {
+        let (_, pem) = parse_x509_pem(include_bytes!("../../tests/data/cert.pem")).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        assert!(certificate_subject_names(&certificate, false).is_empty());
+        assert!(!matches_server_name(
+            &certificate,
+            &server_name("foobar.com"),
+            false
+        ));
+        assert!(matches_server_name(
+            &certificate,
+            &server_name("foobar.com"),
+            true
+        ));
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+use crate::identity::{Identities as _, KeyAlgorithm};
+use crate::parser::parse::Identities;
+use crate::provenance::{PkiItem, Provenance};
+use crate::validate::validate::{is_issued_by, rsa_key_size, uses_sha1_signature};
+use crate::{Identity, ParsedPkiData};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    RsaKeySize,
+    KeyAlgorithm,
+    Sha1Signature,
+    ExtendedKeyUsage,
+    BasicConstraints,
+    MaxValidity,
+    KeyUsage,
+}
+
+impl LintRule {
+    fn default_severity(&self) -> Severity {
+        match self {
+            LintRule::RsaKeySize
+            | LintRule::KeyAlgorithm
+            | LintRule::Sha1Signature
+            | LintRule::ExtendedKeyUsage
+            | LintRule::BasicConstraints
+            | LintRule::KeyUsage => Severity::Error,
+            LintRule::MaxValidity => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtendedKeyUsage {
+    ServerAuth,
+    ClientAuth,
+}
+
+/// The organizational policy certificates are linted against.
+#[derive(Clone, Debug)]
+pub struct LintPolicy {
+    pub min_rsa_key_size: usize,
+    pub allowed_key_algorithms: Vec<KeyAlgorithm>,
+    /// Required on end-entity certificates.
+    pub required_extended_key_usages: Vec<ExtendedKeyUsage>,
+    /// Maximum validity period of end-entity certificates.
+    pub max_validity: Option<Duration>,
+    /// Overrides the default severity of a rule, `None` disables the rule.
+    pub severities: HashMap<LintRule, Option<Severity>>,
+}
+
+impl Default for LintPolicy {
+    fn default() -> Self {
+        Self {
+            min_rsa_key_size: 2048,
+            allowed_key_algorithms: vec![
+                KeyAlgorithm::EcdsaP256,
+                KeyAlgorithm::EcdsaP384,
+                KeyAlgorithm::Ed25519,
+                KeyAlgorithm::Rsa,
+            ],
+            required_extended_key_usages: vec![ExtendedKeyUsage::ServerAuth],
+            // The CA/Browser Forum limit for TLS server certificates.
+            max_validity: Some(Duration::from_secs(398 * 24 * 60 * 60)),
+            severities: HashMap::new(),
+        }
+    }
+}
+
+impl LintPolicy {
+    pub fn with_severity(mut self, rule: LintRule, severity: Option<Severity>) -> Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+
+    fn severity(&self, rule: LintRule) -> Option<Severity> {
+        match self.severities.get(&rule) {
+            Some(severity) => *severity,
+            None => Some(rule.default_severity()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub subject: String,
+    pub serial: String,
+    pub message: String,
+    pub provenance: Option<Provenance>,
+}
+
+impl Display for LintFinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} {:?}: {} (serial {}): {}",
+            self.severity, self.rule, self.subject, self.serial, self.message
+        )?;
+        if let Some(provenance) = &self.provenance {
+            write!(f, ", from {}", provenance)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LintError {
+    #[error("PolicyViolations: {}", .0.len())]
+    PolicyViolations(Vec<LintFinding>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+    }
+
+    /// Fails with the error findings, for rejecting a reload.
+    pub fn check(&self) -> Result<(), LintError> {
+        let errors: Vec<_> = self
+            .findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .cloned()
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(LintError::PolicyViolations(errors))
+        }
+    }
+}
+
+/// The role of the certificate, which decides the rules that apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    Ca,
+    EndEntity,
+}
+
+pub struct Linter {
+    policy: LintPolicy,
+}
+
+impl Linter {
+    pub fn new(policy: LintPolicy) -> Self {
+        Self { policy }
+    }
+
+    fn lint_certificate(
+        &self,
+        certificate: &X509Certificate,
+        role: Role,
+        provenance: Option<&Provenance>,
+        report: &mut LintReport,
+    ) {
+        let mut finding = |rule: LintRule, message: String| {
+            if let Some(severity) = self.policy.severity(rule) {
+                report.findings.push(LintFinding {
+                    rule,
+                    severity,
+                    subject: certificate.subject().to_string(),
+                    serial: certificate.raw_serial_as_string(),
+                    message,
+                    provenance: provenance.cloned(),
+                });
+            }
+        };
+        let self_signed = is_issued_by(certificate, certificate);
+
+        if let Some(bits) = rsa_key_size(certificate) {
+            if bits < self.policy.min_rsa_key_size {
+                finding(
+                    LintRule::RsaKeySize,
+                    format!(
+                        "RSA key of {} bits, at least {} required",
+                        bits, self.policy.min_rsa_key_size
+                    ),
+                );
+            }
+        }
+        let key_algorithm = KeyAlgorithm::from_public_key(certificate.public_key());
+        if !self.policy.allowed_key_algorithms.contains(&key_algorithm) {
+            finding(
+                LintRule::KeyAlgorithm,
+                format!("Key algorithm {:?} is not allowed", key_algorithm),
+            );
+        }
+        // The signature of a root is not relied upon.
+        if uses_sha1_signature(certificate) && !self_signed {
+            finding(LintRule::Sha1Signature, "Signed with SHA-1".to_string());
+        }
+
+        let basic_constraints = certificate.basic_constraints().ok().flatten();
+        let is_ca = basic_constraints.as_ref().is_some_and(|x| x.value.ca);
+        let key_usage = certificate.key_usage().ok().flatten().map(|x| *x.value);
+        match role {
+            Role::Ca => {
+                if !is_ca {
+                    finding(
+                        LintRule::BasicConstraints,
+                        "Issues certificates without basicConstraints CA:TRUE".to_string(),
+                    );
+                } else if basic_constraints.as_ref().is_some_and(|x| !x.critical) {
+                    finding(
+                        LintRule::BasicConstraints,
+                        "basicConstraints of a CA must be critical".to_string(),
+                    );
+                }
+                if key_usage.is_some_and(|key_usage| !key_usage.key_cert_sign()) {
+                    finding(LintRule::KeyUsage, "CA without keyCertSign".to_string());
+                }
+            }
+            Role::EndEntity => {
+                if is_ca {
+                    finding(
+                        LintRule::BasicConstraints,
+                        "End-entity certificate with basicConstraints CA:TRUE".to_string(),
+                    );
+                }
+                if let Some(key_usage) = key_usage {
+                    if key_usage.key_cert_sign() || key_usage.crl_sign() {
+                        finding(
+                            LintRule::KeyUsage,
+                            "End-entity certificate allowed to sign certificates or CRLs"
+                                .to_string(),
+                        );
+                    }
+                    if !key_usage.digital_signature() && !key_usage.key_encipherment() {
+                        finding(
+                            LintRule::KeyUsage,
+                            "Neither digitalSignature nor keyEncipherment".to_string(),
+                        );
+                    }
+                    if key_usage.key_encipherment() && key_algorithm != KeyAlgorithm::Rsa {
+                        finding(
+                            LintRule::KeyUsage,
+                            "keyEncipherment requires an RSA key".to_string(),
+                        );
+                    }
+                }
+                let extended_key_usage = certificate.extended_key_usage().ok().flatten();
+                for required in &self.policy.required_extended_key_usages {
+                    let present = extended_key_usage
+                        .as_ref()
+                        .is_some_and(|eku| match required {
+                            ExtendedKeyUsage::ServerAuth => eku.value.server_auth,
+                            ExtendedKeyUsage::ClientAuth => eku.value.client_auth,
+                        });
+                    if !present {
+                        finding(
+                            LintRule::ExtendedKeyUsage,
+                            format!("Missing extendedKeyUsage {:?}", required),
+                        );
+                    }
+                }
+                if let Some(max_validity) = self.policy.max_validity {
+                    let validity = certificate.validity();
+                    let lifetime = validity.not_after.timestamp() - validity.not_before.timestamp();
+                    if lifetime > max_validity.as_secs() as i64 {
+                        finding(
+                            LintRule::MaxValidity,
+                            format!(
+                                "Valid for {} days, at most {} allowed",
+                                lifetime / (24 * 60 * 60),
+                                max_validity.as_secs() / (24 * 60 * 60)
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lints every certificate of the parsed data. CA certificates and certificates issuing another certificate of the
+    /// data are linted as CA, the others as end-entity.
+    pub fn lint_pki_data(&self, pki_data: &ParsedPkiData) -> LintReport {
+        let mut report = LintReport::default();
+        // Indexed before parsing, so unparsable certificates don't shift the provenance of the others.
+        let certificates: Vec<_> = pki_data
+            .x509
+            .iter()
+            .enumerate()
+            .filter_map(|(index, der)| {
+                X509Certificate::from_der(der)
+                    .ok()
+                    .map(|(_, certificate)| (index, certificate))
+            })
+            .collect();
+        // Signatures are only checked for certificates whose subject issues another certificate.
+        let issuer_names: HashSet<&[u8]> = certificates
+            .iter()
+            .map(|(_, certificate)| certificate.issuer().as_raw())
+            .collect();
+        for (index, certificate) in &certificates {
+            let index = *index;
+            let issues_others = issuer_names.contains(certificate.subject().as_raw())
+                && certificates.iter().any(|(other_index, other)| {
+                    *other_index != index && is_issued_by(other, certificate)
+                });
+            let is_ca = certificate.is_ca();
+            let role = if issues_others || is_ca {
+                Role::Ca
+            } else {
+                Role::EndEntity
+            };
+            let provenance = pki_data.provenance_of(PkiItem::Certificate(index));
+            self.lint_certificate(certificate, role, provenance, &mut report);
+        }
+        report
+    }
+
+    /// Lints the chain of the identity, the leaf as end-entity and the others as CA.
+    pub fn lint_identity(&self, identity: &Identity) -> LintReport {
+        let mut report = LintReport::default();
+        for (index, certificate) in identity.get_certificate_chain().iter().enumerate() {
+            let (role, provenance) = if index == 0 {
+                (Role::EndEntity, identity.certificate_provenance.as_ref())
+            } else {
+                (Role::Ca, None)
+            };
+            self.lint_certificate(certificate, role, provenance, &mut report);
+        }
+        report
+    }
+
+    /// Lints the data of a reload, see [`Linter::lint_pki_data`], and the chains of the identities built from it, see
+    /// [`Linter::lint_identity`]. A finding already reported for a certificate is not repeated.
+    pub fn lint_reload(&self, pki_data: &ParsedPkiData, identities: &Identities) -> LintReport {
+        let mut report = self.lint_pki_data(pki_data);
+        for identity in identities.iter() {
+            for finding in self.lint_identity(identity).findings {
+                let reported = report.findings.iter().any(|reported| {
+                    reported.rule == finding.rule
+                        && reported.serial == finding.serial
+                        && reported.message == finding.message
+                });
+                if !reported {
+                    report.findings.push(finding);
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use crate::parser::parse::Identities;
+    use crate::parser::parse::PkiParser;
+    use crate::parser::IdentityParser;
+    use crate::validate::lint::{LintPolicy, LintRule, Linter, Severity};
+    use crate::ParsedPkiData;
+
+    fn parse(pems: &[&[u8]]) -> ParsedPkiData<'static> {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        for pem in pems {
+            PkiParser::new()
+                .parse_pem(&mut parsed_pki_data, Cursor::new(pem))
+                .unwrap();
+        }
+        parsed_pki_data
+    }
+
+    #[test]
+    fn test_lint_pki_data() {
+        let parsed_pki_data = parse(&[
+            include_bytes!("../../tests/data/ocsp-ca.pem"),
+            include_bytes!("../../tests/data/ocsp-leaf.pem"),
+        ]);
+        let report = Linter::new(LintPolicy::default()).lint_pki_data(&parsed_pki_data);
+        // The leaf is valid for 10 years.
+        let rules: Vec<_> = report.findings.iter().map(|finding| finding.rule).collect();
+        assert_eq!(rules, vec![LintRule::MaxValidity]);
+        assert_eq!(report.findings[0].severity, Severity::Warning);
+        assert!(report.check().is_ok());
+
+        let policy = LintPolicy {
+            max_validity: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+            ..LintPolicy::default()
+        }
+        .with_severity(LintRule::MaxValidity, Some(Severity::Error));
+        let report = Linter::new(policy).lint_pki_data(&parsed_pki_data);
+        assert!(report.has_errors());
+        assert!(report.check().is_err());
+    }
+
+    #[test]
+    fn test_lint_pki_data_provenance() {
+        // The first block doesn't parse as a certificate, the leaf's finding must point at the third.
+        let mut bundle = b"-----BEGIN CERTIFICATE-----\nMAA=\n-----END CERTIFICATE-----\n".to_vec();
+        bundle.extend_from_slice(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        bundle.extend_from_slice(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let parsed_pki_data = parse(&[&bundle]);
+        let report = Linter::new(LintPolicy::default()).lint_pki_data(&parsed_pki_data);
+        assert_eq!(report.findings.len(), 1);
+        let provenance = report.findings[0].provenance.as_ref().unwrap();
+        assert_eq!(provenance.block_index, Some(2));
+    }
+
+    #[test]
+    fn test_lint_missing_extended_key_usage() {
+        // The delegated OCSP responder only has the OCSPSigning extendedKeyUsage.
+        let parsed_pki_data = parse(&[
+            include_bytes!("../../tests/data/ocsp-ca.pem"),
+            include_bytes!("../../tests/data/ocsp-responder.pem"),
+        ]);
+        let report = Linter::new(LintPolicy::default().with_severity(LintRule::MaxValidity, None))
+            .lint_pki_data(&parsed_pki_data);
+        let rules: Vec<_> = report.findings.iter().map(|finding| finding.rule).collect();
+        assert_eq!(rules, vec![LintRule::ExtendedKeyUsage]);
+    }
+
+    #[test]
+    fn test_lint_reload() {
+        // A self-signed certificate with basicConstraints CA:TRUE, served with its key.
+        let parsed_pki_data = parse(&[
+            include_bytes!("../../tests/data/ed25519.pem"),
+            include_bytes!("../../tests/data/ed25519.key"),
+        ]);
+        let pki_parser = PkiParser::new();
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        let linter = Linter::new(LintPolicy::default().with_severity(LintRule::MaxValidity, None));
+
+        // As part of the data it's linted as CA.
+        let report = linter.lint_pki_data(&parsed_pki_data);
+        assert!(!report
+            .findings
+            .iter()
+            .any(|finding| finding.rule == LintRule::BasicConstraints));
+        // As the certificate of an identity it's linted as end-entity.
+        let report = linter.lint_reload(&parsed_pki_data, &identities);
+        let basic_constraints: Vec<_> = report
+            .findings
+            .iter()
+            .filter(|finding| finding.rule == LintRule::BasicConstraints)
+            .collect();
+        assert_eq!(basic_constraints.len(), 1);
+        assert_eq!(
+            basic_constraints[0].message,
+            "End-entity certificate with basicConstraints CA:TRUE"
+        );
+        assert!(report.check().is_err());
+    }
+}
@@ -1,3 +1,18 @@
+use std::time::Duration;
+
+use crate::validate::ocsp::OcspFailurePolicy;
+
+pub mod clock;
+pub mod crl;
+pub mod hostname;
+pub mod lint;
+pub mod name_constraints;
+pub mod ocsp;
+pub mod openssh;
+pub mod report;
+pub mod sct;
+pub mod trust;
+#[allow(clippy::module_inception)]
 pub mod validate;
 
 pub trait PkiValidatorConfiguration {
@@ -9,5 +24,30 @@ pub trait PkiValidatorConfiguration {
 
     fn get_validate_certificate_chain(&self) -> bool;
 
+    /// Match the subject common name when the certificate has no DNS or IP address Subject Alternative Name.
+    fn get_allow_common_name_fallback(&self) -> bool {
+        false
+    }
+
+    /// Reject certificates of the chain listed in a CRL of their issuer.
+    fn get_check_revocation(&self) -> bool {
+        false
+    }
+
+    /// Whether certificates without a usable OCSP response are accepted.
+    fn get_ocsp_failure_policy(&self) -> OcspFailurePolicy {
+        OcspFailurePolicy::default()
+    }
+
+    /// Require SCTs of enough distinct trusted CT logs, for public-facing certificates.
+    fn get_require_certificate_transparency(&self) -> bool {
+        false
+    }
+
+    /// Accepted clock skew at both ends of the validity periods.
+    fn get_clock_skew_tolerance(&self) -> Duration {
+        Duration::ZERO
+    }
+
     fn get_domain(&self) -> String;
 }
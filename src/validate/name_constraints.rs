@@ -0,0 +1,377 @@
+use std::net::IpAddr;
+
+use rustls_pki_types::ServerName;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, GeneralSubtree};
+
+use crate::validate::hostname::{ip_from_bytes, normalize_dns};
+use crate::validate::validate::{is_issued_by, ValidateCertificateError};
+
+/// A name of a certificate subject to the name constraints of its issuers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstrainedName {
+    Dns(String),
+    IpAddress(IpAddr),
+    Email(String),
+    Uri(String),
+}
+
+impl ConstrainedName {
+    fn from_general_name(general_name: &GeneralName) -> Option<Self> {
+        match general_name {
+            GeneralName::DNSName(dns) => Some(ConstrainedName::Dns(normalize_dns(dns))),
+            GeneralName::IPAddress(ip) => ip_from_bytes(ip).map(ConstrainedName::IpAddress),
+            GeneralName::RFC822Name(email) => {
+                Some(ConstrainedName::Email(email.to_ascii_lowercase()))
+            }
+            GeneralName::URI(uri) => Some(ConstrainedName::Uri(uri.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Whether the name is within the subtree. `None` when the subtree constrains another name type.
+    fn within(&self, subtree: &GeneralName) -> Option<bool> {
+        match (self, subtree) {
+            (ConstrainedName::Dns(name), GeneralName::DNSName(base)) => {
+                // A wildcard is within the subtree if every name it covers is.
+                let name = match name.strip_prefix("*.") {
+                    Some(suffix) => format!("x.{}", suffix),
+                    None => name.clone(),
+                };
+                Some(dns_within(&name, base))
+            }
+            (ConstrainedName::IpAddress(ip), GeneralName::IPAddress(base)) => {
+                Some(ip_within(ip, base))
+            }
+            (ConstrainedName::Email(email), GeneralName::RFC822Name(base)) => {
+                Some(email_within(email, base))
+            }
+            (ConstrainedName::Uri(uri), GeneralName::URI(base)) => {
+                Some(uri_host(uri).is_some_and(|host| host_within(&host, base)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the name overlaps the excluded subtree, a wildcard overlaps a subtree below it.
+    fn overlaps(&self, subtree: &GeneralName) -> Option<bool> {
+        match (self, subtree) {
+            (ConstrainedName::Dns(name), GeneralName::DNSName(base)) => match name
+                .strip_prefix("*.")
+            {
+                Some(suffix) => {
+                    let base = normalize_dns(base.trim_start_matches('.'));
+                    Some(dns_within(&base, suffix) || dns_within(&format!("x.{}", suffix), &base))
+                }
+                None => Some(dns_within(name, base)),
+            },
+            _ => self.within(subtree),
+        }
+    }
+}
+
+impl std::fmt::Display for ConstrainedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstrainedName::Dns(name) => write!(f, "DNS:{}", name),
+            ConstrainedName::IpAddress(ip) => write!(f, "IP:{}", ip),
+            ConstrainedName::Email(email) => write!(f, "email:{}", email),
+            ConstrainedName::Uri(uri) => write!(f, "URI:{}", uri),
+        }
+    }
+}
+
+/// `example.com` covers the domain and its subdomains, `.example.com` only the subdomains (RFC 5280 4.2.1.10).
+fn dns_within(name: &str, base: &str) -> bool {
+    let base = normalize_dns(base);
+    if base.is_empty() {
+        return true;
+    }
+    match base.strip_prefix('.') {
+        Some(_) => name.ends_with(&base),
+        None => name == base || name.ends_with(&format!(".{}", base)),
+    }
+}
+
+/// The subtree is the address followed by the mask.
+fn ip_within(ip: &IpAddr, base: &[u8]) -> bool {
+    let ip = match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    };
+    if base.len() != ip.len() * 2 {
+        return false;
+    }
+    let (address, mask) = base.split_at(ip.len());
+    ip.iter()
+        .zip(address)
+        .zip(mask)
+        .all(|((ip, address), mask)| ip & mask == address & mask)
+}
+
+/// A mailbox, all mailboxes of a host, or with a leading dot all mailboxes of the subdomains of a host.
+fn email_within(email: &str, base: &str) -> bool {
+    let base = base.to_ascii_lowercase();
+    if base.contains('@') {
+        return email == base;
+    }
+    match email.rsplit_once('@') {
+        Some((_, host)) => host_within(host, &base),
+        None => false,
+    }
+}
+
+fn host_within(host: &str, base: &str) -> bool {
+    let host = normalize_dns(host);
+    let base = normalize_dns(base);
+    if base.starts_with('.') {
+        host.ends_with(&base)
+    } else {
+        host == base
+    }
+}
+
+/// The host of the authority of the URI, URIs without one violate URI constraints.
+fn uri_host(uri: &str) -> Option<String> {
+    let (_, rest) = uri.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if host.starts_with('[') {
+        host.split_once(']').map(|(host, _)| &host[1..])?
+    } else {
+        host.split(':').next()?
+    };
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// The Subject Alternative Names and subject email addresses of the certificate. Like webpki, the subject common name
+/// of an end-entity certificate without DNS or IP address SANs is subject to DNS constraints when it's a DNS name.
+pub fn constrained_names(certificate: &X509Certificate) -> Vec<ConstrainedName> {
+    let mut names = Vec::new();
+    if let Ok(Some(san)) = certificate.subject_alternative_name() {
+        names.extend(
+            san.value
+                .general_names
+                .iter()
+                .filter_map(ConstrainedName::from_general_name),
+        );
+    }
+    let has_host_names = names.iter().any(|name| {
+        matches!(
+            name,
+            ConstrainedName::Dns(_) | ConstrainedName::IpAddress(_)
+        )
+    });
+    if !has_host_names && !certificate.is_ca() {
+        names.extend(
+            certificate
+                .subject()
+                .iter_common_name()
+                .filter_map(|common_name| common_name.as_str().ok())
+                .filter(|common_name| is_dns_name(common_name))
+                .map(|common_name| ConstrainedName::Dns(normalize_dns(common_name))),
+        );
+    }
+    names.extend(
+        certificate
+            .subject()
+            .iter_email()
+            .filter_map(|email| email.as_str().ok())
+            .map(|email| ConstrainedName::Email(email.to_ascii_lowercase())),
+    );
+    names
+}
+
+/// Whether the common name is a DNS name or a wildcard, rather than a label such as `My Service`.
+fn is_dns_name(common_name: &str) -> bool {
+    let name = common_name.strip_prefix("*.").unwrap_or(common_name);
+    matches!(ServerName::try_from(name), Ok(ServerName::DnsName(_)))
+}
+
+/// Checks the name against the subtrees of a name constraints extension. Name types without permitted subtrees are
+/// unconstrained.
+fn permitted_by(
+    name: &ConstrainedName,
+    permitted: &[GeneralSubtree],
+    excluded: &[GeneralSubtree],
+) -> bool {
+    if excluded
+        .iter()
+        .any(|subtree| name.overlaps(&subtree.base) == Some(true))
+    {
+        return false;
+    }
+    let mut results = permitted
+        .iter()
+        .filter_map(|subtree| name.within(&subtree.base))
+        .peekable();
+    results.peek().is_none() || results.any(|within| within)
+}
+
+/// Enforces the `nameConstraints` and `pathLenConstraint` of the CA certificates along the chain, ordered from the leaf
+/// to the root. Self-issued intermediates are neither subject to name constraints nor counted for the path length,
+/// the root's own names are not checked.
+pub fn verify_chain_constraints(chain: &[X509Certificate]) -> Result<(), ValidateCertificateError> {
+    for (index, ca) in chain.iter().enumerate().skip(1) {
+        let below = &chain[..index];
+        if let Ok(Some(basic_constraints)) = ca.basic_constraints() {
+            if let Some(path_len) = basic_constraints.value.path_len_constraint {
+                let intermediates = below
+                    .iter()
+                    .skip(1)
+                    .filter(|certificate| !is_issued_by(certificate, certificate))
+                    .count();
+                if intermediates > path_len as usize {
+                    return Err(ValidateCertificateError::PathLengthExceeded(
+                        ca.subject().to_string(),
+                        path_len,
+                    ));
+                }
+            }
+        }
+
+        let Ok(Some(name_constraints)) = ca.name_constraints() else {
+            continue;
+        };
+        let permitted = name_constraints
+            .value
+            .permitted_subtrees
+            .as_deref()
+            .unwrap_or_default();
+        let excluded = name_constraints
+            .value
+            .excluded_subtrees
+            .as_deref()
+            .unwrap_or_default();
+        for (position, certificate) in below.iter().enumerate() {
+            if position > 0 && is_issued_by(certificate, certificate) {
+                continue;
+            }
+            if let Some(name) = constrained_names(certificate)
+                .into_iter()
+                .find(|name| !permitted_by(name, permitted, excluded))
+            {
+                return Err(ValidateCertificateError::NameConstraintViolation(
+                    name.to_string(),
+                    ca.subject().to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_parser::certificate::X509Certificate;
+    use x509_parser::pem::{parse_x509_pem, Pem};
+
+    use crate::validate::name_constraints::{
+        constrained_names, uri_host, verify_chain_constraints, ConstrainedName,
+    };
+    use crate::validate::validate::ValidateCertificateError;
+
+    fn pems(data: &[&[u8]]) -> Vec<Pem> {
+        data.iter()
+            .map(|pem| parse_x509_pem(pem).unwrap().1)
+            .collect()
+    }
+
+    fn chain(pems: &[Pem]) -> Vec<X509Certificate> {
+        pems.iter().map(|pem| pem.parse_x509().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_name_constraints() {
+        let intermediate = include_bytes!("../../tests/data/nc-int.pem");
+        let root = include_bytes!("../../tests/data/nc-root.pem");
+        let ok = pems(&[
+            include_bytes!("../../tests/data/nc-ok.pem"),
+            intermediate,
+            root,
+        ]);
+        assert!(verify_chain_constraints(&chain(&ok)).is_ok());
+
+        for (leaf, violating) in [
+            (
+                &include_bytes!("../../tests/data/nc-outside.pem")[..],
+                "DNS:www.example.com",
+            ),
+            (
+                include_bytes!("../../tests/data/nc-excluded.pem"),
+                "DNS:db.secret.example.internal",
+            ),
+            (
+                include_bytes!("../../tests/data/nc-badip.pem"),
+                "IP:192.168.1.1",
+            ),
+        ] {
+            let pems = pems(&[leaf, intermediate, root]);
+            match verify_chain_constraints(&chain(&pems)) {
+                Err(ValidateCertificateError::NameConstraintViolation(name, ca)) => {
+                    assert_eq!(name, violating);
+                    assert_eq!(ca, "CN=Constrained Test Intermediate");
+                }
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_length_constraint() {
+        let pems = pems(&[
+            include_bytes!("../../tests/data/nc-sub-leaf.pem"),
+            include_bytes!("../../tests/data/nc-sub.pem"),
+            include_bytes!("../../tests/data/nc-int.pem"),
+            include_bytes!("../../tests/data/nc-root.pem"),
+        ]);
+        let chain = chain(&pems);
+        assert!(matches!(
+            verify_chain_constraints(&chain),
+            Err(ValidateCertificateError::PathLengthExceeded(ca, 0)) if ca == "CN=Constrained Test Intermediate"
+        ));
+        assert!(verify_chain_constraints(&chain[1..]).is_ok());
+    }
+
+    #[test]
+    fn test_common_name_constraints() {
+        // The leaves have no Subject Alternative Name, the CA only permits example.internal.
+        let ca = include_bytes!("../../tests/data/nc-cn-ca.pem");
+        let ok = pems(&[include_bytes!("../../tests/data/nc-cn-ok.pem"), ca]);
+        assert_eq!(
+            constrained_names(&chain(&ok)[0]),
+            vec![ConstrainedName::Dns("svc.example.internal".to_string())]
+        );
+        assert!(verify_chain_constraints(&chain(&ok)).is_ok());
+
+        let outside = pems(&[include_bytes!("../../tests/data/nc-cn-outside.pem"), ca]);
+        assert!(matches!(
+            verify_chain_constraints(&chain(&outside)),
+            Err(ValidateCertificateError::NameConstraintViolation(name, _)) if name == "DNS:www.example.com"
+        ));
+
+        // Not a DNS name.
+        let name = pems(&[include_bytes!("../../tests/data/nc-cn-name.pem"), ca]);
+        assert!(constrained_names(&chain(&name)[0]).is_empty());
+        assert!(verify_chain_constraints(&chain(&name)).is_ok());
+
+        // With an IP address SAN the common name is not a constrained name.
+        let badip = pems(&[include_bytes!("../../tests/data/nc-badip.pem")]);
+        assert_eq!(
+            constrained_names(&chain(&badip)[0]),
+            vec![ConstrainedName::IpAddress("192.168.1.1".parse().unwrap())]
+        );
+    }
+
+    #[test]
+    fn test_uri_host() {
+        assert_eq!(
+            uri_host("https://user@api.example.internal:8443/v1").as_deref(),
+            Some("api.example.internal")
+        );
+        assert_eq!(uri_host("ldap://[::1]/o=x").as_deref(), Some("::1"));
+        assert_eq!(uri_host("urn:uuid:1234"), None);
+    }
+}
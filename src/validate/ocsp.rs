@@ -0,0 +1,682 @@
+use std::sync::Arc;
+
+use der::asn1::{Null, OctetString};
+use der::oid::ObjectIdentifier;
+use der::{Any, Decode, Encode, Header, Reader, SliceReader};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use spki::AlgorithmIdentifierOwned;
+use x509_cert::serial_number::SerialNumber;
+use x509_ocsp::{
+    BasicOcspResponse, CertId, CertStatus, OcspGeneralizedTime, OcspRequest, OcspResponse,
+    OcspResponseStatus, Request, ResponderId, SingleResponse, TbsRequest, Version,
+};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::asn1_rs::BitString;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::AlgorithmIdentifier;
+
+use crate::validate::crl::RevocationReason;
+
+const ID_SHA_1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.14.3.2.26");
+const ID_SHA_256: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.1");
+/// id-pkix-ocsp-basic, RFC 6960 4.2.1
+const ID_PKIX_OCSP_BASIC: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.48.1.1");
+/// id-pkix-ocsp-nonce, RFC 6960 4.4.1
+const ID_PKIX_OCSP_NONCE: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.48.1.2");
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OcspCertStatus {
+    Good,
+    Revoked {
+        reason: Option<RevocationReason>,
+        revocation_time: ASN1Time,
+    },
+    /// The responder doesn't know the certificate.
+    Unknown,
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum OcspError {
+    #[error("InvalidOcspRequest")]
+    InvalidOcspRequest,
+    #[error("InvalidOcspResponse")]
+    InvalidOcspResponse,
+    /// The responder answered with an error status, e.g. tryLater.
+    #[error("UnsuccessfulOcspResponse: {0}")]
+    UnsuccessfulOcspResponse(u8),
+    #[error("UnsupportedResponseType")]
+    UnsupportedResponseType,
+    /// The response was neither signed by the issuer of the certificate, nor by a responder the issuer delegated to.
+    #[error("ResponderNotAuthorized")]
+    ResponderNotAuthorized,
+    #[error("InvalidOcspSignature")]
+    InvalidOcspSignature,
+    /// The response has no status for the certificate.
+    #[error("MissingCertificateStatus")]
+    MissingCertificateStatus,
+    #[error("OcspResponseNotYetValid")]
+    OcspResponseNotYetValid,
+    /// The response's nextUpdate has passed.
+    #[error("OcspResponseExpired")]
+    OcspResponseExpired,
+    /// The response doesn't echo the nonce of the request.
+    #[error("NonceMismatch")]
+    NonceMismatch,
+    #[error("MissingOcspResponse")]
+    MissingOcspResponse,
+    /// The issuer certificate is required to verify the response.
+    #[error("MissingIssuer")]
+    MissingIssuer,
+    #[error("CertificateRevoked: {0:?}")]
+    CertificateRevoked(Option<RevocationReason>),
+    #[error("CertificateStatusUnknown")]
+    CertificateStatusUnknown,
+}
+
+/// What to do when the revocation status can't be established, a revoked certificate is always rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OcspFailurePolicy {
+    /// Accept certificates without a valid response, the common choice as responders are often unreachable.
+    #[default]
+    SoftFail,
+    /// Only accept certificates with a valid response reporting them as good.
+    HardFail,
+}
+
+impl OcspFailurePolicy {
+    /// Applies the policy to the outcome of [`check_ocsp_status`].
+    pub fn apply(&self, result: Result<(), OcspError>) -> Result<(), OcspError> {
+        match (self, result) {
+            (_, Ok(())) => Ok(()),
+            (_, Err(err @ OcspError::CertificateRevoked(_))) => Err(err),
+            (OcspFailurePolicy::SoftFail, Err(err)) => {
+                tracing::debug!("Ignoring OCSP failure: {}", err);
+                Ok(())
+            }
+            (OcspFailurePolicy::HardFail, Err(err)) => Err(err),
+        }
+    }
+}
+
+/// The verified status of a single certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OcspResponseStatusInfo {
+    pub status: OcspCertStatus,
+    pub produced_at: ASN1Time,
+    pub this_update: ASN1Time,
+    /// Absent when the responder always has newer information available.
+    pub next_update: Option<ASN1Time>,
+}
+
+fn sha1(data: &[u8]) -> Vec<u8> {
+    ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, data)
+        .as_ref()
+        .to_vec()
+}
+
+fn ocsp_time(time: &OcspGeneralizedTime) -> Result<ASN1Time, OcspError> {
+    ASN1Time::from_timestamp(time.0.to_unix_duration().as_secs() as i64)
+        .map_err(|_| OcspError::InvalidOcspResponse)
+}
+
+/// The CertID of the certificate, hashed with SHA-1 like most responders expect (RFC 5019 2.1.1).
+pub fn ocsp_cert_id(
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+) -> Result<CertId, OcspError> {
+    Ok(CertId {
+        hash_algorithm: AlgorithmIdentifierOwned {
+            oid: ID_SHA_1,
+            parameters: Some(Any::from(Null)),
+        },
+        issuer_name_hash: OctetString::new(sha1(issuer.subject().as_raw()))
+            .map_err(|_| OcspError::InvalidOcspRequest)?,
+        issuer_key_hash: OctetString::new(sha1(&issuer.public_key().subject_public_key.data))
+            .map_err(|_| OcspError::InvalidOcspRequest)?,
+        serial_number: SerialNumber::new(certificate.raw_serial())
+            .map_err(|_| OcspError::InvalidOcspRequest)?,
+    })
+}
+
+/// DER encoded unsigned OCSP request for the certificate.
+pub fn build_ocsp_request(
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+) -> Result<Vec<u8>, OcspError> {
+    let request = OcspRequest {
+        tbs_request: TbsRequest {
+            version: Version::V1,
+            requestor_name: None,
+            request_list: vec![Request {
+                req_cert: ocsp_cert_id(certificate, issuer)?,
+                single_request_extensions: None,
+            }],
+            request_extensions: None,
+        },
+        optional_signature: None,
+    };
+    request.to_der().map_err(|_| OcspError::InvalidOcspRequest)
+}
+
+/// Checks if the CertID names the certificate, responders may answer with another hash algorithm than requested.
+fn matches_cert_id(
+    cert_id: &CertId,
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+) -> bool {
+    let algorithm = if cert_id.hash_algorithm.oid == ID_SHA_1 {
+        &ring::digest::SHA1_FOR_LEGACY_USE_ONLY
+    } else if cert_id.hash_algorithm.oid == ID_SHA_256 {
+        &ring::digest::SHA256
+    } else {
+        return false;
+    };
+    let name_hash = ring::digest::digest(algorithm, issuer.subject().as_raw());
+    let key_hash = ring::digest::digest(algorithm, &issuer.public_key().subject_public_key.data);
+    cert_id.serial_number.as_bytes() == certificate.raw_serial()
+        && cert_id.issuer_name_hash.as_bytes() == name_hash.as_ref()
+        && cert_id.issuer_key_hash.as_bytes() == key_hash.as_ref()
+}
+
+/// Decodes the BasicOCSPResponse of a DER encoded OCSPResponse.
+pub fn decode_ocsp_response(response: &[u8]) -> Result<BasicOcspResponse, OcspError> {
+    BasicOcspResponse::from_der(&basic_ocsp_response_der(response)?)
+        .map_err(|_| OcspError::InvalidOcspResponse)
+}
+
+/// The DER of the BasicOCSPResponse of a DER encoded OCSPResponse.
+fn basic_ocsp_response_der(response: &[u8]) -> Result<Vec<u8>, OcspError> {
+    let response = OcspResponse::from_der(response).map_err(|_| OcspError::InvalidOcspResponse)?;
+    if response.response_status != OcspResponseStatus::Successful {
+        return Err(OcspError::UnsuccessfulOcspResponse(
+            response.response_status as u8,
+        ));
+    }
+    let response_bytes = response
+        .response_bytes
+        .ok_or(OcspError::InvalidOcspResponse)?;
+    if response_bytes.response_type != ID_PKIX_OCSP_BASIC {
+        return Err(OcspError::UnsupportedResponseType);
+    }
+    Ok(response_bytes.response.as_bytes().to_vec())
+}
+
+/// The tbsResponseData of a BasicOCSPResponse as the responder encoded it. The signature is over these bytes,
+/// re-encoding the decoded structure doesn't reproduce them when the responder's encoding is not canonical.
+fn raw_tbs_response_data(basic_response: &[u8]) -> Result<&[u8], OcspError> {
+    let mut reader =
+        SliceReader::new(basic_response).map_err(|_| OcspError::InvalidOcspResponse)?;
+    Header::decode(&mut reader).map_err(|_| OcspError::InvalidOcspResponse)?;
+    reader
+        .tlv_bytes()
+        .map_err(|_| OcspError::InvalidOcspResponse)
+}
+
+fn is_responder(responder_id: &ResponderId, responder: &X509Certificate) -> bool {
+    match responder_id {
+        ResponderId::ByName(name) => name
+            .to_der()
+            .is_ok_and(|name| name == responder.subject().as_raw()),
+        ResponderId::ByKey(key_hash) => {
+            key_hash.as_bytes() == sha1(&responder.public_key().subject_public_key.data)
+        }
+    }
+}
+
+/// Checks that `responder` signed the response, `tbs_response_data` is the raw tbsResponseData of the response.
+fn verify_response_signature(
+    response: &BasicOcspResponse,
+    tbs_response_data: &[u8],
+    responder: &X509Certificate,
+) -> Result<(), OcspError> {
+    let signature_algorithm = response
+        .signature_algorithm
+        .to_der()
+        .map_err(|_| OcspError::InvalidOcspResponse)?;
+    let (_, signature_algorithm) = AlgorithmIdentifier::from_der(&signature_algorithm)
+        .map_err(|_| OcspError::InvalidOcspResponse)?;
+    let signature = BitString::new(0, response.signature.raw_bytes());
+    x509_parser::verify::verify_signature(
+        responder.public_key(),
+        &signature_algorithm,
+        &signature,
+        tbs_response_data,
+    )
+    .map_err(|_| OcspError::InvalidOcspSignature)
+}
+
+/// A delegated responder is issued by the CA for signing OCSP responses, RFC 6960 4.2.2.2.
+fn is_delegated_responder(
+    responder: &X509Certificate,
+    issuer: &X509Certificate,
+    now: ASN1Time,
+) -> bool {
+    responder.issuer() == issuer.subject()
+        && responder
+            .verify_signature(Some(issuer.public_key()))
+            .is_ok()
+        && responder.validity().is_valid_at(now)
+        && matches!(responder.extended_key_usage(), Ok(Some(eku)) if eku.value.ocsp_signing)
+}
+
+/// Checks that the issuer, or a responder it delegated to and included in the response, signed the response.
+fn verify_responder(
+    response: &BasicOcspResponse,
+    tbs_response_data: &[u8],
+    issuer: &X509Certificate,
+    now: ASN1Time,
+) -> Result<(), OcspError> {
+    let responder_id = &response.tbs_response_data.responder_id;
+    if is_responder(responder_id, issuer) {
+        return verify_response_signature(response, tbs_response_data, issuer);
+    }
+    let certificates: Vec<Vec<u8>> = response
+        .certs
+        .iter()
+        .flatten()
+        .filter_map(|certificate| certificate.to_der().ok())
+        .collect();
+    for der in &certificates {
+        let Ok((_, responder)) = X509Certificate::from_der(der) else {
+            continue;
+        };
+        if !is_responder(responder_id, &responder) {
+            continue;
+        }
+        if !is_delegated_responder(&responder, issuer, now) {
+            return Err(OcspError::ResponderNotAuthorized);
+        }
+        return verify_response_signature(response, tbs_response_data, &responder);
+    }
+    Err(OcspError::ResponderNotAuthorized)
+}
+
+/// The nonce of the response, RFC 8954.
+pub fn ocsp_response_nonce(response: &BasicOcspResponse) -> Option<Vec<u8>> {
+    let extension = response
+        .tbs_response_data
+        .response_extensions
+        .iter()
+        .flatten()
+        .find(|extension| extension.extn_id == ID_PKIX_OCSP_NONCE)?;
+    let value = extension.extn_value.as_bytes();
+    // The nonce is an OCTET STRING within the extension value, some responders omit the wrapping.
+    Some(match OctetString::from_der(value) {
+        Ok(nonce) => nonce.as_bytes().to_vec(),
+        Err(_) => value.to_vec(),
+    })
+}
+
+fn single_response<'r>(
+    response: &'r BasicOcspResponse,
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+) -> Result<&'r SingleResponse, OcspError> {
+    response
+        .tbs_response_data
+        .responses
+        .iter()
+        .find(|single| matches_cert_id(&single.cert_id, certificate, issuer))
+        .ok_or(OcspError::MissingCertificateStatus)
+}
+
+fn status_info(
+    response: &BasicOcspResponse,
+    single: &SingleResponse,
+    now: ASN1Time,
+) -> Result<OcspResponseStatusInfo, OcspError> {
+    let this_update = ocsp_time(&single.this_update)?;
+    let next_update = single.next_update.as_ref().map(ocsp_time).transpose()?;
+    if this_update > now {
+        return Err(OcspError::OcspResponseNotYetValid);
+    }
+    if next_update.is_some_and(|next_update| next_update < now) {
+        return Err(OcspError::OcspResponseExpired);
+    }
+    let status = match &single.cert_status {
+        CertStatus::Good(_) => OcspCertStatus::Good,
+        CertStatus::Revoked(info) => OcspCertStatus::Revoked {
+            reason: info
+                .revocation_reason
+                .map(|reason| RevocationReason::from(reason as u8)),
+            revocation_time: ocsp_time(&info.revocation_time)?,
+        },
+        CertStatus::Unknown(_) => OcspCertStatus::Unknown,
+    };
+    Ok(OcspResponseStatusInfo {
+        status,
+        produced_at: ocsp_time(&response.tbs_response_data.produced_at)?,
+        this_update,
+        next_update,
+    })
+}
+
+/// Verifies a response signed by the certificate's issuer or its delegated responder and returns the certificate's status,
+/// `now` must be within thisUpdate..nextUpdate.
+pub fn verify_ocsp_response(
+    response: &[u8],
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+    now: ASN1Time,
+) -> Result<OcspResponseStatusInfo, OcspError> {
+    verify_ocsp_response_with_nonce(response, certificate, issuer, None, now)
+}
+
+/// Like [`verify_ocsp_response`], additionally requires the response to echo `nonce` when given.
+pub fn verify_ocsp_response_with_nonce(
+    response: &[u8],
+    certificate: &X509Certificate,
+    issuer: &X509Certificate,
+    nonce: Option<&[u8]>,
+    now: ASN1Time,
+) -> Result<OcspResponseStatusInfo, OcspError> {
+    let basic_response = basic_ocsp_response_der(response)?;
+    let response =
+        BasicOcspResponse::from_der(&basic_response).map_err(|_| OcspError::InvalidOcspResponse)?;
+    verify_responder(
+        &response,
+        raw_tbs_response_data(&basic_response)?,
+        issuer,
+        now,
+    )?;
+    if let Some(nonce) = nonce {
+        if ocsp_response_nonce(&response).as_deref() != Some(nonce) {
+            return Err(OcspError::NonceMismatch);
+        }
+    }
+    let single = single_response(&response, certificate, issuer)?;
+    status_info(&response, single, now)
+}
+
+/// Checks that the response reports the certificate as good, see [`OcspFailurePolicy::apply`] for tolerating failures.
+pub fn check_ocsp_status(
+    response: Option<&[u8]>,
+    certificate: &X509Certificate,
+    issuer: Option<&X509Certificate>,
+    now: ASN1Time,
+) -> Result<(), OcspError> {
+    let response = response.ok_or(OcspError::MissingOcspResponse)?;
+    let issuer = issuer.ok_or(OcspError::MissingIssuer)?;
+    match verify_ocsp_response(response, certificate, issuer, now)?.status {
+        OcspCertStatus::Good => Ok(()),
+        OcspCertStatus::Revoked { reason, .. } => Err(OcspError::CertificateRevoked(reason)),
+        OcspCertStatus::Unknown => Err(OcspError::CertificateStatusUnknown),
+    }
+}
+
+/// Wraps a rustls server certificate verifier, additionally checking the OCSP response stapled by the server.
+/// The issuer is taken from the first intermediate, servers sending only their leaf are treated as missing the issuer.
+///
+/// There is no client certificate counterpart: rustls doesn't pass an OCSP response to
+/// `ClientCertVerifier::verify_client_cert`, and TLS clients don't staple in practice. Check client
+/// certificates against CRLs instead, e.g. with `WebPkiClientVerifier::builder(..).with_crls(..)`.
+#[derive(Debug)]
+pub struct OcspServerCertVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    policy: OcspFailurePolicy,
+}
+
+impl OcspServerCertVerifier {
+    pub fn new(inner: Arc<dyn ServerCertVerifier>, policy: OcspFailurePolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl ServerCertVerifier for OcspServerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let (_, certificate) = X509Certificate::from_der(end_entity)
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let issuer = intermediates
+            .first()
+            .and_then(|issuer| X509Certificate::from_der(issuer).ok())
+            .map(|(_, issuer)| issuer);
+        let now = ASN1Time::from_timestamp(now.as_secs() as i64)
+            .map_err(|_| rustls::Error::FailedToGetCurrentTime)?;
+        let response = (!ocsp_response.is_empty()).then_some(ocsp_response);
+        let result = check_ocsp_status(response, &certificate, issuer.as_ref(), now);
+        match self.policy.apply(result) {
+            Ok(()) => Ok(verified),
+            Err(OcspError::CertificateRevoked(_)) => {
+                Err(rustls::Error::InvalidCertificate(CertificateError::Revoked))
+            }
+            Err(OcspError::MissingOcspResponse | OcspError::CertificateStatusUnknown) => Err(
+                rustls::Error::InvalidCertificate(CertificateError::UnknownRevocationStatus),
+            ),
+            Err(err) => Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                OtherError(Arc::new(err)),
+            ))),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::{CertificateError, DigitallySignedStruct, SignatureScheme};
+    use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+    use x509_parser::pem::Pem;
+    use x509_parser::time::ASN1Time;
+
+    use crate::validate::crl::RevocationReason;
+    use crate::validate::ocsp::{
+        build_ocsp_request, check_ocsp_status, verify_ocsp_response,
+        verify_ocsp_response_with_nonce, OcspCertStatus, OcspError, OcspFailurePolicy,
+        OcspServerCertVerifier,
+    };
+
+    /// Accepts every chain, leaving the OCSP check to the wrapper.
+    #[derive(Debug)]
+    struct AcceptAnyServerCert;
+
+    impl ServerCertVerifier for AcceptAnyServerCert {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            vec![]
+        }
+    }
+
+    fn pem(data: &[u8]) -> Pem {
+        Pem::iter_from_buffer(data).next().unwrap().unwrap()
+    }
+
+    fn time(timestamp: i64) -> ASN1Time {
+        ASN1Time::from_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_build_ocsp_request() {
+        let ca = pem(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let leaf = pem(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let request =
+            build_ocsp_request(&leaf.parse_x509().unwrap(), &ca.parse_x509().unwrap()).unwrap();
+        // Same as `openssl ocsp -no_nonce -reqout`.
+        assert_eq!(request, include_bytes!("../../tests/data/ocsp-request.der"));
+    }
+
+    #[test]
+    fn test_verify_ocsp_response() {
+        let ca = pem(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let ca = ca.parse_x509().unwrap();
+        let leaf = pem(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let leaf = leaf.parse_x509().unwrap();
+        let revoked_leaf = pem(include_bytes!("../../tests/data/ocsp-revoked-leaf.pem"));
+        let revoked_leaf = revoked_leaf.parse_x509().unwrap();
+        let good = include_bytes!("../../tests/data/ocsp-good.der");
+        let revoked = include_bytes!("../../tests/data/ocsp-revoked.der");
+        // 2026-10-20, the responses are valid 2026-10-19 02:07:02 to 2036-10-16 02:07:02.
+        let now = time(1_792_454_400);
+
+        let info = verify_ocsp_response(good, &leaf, &ca, now).unwrap();
+        assert_eq!(info.status, OcspCertStatus::Good);
+        assert_eq!(info.next_update, Some(time(2_107_735_622)));
+        assert!(matches!(
+            verify_ocsp_response(revoked, &revoked_leaf, &ca, now)
+                .unwrap()
+                .status,
+            OcspCertStatus::Revoked {
+                reason: Some(RevocationReason::KeyCompromise),
+                ..
+            }
+        ));
+        assert_eq!(
+            verify_ocsp_response(good, &revoked_leaf, &ca, now),
+            Err(OcspError::MissingCertificateStatus)
+        );
+        // 2036-11-01, past nextUpdate.
+        assert_eq!(
+            verify_ocsp_response(good, &leaf, &ca, time(2_109_110_400)),
+            Err(OcspError::OcspResponseExpired)
+        );
+    }
+
+    #[test]
+    fn test_verify_delegated_ocsp_response() {
+        let ca = pem(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let ca = ca.parse_x509().unwrap();
+        let other_ca = pem(include_bytes!("../../tests/data/revocation-ca.pem"));
+        let other_ca = other_ca.parse_x509().unwrap();
+        let leaf = pem(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let leaf = leaf.parse_x509().unwrap();
+        // Signed by ocsp-responder.pem, answering a request with a nonce.
+        let delegated = include_bytes!("../../tests/data/ocsp-delegated.der");
+        let nonce = const_hex::decode("6DFE1D94B187835FA559A249AD596C19").unwrap();
+        let now = time(1_792_454_400);
+
+        let info =
+            verify_ocsp_response_with_nonce(delegated, &leaf, &ca, Some(&nonce), now).unwrap();
+        assert_eq!(info.status, OcspCertStatus::Good);
+        assert_eq!(
+            verify_ocsp_response_with_nonce(delegated, &leaf, &ca, Some(&[1, 2, 3]), now),
+            Err(OcspError::NonceMismatch)
+        );
+        // The responder was not issued by this CA.
+        assert_eq!(
+            verify_ocsp_response(delegated, &leaf, &other_ca, now),
+            Err(OcspError::ResponderNotAuthorized)
+        );
+    }
+
+    #[test]
+    fn test_ocsp_failure_policy() {
+        let ca = pem(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let ca = ca.parse_x509().unwrap();
+        let revoked_leaf = pem(include_bytes!("../../tests/data/ocsp-revoked-leaf.pem"));
+        let revoked_leaf = revoked_leaf.parse_x509().unwrap();
+        let revoked = include_bytes!("../../tests/data/ocsp-revoked.der");
+        let now = time(1_792_454_400);
+
+        let missing = check_ocsp_status(None, &revoked_leaf, Some(&ca), now);
+        assert_eq!(missing, Err(OcspError::MissingOcspResponse));
+        assert_eq!(OcspFailurePolicy::SoftFail.apply(missing.clone()), Ok(()));
+        assert_eq!(OcspFailurePolicy::HardFail.apply(missing.clone()), missing);
+
+        let revoked = check_ocsp_status(Some(revoked), &revoked_leaf, Some(&ca), now);
+        assert!(matches!(revoked, Err(OcspError::CertificateRevoked(_))));
+        assert_eq!(OcspFailurePolicy::SoftFail.apply(revoked.clone()), revoked);
+    }
+
+    #[test]
+    fn test_ocsp_server_cert_verifier() {
+        let ca = pem(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let ca = CertificateDer::from(ca.contents);
+        let leaf = pem(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let leaf = CertificateDer::from(leaf.contents);
+        let revoked_leaf = pem(include_bytes!("../../tests/data/ocsp-revoked-leaf.pem"));
+        let revoked_leaf = CertificateDer::from(revoked_leaf.contents);
+        let good = include_bytes!("../../tests/data/ocsp-good.der");
+        let revoked = include_bytes!("../../tests/data/ocsp-revoked.der");
+        let server_name = ServerName::try_from("ocsp.foobar.com").unwrap();
+        let now = UnixTime::since_unix_epoch(Duration::from_secs(1_792_454_400));
+        let verify = |policy, end_entity: &CertificateDer, ocsp_response: &[u8]| {
+            OcspServerCertVerifier::new(Arc::new(AcceptAnyServerCert), policy)
+                .verify_server_cert(end_entity, &[ca.clone()], &server_name, ocsp_response, now)
+                .map(|_| ())
+        };
+
+        for policy in [OcspFailurePolicy::SoftFail, OcspFailurePolicy::HardFail] {
+            assert_eq!(verify(policy, &leaf, good), Ok(()));
+            assert!(matches!(
+                verify(policy, &revoked_leaf, revoked),
+                Err(rustls::Error::InvalidCertificate(CertificateError::Revoked))
+            ));
+        }
+        // Nothing stapled.
+        assert_eq!(verify(OcspFailurePolicy::SoftFail, &leaf, &[]), Ok(()));
+        assert!(matches!(
+            verify(OcspFailurePolicy::HardFail, &leaf, &[]),
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus
+            ))
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+use ssh_key::certificate::CertType;
+use ssh_key::Certificate;
+
+/// Checks that `now`, in seconds since the Unix epoch, is within the certificate's `valid_after`..`valid_before` window.
+pub fn is_ssh_certificate_valid_at(certificate: &Certificate, now: u64) -> bool {
+    certificate.valid_after() <= now && now < certificate.valid_before()
+}
+
+pub fn is_ssh_certificate_expired(certificate: &Certificate, now: u64) -> bool {
+    now >= certificate.valid_before()
+}
+
+/// Checks that the certificate is valid for the principal. A certificate without principals is valid for any,
+/// host principals are compared case-insensitively like host names.
+pub fn ssh_certificate_has_principal(certificate: &Certificate, principal: &str) -> bool {
+    let principals = certificate.valid_principals();
+    if principals.is_empty() {
+        return true;
+    }
+    match certificate.cert_type() {
+        CertType::Host => principals.iter().any(|x| {
+            x.trim_end_matches('.')
+                .eq_ignore_ascii_case(principal.trim_end_matches('.'))
+        }),
+        CertType::User => principals.iter().any(|x| x == principal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssh_key::Certificate;
+
+    use crate::validate::openssh::{
+        is_ssh_certificate_expired, is_ssh_certificate_valid_at, ssh_certificate_has_principal,
+    };
+
+    #[test]
+    fn test_validate_ssh_host_certificate() {
+        let certificate =
+            Certificate::from_openssh(include_str!("../../tests/data/ssh-host-cert.pub")).unwrap();
+        // Valid from 2024-01-01 to 2050-01-01.
+        assert!(is_ssh_certificate_valid_at(&certificate, 1_720_000_000));
+        assert!(!is_ssh_certificate_valid_at(&certificate, 1_700_000_000));
+        assert!(!is_ssh_certificate_expired(&certificate, 1_700_000_000));
+        assert!(is_ssh_certificate_expired(&certificate, 2_524_608_000));
+
+        assert!(ssh_certificate_has_principal(
+            &certificate,
+            "HOST.foobar.com"
+        ));
+        assert!(ssh_certificate_has_principal(&certificate, "foobar.com."));
+        assert!(!ssh_certificate_has_principal(
+            &certificate,
+            "other.foobar.com"
+        ));
+    }
+}
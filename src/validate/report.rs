@@ -0,0 +1,197 @@
+use std::fmt::{Display, Formatter};
+
+use x509_parser::certificate::X509Certificate;
+
+/// The checks run by [`PkiValidator::validate_identity`](crate::validate::validate::PkiValidator::validate_identity).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// The private key belongs to the certificate.
+    KeyPair,
+    SelfSigned,
+    ServerName,
+    Validity,
+    Chain,
+    /// The name constraints and path length constraints of the chain's CAs.
+    ChainConstraints,
+    Revocation,
+    CertificateTransparency,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed(String),
+    /// Disabled by the configuration, or not applicable, with the reason.
+    Skipped(String),
+}
+
+/// A certificate of the identity's chain, the leaf is at index 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificateRef {
+    pub chain_index: usize,
+    pub subject: String,
+    pub serial: String,
+}
+
+impl CertificateRef {
+    pub fn new(chain_index: usize, certificate: &X509Certificate) -> Self {
+        Self {
+            chain_index,
+            subject: certificate.subject().to_string(),
+            serial: certificate.raw_serial_as_string(),
+        }
+    }
+}
+
+impl Display for CertificateRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} (serial {})",
+            self.chain_index, self.subject, self.serial
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub check: Check,
+    pub status: CheckStatus,
+    /// The offending certificate, for checks of a single certificate of the chain.
+    pub certificate: Option<CertificateRef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// Valid, but expires within the configured warning period.
+    ExpiresSoon {
+        certificate: CertificateRef,
+        days_left: i64,
+    },
+    Sha1Signature {
+        certificate: CertificateRef,
+    },
+    WeakRsaKey {
+        certificate: CertificateRef,
+        bits: usize,
+    },
+}
+
+/// All findings of validating an identity, where the `verify_*` methods stop at the first failure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub checks: Vec<CheckResult>,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl ValidationReport {
+    pub(crate) fn push(
+        &mut self,
+        check: Check,
+        status: CheckStatus,
+        certificate: Option<CertificateRef>,
+    ) {
+        self.checks.push(CheckResult {
+            check,
+            status,
+            certificate,
+        });
+    }
+
+    /// Valid if no check failed, warnings don't count.
+    pub fn is_valid(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks
+            .iter()
+            .filter(|result| matches!(result.status, CheckStatus::Failed(_)))
+    }
+
+    /// The status of the check, failed if it failed for any certificate of the chain.
+    pub fn status_of(&self, check: Check) -> Option<&CheckStatus> {
+        let mut results = self.checks.iter().filter(|result| result.check == check);
+        results
+            .clone()
+            .find(|result| matches!(result.status, CheckStatus::Failed(_)))
+            .or_else(|| results.next())
+            .map(|result| &result.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rustls_pki_types::{CertificateDer, ServerName};
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::parser::parse::{Identities, PkiParser};
+    use crate::parser::IdentityParser;
+    use crate::validate::report::{Check, CheckStatus, ValidationWarning};
+    use crate::validate::trust::TrustStore;
+    use crate::validate::validate::{PkiValidator, PkiValidatorConfig};
+    use crate::ParsedPkiData;
+
+    #[test]
+    fn test_validation_report_collects_all_findings() {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        for pem in [
+            &include_bytes!("../../tests/data/ocsp-leaf.pem")[..],
+            include_bytes!("../../tests/data/ocsp-ca.pem"),
+            include_bytes!("../../tests/data/ocsp-leaf.key"),
+        ] {
+            pki_parser
+                .parse_pem(&mut parsed_pki_data, Cursor::new(pem))
+                .unwrap();
+        }
+        let mut identities = Identities::default();
+        pki_parser
+            .parse_identity(&parsed_pki_data, &mut identities)
+            .unwrap();
+        let identity = identities.iter().next().unwrap();
+
+        let (_, ca) = parse_x509_pem(include_bytes!("../../tests/data/ocsp-ca.pem")).unwrap();
+        let validator = PkiValidator::new(PkiValidatorConfig {
+            allow_self_signed: false,
+            validate_expiration: true,
+            validate_domain: true,
+            verify_certificate_chain: true,
+            allow_common_name_fallback: false,
+            check_revocation: false,
+            ocsp_failure_policy: Default::default(),
+            require_certificate_transparency: false,
+            clock_skew_tolerance: Duration::ZERO,
+            server_name: ServerName::try_from("other.foobar.com").unwrap(),
+        })
+        .with_trust_store(
+            &TrustStore::from_certificates([CertificateDer::from(ca.contents)]).unwrap(),
+            Arc::new(rustls::crypto::ring::default_provider()),
+        )
+        .unwrap()
+        .with_expiry_warning(Duration::from_secs(20 * 365 * 24 * 60 * 60));
+
+        let report = validator.validate_identity(identity);
+        assert!(!report.is_valid());
+        let failed: Vec<_> = report.failures().map(|result| result.check).collect();
+        assert_eq!(failed, vec![Check::ServerName, Check::Chain]);
+        assert_eq!(report.status_of(Check::KeyPair), Some(&CheckStatus::Passed));
+        assert_eq!(
+            report.status_of(Check::Validity),
+            Some(&CheckStatus::Passed)
+        );
+        assert!(matches!(
+            report.status_of(Check::Revocation),
+            Some(CheckStatus::Skipped(_))
+        ));
+        assert!(report
+            .warnings
+            .iter()
+            .all(|warning| matches!(warning, ValidationWarning::ExpiresSoon { .. })));
+        assert!(!report.warnings.is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use std::time::Duration;
+
+use der::asn1::OctetString;
+use der::oid::ObjectIdentifier;
+use der::{Decode, Encode};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use rustls_pki_types::CertificateDer;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::{
+    OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384, OID_PKCS1_RSAENCRYPTION,
+};
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+/// The precertificate SCT list extension, RFC 6962 3.3
+const SCT_LIST_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.11129.2.4.2");
+/// TLS HashAlgorithm sha256 and SignatureAlgorithm rsa/ecdsa, RFC 5246 7.4.1.4.1
+const HASH_SHA256: u8 = 4;
+const SIGNATURE_RSA: u8 = 1;
+const SIGNATURE_ECDSA: u8 = 3;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SctError {
+    #[error("InvalidSctList")]
+    InvalidSctList,
+    #[error("InvalidCtLogKey")]
+    InvalidCtLogKey,
+    #[error("UnsupportedSctVersion: {0}")]
+    UnsupportedSctVersion(u8),
+    #[error("UnsupportedSctSignatureAlgorithm")]
+    UnsupportedSctSignatureAlgorithm,
+    #[error("UnknownCtLog")]
+    UnknownCtLog,
+    #[error("InvalidSctSignature")]
+    InvalidSctSignature,
+    /// The log claims to have seen the certificate in the future.
+    #[error("FutureSctTimestamp")]
+    FutureSctTimestamp,
+    /// The issuer's key is part of the signed data of embedded SCTs.
+    #[error("MissingIssuer")]
+    MissingIssuer,
+    #[error("InvalidCertificate")]
+    InvalidCertificate,
+    /// Fewer distinct logs with a valid SCT than the policy requires.
+    #[error("InsufficientScts: required {required}, found {found}")]
+    InsufficientScts { required: usize, found: usize },
+}
+
+/// How the SCT was delivered, which determines the signed log entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SctSource {
+    /// Embedded in the certificate, signed over the precertificate.
+    Embedded,
+    /// Sent in the `signed_certificate_timestamp` TLS extension, signed over the certificate.
+    TlsExtension,
+}
+
+/// A v1 SignedCertificateTimestamp, RFC 6962 3.2
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedCertificateTimestamp {
+    pub log_id: [u8; 32],
+    /// Milliseconds since the epoch.
+    pub timestamp: u64,
+    pub extensions: Vec<u8>,
+    pub hash_algorithm: u8,
+    pub signature_algorithm: u8,
+    pub signature: Vec<u8>,
+    pub source: SctSource,
+}
+
+/// A Certificate Transparency log trusted for SCT verification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CtLog {
+    /// SHA-256 of the log's public key.
+    pub log_id: [u8; 32],
+    /// DER encoded SubjectPublicKeyInfo.
+    pub public_key: Vec<u8>,
+}
+
+impl CtLog {
+    pub fn new(public_key: &[u8]) -> Result<Self, SctError> {
+        SubjectPublicKeyInfo::from_der(public_key).map_err(|_| SctError::InvalidCtLogKey)?;
+        let digest = ring::digest::digest(&ring::digest::SHA256, public_key);
+        let mut log_id = [0; 32];
+        log_id.copy_from_slice(digest.as_ref());
+        Ok(Self {
+            log_id,
+            public_key: public_key.to_vec(),
+        })
+    }
+}
+
+/// Parses the `PUBLIC KEY` PEM blocks of the logs, as published in the CT log lists.
+pub fn parse_ct_logs_pem(data: &[u8]) -> Result<Vec<CtLog>, SctError> {
+    let mut logs = Vec::new();
+    for pem in Pem::iter_from_buffer(data) {
+        let pem = pem.map_err(|_| SctError::InvalidCtLogKey)?;
+        if pem.label == "PUBLIC KEY" {
+            logs.push(CtLog::new(&pem.contents)?);
+        }
+    }
+    Ok(logs)
+}
+
+/// Minimum number of SCTs from distinct logs, by certificate lifetime, following the Chrome CT policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SctPolicy {
+    pub short_lifetime: Duration,
+    /// Required for certificates valid for at most `short_lifetime`.
+    pub short_lifetime_logs: usize,
+    pub long_lifetime_logs: usize,
+}
+
+impl Default for SctPolicy {
+    fn default() -> Self {
+        Self {
+            short_lifetime: Duration::from_secs(180 * 24 * 60 * 60),
+            short_lifetime_logs: 2,
+            long_lifetime_logs: 3,
+        }
+    }
+}
+
+impl SctPolicy {
+    pub fn required_logs(&self, certificate: &X509Certificate) -> usize {
+        let validity = certificate.validity();
+        let lifetime = validity.not_after.timestamp() - validity.not_before.timestamp();
+        if lifetime <= self.short_lifetime.as_secs() as i64 {
+            self.short_lifetime_logs
+        } else {
+            self.long_lifetime_logs
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SctError> {
+        if self.0.len() < len {
+            return Err(SctError::InvalidSctList);
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn u8(&mut self) -> Result<u8, SctError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16_prefixed(&mut self) -> Result<&'a [u8], SctError> {
+        let len = self.take(2)?;
+        self.take(u16::from_be_bytes([len[0], len[1]]) as usize)
+    }
+}
+
+fn parse_sct(data: &[u8], source: SctSource) -> Result<SignedCertificateTimestamp, SctError> {
+    let mut reader = Reader(data);
+    let version = reader.u8()?;
+    if version != 0 {
+        return Err(SctError::UnsupportedSctVersion(version));
+    }
+    let mut log_id = [0; 32];
+    log_id.copy_from_slice(reader.take(32)?);
+    let mut timestamp = [0; 8];
+    timestamp.copy_from_slice(reader.take(8)?);
+    let sct = SignedCertificateTimestamp {
+        log_id,
+        timestamp: u64::from_be_bytes(timestamp),
+        extensions: reader.u16_prefixed()?.to_vec(),
+        hash_algorithm: reader.u8()?,
+        signature_algorithm: reader.u8()?,
+        signature: reader.u16_prefixed()?.to_vec(),
+        source,
+    };
+    if !reader.0.is_empty() {
+        return Err(SctError::InvalidSctList);
+    }
+    Ok(sct)
+}
+
+/// Parses a TLS encoded SignedCertificateTimestampList, the content of the TLS extension and of the certificate extension.
+pub fn parse_sct_list(
+    data: &[u8],
+    source: SctSource,
+) -> Result<Vec<SignedCertificateTimestamp>, SctError> {
+    let mut reader = Reader(data);
+    let mut list = Reader(reader.u16_prefixed()?);
+    if !reader.0.is_empty() {
+        return Err(SctError::InvalidSctList);
+    }
+    let mut scts = Vec::new();
+    while !list.0.is_empty() {
+        scts.push(parse_sct(list.u16_prefixed()?, source)?);
+    }
+    Ok(scts)
+}
+
+/// The SCTs embedded in the certificate, empty if it has none.
+pub fn embedded_scts(
+    certificate: &X509Certificate,
+) -> Result<Vec<SignedCertificateTimestamp>, SctError> {
+    let extension = certificate
+        .extensions()
+        .iter()
+        .find(|x| x.oid.as_bytes() == SCT_LIST_OID.as_bytes());
+    let Some(extension) = extension else {
+        return Ok(Vec::new());
+    };
+    let list = OctetString::from_der(extension.value).map_err(|_| SctError::InvalidSctList)?;
+    parse_sct_list(list.as_bytes(), SctSource::Embedded)
+}
+
+fn u24_prefixed(data: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(data);
+}
+
+/// The TBSCertificate without the SCT list extension, which is what the log saw of the precertificate.
+fn precertificate_tbs(certificate: &X509Certificate) -> Result<Vec<u8>, SctError> {
+    let mut tbs_certificate =
+        x509_cert::TbsCertificate::from_der(certificate.tbs_certificate.as_ref())
+            .map_err(|_| SctError::InvalidCertificate)?;
+    if let Some(extensions) = tbs_certificate.extensions.as_mut() {
+        extensions.retain(|extension| extension.extn_id != SCT_LIST_OID);
+    }
+    tbs_certificate
+        .to_der()
+        .map_err(|_| SctError::InvalidCertificate)
+}
+
+/// The digitally-signed struct of the SCT, RFC 6962 3.2
+fn signed_data(
+    sct: &SignedCertificateTimestamp,
+    certificate: &CertificateDer,
+    issuer: Option<&X509Certificate>,
+) -> Result<Vec<u8>, SctError> {
+    // sct_version v1, signature_type certificate_timestamp
+    let mut data = vec![0, 0];
+    data.extend_from_slice(&sct.timestamp.to_be_bytes());
+    match sct.source {
+        SctSource::Embedded => {
+            let issuer = issuer.ok_or(SctError::MissingIssuer)?;
+            // entry_type precert_entry
+            data.extend_from_slice(&[0, 1]);
+            data.extend_from_slice(
+                ring::digest::digest(&ring::digest::SHA256, issuer.public_key().raw).as_ref(),
+            );
+            let (_, certificate) =
+                X509Certificate::from_der(certificate).map_err(|_| SctError::InvalidCertificate)?;
+            u24_prefixed(&precertificate_tbs(&certificate)?, &mut data);
+        }
+        SctSource::TlsExtension => {
+            // entry_type x509_entry
+            data.extend_from_slice(&[0, 0]);
+            u24_prefixed(certificate, &mut data);
+        }
+    }
+    data.extend_from_slice(&(sct.extensions.len() as u16).to_be_bytes());
+    data.extend_from_slice(&sct.extensions);
+    Ok(data)
+}
+
+fn verification_algorithm(
+    public_key: &SubjectPublicKeyInfo,
+    sct: &SignedCertificateTimestamp,
+) -> Result<&'static dyn VerificationAlgorithm, SctError> {
+    if sct.hash_algorithm != HASH_SHA256 {
+        return Err(SctError::UnsupportedSctSignatureAlgorithm);
+    }
+    let algorithm = &public_key.algorithm.algorithm;
+    if sct.signature_algorithm == SIGNATURE_RSA && *algorithm == OID_PKCS1_RSAENCRYPTION {
+        return Ok(&signature::RSA_PKCS1_2048_8192_SHA256);
+    }
+    if sct.signature_algorithm == SIGNATURE_ECDSA && *algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+        let curve = public_key
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|parameters| parameters.as_oid().ok());
+        match curve {
+            Some(curve) if curve == OID_EC_P256 => return Ok(&signature::ECDSA_P256_SHA256_ASN1),
+            Some(curve) if curve == OID_NIST_EC_P384 => {
+                return Ok(&signature::ECDSA_P384_SHA256_ASN1)
+            }
+            _ => {}
+        }
+    }
+    Err(SctError::UnsupportedSctSignatureAlgorithm)
+}
+
+/// Verifies the SCT was issued for the certificate by one of the logs. `issuer` is required for embedded SCTs.
+pub fn verify_sct<'l>(
+    sct: &SignedCertificateTimestamp,
+    certificate: &CertificateDer,
+    issuer: Option<&X509Certificate>,
+    logs: &'l [CtLog],
+    now: ASN1Time,
+) -> Result<&'l CtLog, SctError> {
+    let log = logs
+        .iter()
+        .find(|log| log.log_id == sct.log_id)
+        .ok_or(SctError::UnknownCtLog)?;
+    if sct.timestamp / 1000 > now.timestamp().max(0) as u64 {
+        return Err(SctError::FutureSctTimestamp);
+    }
+    let (_, public_key) =
+        SubjectPublicKeyInfo::from_der(&log.public_key).map_err(|_| SctError::InvalidCtLogKey)?;
+    let algorithm = verification_algorithm(&public_key, sct)?;
+    let data = signed_data(sct, certificate, issuer)?;
+    UnparsedPublicKey::new(algorithm, &public_key.subject_public_key.data)
+        .verify(&data, &sct.signature)
+        .map_err(|_| SctError::InvalidSctSignature)?;
+    Ok(log)
+}
+
+/// Checks that the embedded and TLS delivered SCTs include valid SCTs of as many distinct logs as the policy requires.
+/// Invalid SCTs and SCTs of unknown logs don't count, but don't fail the check either.
+pub fn check_sct_policy(
+    certificate: &CertificateDer,
+    issuer: Option<&X509Certificate>,
+    tls_scts: &[SignedCertificateTimestamp],
+    logs: &[CtLog],
+    policy: &SctPolicy,
+    now: ASN1Time,
+) -> Result<(), SctError> {
+    let (_, parsed) =
+        X509Certificate::from_der(certificate).map_err(|_| SctError::InvalidCertificate)?;
+    let mut scts = embedded_scts(&parsed)?;
+    scts.extend_from_slice(tls_scts);
+    let mut seen: Vec<[u8; 32]> = Vec::new();
+    for sct in &scts {
+        match verify_sct(sct, certificate, issuer, logs, now) {
+            Ok(log) => {
+                if !seen.contains(&log.log_id) {
+                    seen.push(log.log_id);
+                }
+            }
+            Err(err) => tracing::debug!("Ignoring SCT: {}", err),
+        }
+    }
+    let required = policy.required_logs(&parsed);
+    if seen.len() < required {
+        return Err(SctError::InsufficientScts {
+            required,
+            found: seen.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls_pki_types::CertificateDer;
+    use x509_parser::pem::Pem;
+    use x509_parser::time::ASN1Time;
+
+    use crate::validate::sct::{
+        check_sct_policy, embedded_scts, parse_ct_logs_pem, parse_sct_list, verify_sct, SctError,
+        SctPolicy, SctSource,
+    };
+
+    fn pem(data: &[u8]) -> Pem {
+        Pem::iter_from_buffer(data).next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_verify_scts() {
+        let ca = pem(include_bytes!("../../tests/data/ct-ca.pem"));
+        let ca = ca.parse_x509().unwrap();
+        let leaf_pem = pem(include_bytes!("../../tests/data/ct-leaf.pem"));
+        let leaf = leaf_pem.parse_x509().unwrap();
+        let leaf_der = CertificateDer::from(leaf_pem.contents.as_slice());
+        let logs = parse_ct_logs_pem(include_bytes!("../../tests/data/ct-logs.pem")).unwrap();
+        // 2026-02-01
+        let now = ASN1Time::from_timestamp(1_769_904_000).unwrap();
+
+        let embedded = embedded_scts(&leaf).unwrap();
+        assert_eq!(embedded.len(), 2);
+        for sct in &embedded {
+            assert!(verify_sct(sct, &leaf_der, Some(&ca), &logs, now).is_ok());
+        }
+        assert_eq!(
+            verify_sct(&embedded[0], &leaf_der, None, &logs, now),
+            Err(SctError::MissingIssuer)
+        );
+        let tls = parse_sct_list(
+            include_bytes!("../../tests/data/ct-tls-scts.bin"),
+            SctSource::TlsExtension,
+        )
+        .unwrap();
+        assert_eq!(tls.len(), 1);
+        assert_eq!(
+            verify_sct(&tls[0], &leaf_der, None, &logs, now)
+                .unwrap()
+                .log_id,
+            logs[0].log_id
+        );
+        // A TLS delivered SCT doesn't verify as embedded one.
+        let mut misplaced = tls[0].clone();
+        misplaced.source = SctSource::Embedded;
+        assert_eq!(
+            verify_sct(&misplaced, &leaf_der, Some(&ca), &logs, now),
+            Err(SctError::InvalidSctSignature)
+        );
+
+        let policy = SctPolicy::default();
+        assert_eq!(
+            check_sct_policy(&leaf_der, Some(&ca), &[], &logs, &policy, now),
+            Ok(())
+        );
+        // The TLS delivered SCT is from a log that already counted.
+        assert_eq!(
+            check_sct_policy(&leaf_der, Some(&ca), &tls, &logs[..1], &policy, now),
+            Err(SctError::InsufficientScts {
+                required: 2,
+                found: 1
+            })
+        );
+    }
+}
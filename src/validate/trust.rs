@@ -0,0 +1,226 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{VerifierBuilderError, WebPkiServerVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls_platform_verifier::Verifier;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+use crate::parser::parse::Identities;
+use crate::parser::sniff::{detect_format, PkiFormat};
+use crate::validate::validate::is_issued_by;
+use crate::ParsedPkiData;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TrustStoreError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("InvalidTrustAnchor")]
+    InvalidTrustAnchor,
+    #[error("NoTrustAnchors")]
+    NoTrustAnchors,
+    #[error(transparent)]
+    VerifierBuilderError(#[from] VerifierBuilderError),
+}
+
+/// The trust anchors chains are validated against.
+#[derive(Clone, Debug)]
+pub enum TrustStore {
+    /// The operating system's trust store, through `rustls-platform-verifier`.
+    Platform,
+    /// Explicit roots, e.g. the CA of a private PKI, validated with webpki.
+    Roots(Arc<RootCertStore>),
+    /// A chain is valid if it's valid for any of the stores.
+    Union(Vec<TrustStore>),
+}
+
+impl TrustStore {
+    pub fn from_certificates<'a>(
+        certificates: impl IntoIterator<Item = CertificateDer<'a>>,
+    ) -> Result<Self, TrustStoreError> {
+        let mut roots = RootCertStore::empty();
+        for certificate in certificates {
+            roots
+                .add(certificate.into_owned())
+                .map_err(|_| TrustStoreError::InvalidTrustAnchor)?;
+        }
+        if roots.is_empty() {
+            return Err(TrustStoreError::NoTrustAnchors);
+        }
+        Ok(TrustStore::Roots(Arc::new(roots)))
+    }
+
+    /// Reads the roots from PEM or DER encoded certificate files. Text around the PEM blocks,
+    /// such as `openssl x509 -text` output, is skipped.
+    pub fn from_files(paths: &[impl AsRef<Path>]) -> Result<Self, TrustStoreError> {
+        let mut certificates = Vec::new();
+        for path in paths {
+            let data = std::fs::read(path)?;
+            if detect_format(&data) == Some(PkiFormat::Pem) {
+                for certificate in rustls_pemfile::certs(&mut Cursor::new(&data)) {
+                    certificates.push(certificate?);
+                }
+            } else {
+                certificates.push(CertificateDer::from(data));
+            }
+        }
+        Self::from_certificates(certificates)
+    }
+
+    /// The self-signed CA certificates of the watched data, for PKIs distributing their root along with the identities.
+    ///
+    /// The roots come from the same source as the chains they validate: whoever can write the file or
+    /// Secret also chooses what it's trusted by, so chain validation only proves the data is consistent.
+    /// Prefer [`TrustStore::from_files`] with roots distributed out of band when the source isn't trusted.
+    pub fn from_pki_data(pki_data: &ParsedPkiData) -> Result<Self, TrustStoreError> {
+        let roots = pki_data.x509.iter().filter(|der| {
+            X509Certificate::from_der(der).is_ok_and(|(_, certificate)| {
+                certificate.is_ca() && is_issued_by(&certificate, &certificate)
+            })
+        });
+        Self::from_certificates(roots.map(|der| CertificateDer::from(der.as_ref())))
+    }
+
+    /// The CA certificates of the identities. Like [`TrustStore::from_pki_data`], the watched data vouches for itself.
+    pub fn from_identities(identities: &Identities) -> Result<Self, TrustStoreError> {
+        let roots = identities
+            .iter()
+            .filter_map(|identity| identity.ca_certificate_der.clone());
+        Self::from_certificates(roots)
+    }
+
+    pub fn union(stores: impl IntoIterator<Item = TrustStore>) -> Self {
+        TrustStore::Union(stores.into_iter().collect())
+    }
+
+    /// The verifier of the store, verifying signatures with the algorithms of `provider`.
+    pub fn verifier(
+        &self,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Arc<dyn ServerCertVerifier>, TrustStoreError> {
+        Ok(match self {
+            TrustStore::Platform => Arc::new(Verifier::new().with_provider(provider)),
+            TrustStore::Roots(roots) => {
+                WebPkiServerVerifier::builder_with_provider(roots.clone(), provider).build()?
+            }
+            TrustStore::Union(stores) => {
+                let verifiers = stores
+                    .iter()
+                    .map(|store| store.verifier(provider.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if verifiers.is_empty() {
+                    return Err(TrustStoreError::NoTrustAnchors);
+                }
+                Arc::new(UnionVerifier { verifiers })
+            }
+        })
+    }
+}
+
+/// Accepts a chain accepted by any of the verifiers.
+#[derive(Debug)]
+struct UnionVerifier {
+    verifiers: Vec<Arc<dyn ServerCertVerifier>>,
+}
+
+impl ServerCertVerifier for UnionVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let mut last_error = None;
+        for verifier in &self.verifiers {
+            match verifier.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            ) {
+                Ok(verified) => return Ok(verified),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or(rustls::Error::General("No verifiers".to_string())))
+    }
+
+    // Handshake signatures don't depend on the trust anchors.
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifiers[0].verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifiers[0].verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.verifiers[0].supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+    use x509_parser::pem::parse_x509_pem;
+
+    use crate::validate::trust::TrustStore;
+
+    fn der(pem: &[u8]) -> CertificateDer<'static> {
+        let (_, pem) = parse_x509_pem(pem).unwrap();
+        CertificateDer::from(pem.contents)
+    }
+
+    #[test]
+    fn test_private_trust_store() {
+        let leaf = der(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let server_name = ServerName::try_from("ocsp.foobar.com").unwrap();
+        let verify = |store: &TrustStore| {
+            store
+                .verifier(Arc::new(rustls::crypto::ring::default_provider()))
+                .unwrap()
+                .verify_server_cert(&leaf, &[], &server_name, &[], UnixTime::now())
+                .is_ok()
+        };
+        let private = TrustStore::from_files(&[concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/ocsp-ca.pem"
+        )])
+        .unwrap();
+        let other =
+            TrustStore::from_certificates([der(include_bytes!("../../tests/data/ct-ca.pem"))])
+                .unwrap();
+        assert!(verify(&private));
+        assert!(!verify(&other));
+        assert!(verify(&TrustStore::union([other, private])));
+    }
+
+    #[test]
+    fn test_trust_store_from_files_with_leading_text() {
+        // openssl x509 -text output, the PEM block follows the decoded certificate.
+        let store =
+            TrustStore::from_files(&[concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/ca.crt")])
+                .unwrap();
+        assert!(matches!(store, TrustStore::Roots(roots) if roots.len() == 1));
+    }
+}
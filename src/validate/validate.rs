@@ -1,15 +1,42 @@
-use der::Decode;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
 use rustls::client::danger::ServerCertVerifier;
+use rustls::crypto::CryptoProvider;
 use rustls::pki_types;
-use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls_pki_types::{CertificateDer, CertificateRevocationListDer, PrivateKeyDer, ServerName};
 use rustls_platform_verifier::Verifier;
-use sec1::EcPrivateKey;
+use tokio::sync::watch;
 use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid;
+use x509_parser::oid_registry::{Oid, OID_PKCS1_SHA1WITHRSA};
 use x509_parser::prelude::FromDer;
-use x509_parser::x509::SubjectPublicKeyInfo;
+use x509_parser::public_key::PublicKey;
+use x509_parser::revocation_list::CertificateRevocationList;
+use x509_parser::time::ASN1Time;
 
+use crate::generic_private_key::GenericPrivateKey;
+use crate::identity::Identities as _;
+use crate::parser::parse::Identities;
+use crate::validate::clock::{
+    to_asn1_time, to_unix_time, validity_status, Clock, SystemClock, ValidityStatus,
+};
+use crate::validate::crl::{check_revocation, CrlError, RevocationReason, RevocationStatus};
+use crate::validate::hostname::matches_server_name;
+use crate::validate::name_constraints::verify_chain_constraints;
+use crate::validate::ocsp::{check_ocsp_status, OcspError, OcspFailurePolicy};
+use crate::validate::openssh::{
+    is_ssh_certificate_expired, is_ssh_certificate_valid_at, ssh_certificate_has_principal,
+};
+use crate::validate::report::{
+    CertificateRef, Check, CheckStatus, ValidationReport, ValidationWarning,
+};
+use crate::validate::sct::{
+    check_sct_policy, CtLog, SctError, SctPolicy, SignedCertificateTimestamp,
+};
+use crate::validate::trust::{TrustStore, TrustStoreError};
 use crate::validate::PkiValidatorConfiguration;
-use crate::Identity;
+use crate::{Identity, ParsedPkiData};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ValidateCertificateError {
@@ -19,84 +46,110 @@ pub enum ValidateCertificateError {
     CertificateHasExpired,
     #[error("Certificate is self signed")]
     CertificateSelfSigned,
+    #[error("InvalidCertificate: {0}")]
+    InvalidCertificate(String),
     #[error("InvalidCertificateChain")]
     InvalidCertificateChain,
     #[error("InvalidCertificateSignature")]
     InvalidCertificateSignature,
+    #[error("Certificate not yet valid")]
+    CertificateNotYetValid,
+    #[error("SSH certificate key id:{0} is not valid for principal:{1}")]
+    NonMatchingSshPrincipal(String, String),
+    #[error("SSH certificate key id:{0} is signed by an untrusted CA:{1}")]
+    UntrustedSshCertificateAuthority(String, String),
+    #[error("Certificate serial:{0} is revoked, reason: {1:?}")]
+    CertificateRevoked(String, Option<RevocationReason>),
+    #[error(transparent)]
+    CrlError(#[from] CrlError),
+    #[error(transparent)]
+    OcspError(#[from] OcspError),
+    #[error(transparent)]
+    SctError(#[from] SctError),
+    #[error("Certificate name:{0} violates the name constraints of:{1}")]
+    NameConstraintViolation(String, String),
+    #[error("Certificate chain exceeds the path length constraint of:{0}, maximum:{1}")]
+    PathLengthExceeded(String, u32),
 }
 
+/// Whether the certificate is signed by its own key. A self-issued certificate whose signature doesn't verify
+/// against its own key, such as a key rollover certificate, is not self-signed.
 pub fn is_self_signed(cert: &X509Certificate) -> bool {
-    if cert.subject() == cert.issuer() {
-        if cert.verify_signature(None).is_ok() {
-            return true;
-        } else if cert.subject() == cert.issuer() {
-            panic!("pki looks self-signed, but signature verification failed");
-        }
-    }
-    false
+    cert.subject() == cert.issuer() && cert.verify_signature(None).is_ok()
 }
 
-pub fn validate_certificate_domain(cert: &X509Certificate, server_name: &ServerName) -> bool {
-    let subject = cert.subject().to_string();
-    let name = ServerName::try_from(subject).unwrap();
-    name == *server_name
+/// Checks that `issuer` is the issuer of `cert`, by name and by signature.
+pub fn is_issued_by(cert: &X509Certificate, issuer: &X509Certificate) -> bool {
+    cert.issuer() == issuer.subject() && cert.verify_signature(Some(issuer.public_key())).is_ok()
 }
 
-pub fn is_certificate_expired(x509: &X509Certificate) -> bool {
-    !x509.validity.is_valid()
+/// Hostname verification against the Subject Alternative Names, see [`matches_server_name`].
+pub fn validate_certificate_domain(
+    cert: &X509Certificate,
+    server_name: &ServerName,
+    allow_common_name_fallback: bool,
+) -> bool {
+    matches_server_name(cert, server_name, allow_common_name_fallback)
 }
 
-pub fn validate_signature(cert: &X509Certificate, private_key: &PrivateKeyDer) -> bool {
-    let (_, subject_public_key) = match private_key {
-        PrivateKeyDer::Pkcs1(pkcs1) => {
-            SubjectPublicKeyInfo::from_der(pkcs1.secret_pkcs1_der()).unwrap()
-        }
-        PrivateKeyDer::Sec1(sec1) => {
-            let public = EcPrivateKey::from_der(sec1.secret_sec1_der())
-                .unwrap()
-                .public_key
-                .unwrap();
-            SubjectPublicKeyInfo::from_der(public).unwrap()
-        }
-        PrivateKeyDer::Pkcs8(pkcs8) => {
-            let public = pkcs8::PrivateKeyInfo::from_der(pkcs8.secret_pkcs8_der())
-                .unwrap()
-                .public_key
-                .unwrap();
+fn parse_certificate<'a>(
+    der: &'a CertificateDer,
+) -> Result<X509Certificate<'a>, ValidateCertificateError> {
+    X509Certificate::from_der(der)
+        .map(|(_, certificate)| certificate)
+        .map_err(|err| ValidateCertificateError::InvalidCertificate(err.to_string()))
+}
 
-            SubjectPublicKeyInfo::from_der(public).unwrap()
-        }
-        _ => {
-            return false;
-        }
-    };
-    cert.verify_signature(Some(&subject_public_key)).is_ok()
+/// Whether the certificate is past its notAfter at `now`, see [`PkiValidator::now`].
+pub fn is_certificate_expired(x509: &X509Certificate, now: ASN1Time) -> bool {
+    validity_status(x509, now, Duration::ZERO) == ValidityStatus::Expired
+}
+
+/// ecdsa-with-SHA1, RFC 3279 2.2.3, missing from the OID registry.
+const OID_SIG_ECDSA_WITH_SHA1: Oid<'static> = oid!(1.2.840 .10045 .4 .1);
+
+pub fn uses_sha1_signature(cert: &X509Certificate) -> bool {
+    let algorithm = &cert.signature_algorithm.algorithm;
+    *algorithm == OID_PKCS1_SHA1WITHRSA || *algorithm == OID_SIG_ECDSA_WITH_SHA1
+}
+
+/// The modulus size in bits, `None` for non-RSA keys.
+pub fn rsa_key_size(cert: &X509Certificate) -> Option<usize> {
+    match cert.public_key().parsed() {
+        Ok(PublicKey::RSA(rsa)) => Some(rsa.key_size()),
+        _ => None,
+    }
+}
+
+/// Checks that the private key belongs to the certificate.
+pub fn validate_signature(cert: &X509Certificate, private_key: &PrivateKeyDer) -> bool {
+    match GenericPrivateKey::from_private_key_der(private_key) {
+        Ok(private_key) => private_key.matches_public_key(cert.public_key()),
+        Err(_) => false,
+    }
 }
 pub fn validate_certificate_chain(
-    verifier: &Verifier,
-    end: &X509Certificate,
-    intermediates: &Vec<X509Certificate>,
+    verifier: &dyn ServerCertVerifier,
+    end: &CertificateDer,
+    intermediates: &[CertificateDer],
     server_name: &ServerName,
+    now: pki_types::UnixTime,
 ) -> bool {
-    let end = CertificateDer::from(end.as_ref());
-    let intermediates: Vec<_> = intermediates
-        .into_iter()
-        .map(|cert| CertificateDer::from(cert.as_ref()))
-        .collect();
-    let now_time = pki_types::UnixTime::now();
     let ocsp_response = Vec::<u8>::new();
 
-    let verified_certs = verifier
-        .verify_server_cert(
-            &end,
-            &intermediates,
-            server_name,
-            ocsp_response.as_slice(),
-            now_time,
-        )
-        .unwrap();
-    dbg!(verified_certs);
-    true
+    match verifier.verify_server_cert(
+        end,
+        intermediates,
+        server_name,
+        ocsp_response.as_slice(),
+        now,
+    ) {
+        Ok(_) => true,
+        Err(err) => {
+            tracing::debug!("Certificate chain verification failed: {}", err);
+            false
+        }
+    }
 }
 
 pub struct PkiValidatorConfig {
@@ -104,16 +157,27 @@ pub struct PkiValidatorConfig {
     pub validate_expiration: bool,
     pub validate_domain: bool,
     pub verify_certificate_chain: bool,
+    pub allow_common_name_fallback: bool,
+    pub check_revocation: bool,
+    pub ocsp_failure_policy: OcspFailurePolicy,
+    pub require_certificate_transparency: bool,
+    /// Accepted difference between the clock and the validity periods, for hosts with a slightly skewed clock.
+    pub clock_skew_tolerance: Duration,
     pub server_name: ServerName<'static>,
 }
 
 impl PkiValidatorConfig {
-    fn new(config: &impl PkiValidatorConfiguration) -> Self {
+    pub fn new(config: &impl PkiValidatorConfiguration) -> Self {
         Self {
             allow_self_signed: config.get_allow_self_signed_certificate(),
             validate_expiration: config.get_validate_expiration(),
             validate_domain: config.get_validate_domain(),
             verify_certificate_chain: config.get_validate_certificate_chain(),
+            allow_common_name_fallback: config.get_allow_common_name_fallback(),
+            check_revocation: config.get_check_revocation(),
+            ocsp_failure_policy: config.get_ocsp_failure_policy(),
+            require_certificate_transparency: config.get_require_certificate_transparency(),
+            clock_skew_tolerance: config.get_clock_skew_tolerance(),
             server_name: ServerName::try_from(config.get_domain()).unwrap(),
         }
     }
@@ -121,77 +185,866 @@ impl PkiValidatorConfig {
 
 pub struct PkiValidator {
     config: PkiValidatorConfig,
-    cert_chain_verifier: Verifier,
+    cert_chain_verifier: Arc<dyn ServerCertVerifier>,
+    crls: Vec<CertificateRevocationListDer<'static>>,
+    crl_issuers: Vec<CertificateDer<'static>>,
+    fetched_crls: Option<watch::Receiver<Arc<Vec<CertificateRevocationListDer<'static>>>>>,
+    ct_logs: Vec<CtLog>,
+    sct_policy: SctPolicy,
+    expiry_warning: Duration,
+    clock: Arc<dyn Clock>,
+    ssh_ca_keys: Vec<ssh_key::PublicKey>,
 }
 impl PkiValidator {
     pub fn new(config: PkiValidatorConfig) -> Self {
         Self {
             config,
-            cert_chain_verifier: Default::default(),
+            cert_chain_verifier: Arc::new(Verifier::new()),
+            crls: Vec::new(),
+            crl_issuers: Vec::new(),
+            fetched_crls: None,
+            ct_logs: Vec::new(),
+            sct_policy: SctPolicy::default(),
+            expiry_warning: Duration::from_secs(30 * 24 * 60 * 60),
+            clock: Arc::new(SystemClock),
+            ssh_ca_keys: Vec::new(),
         }
     }
+
+    /// The clock validation runs against, the system clock by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn now(&self) -> ASN1Time {
+        to_asn1_time(self.clock.now())
+    }
+
+    /// Warn about certificates expiring within this period, see [`PkiValidator::validate_identity`].
+    pub fn with_expiry_warning(mut self, expiry_warning: Duration) -> Self {
+        self.expiry_warning = expiry_warning;
+        self
+    }
+
+    /// The logs trusted for Certificate Transparency, see [`parse_ct_logs_pem`](crate::validate::sct::parse_ct_logs_pem).
+    pub fn with_ct_logs(mut self, ct_logs: Vec<CtLog>) -> Self {
+        self.ct_logs = ct_logs;
+        self
+    }
+
+    /// Validate chains against the trust store instead of the platform verifier, e.g. the root of a private PKI.
+    pub fn with_trust_store(
+        mut self,
+        trust_store: &TrustStore,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, TrustStoreError> {
+        self.cert_chain_verifier = trust_store.verifier(provider)?;
+        Ok(self)
+    }
+
+    /// The CA keys trusted to sign OpenSSH certificates, see [`PkiValidator::verify_ssh_certificate`].
+    pub fn with_ssh_ca_keys(mut self, ssh_ca_keys: Vec<ssh_key::PublicKey>) -> Self {
+        self.ssh_ca_keys = ssh_ca_keys;
+        self
+    }
+
+    pub fn with_sct_policy(mut self, sct_policy: SctPolicy) -> Self {
+        self.sct_policy = sct_policy;
+        self
+    }
+
+    /// Also check revocation against the CRLs downloaded from distribution points, see [`CrlFetcher::subscribe`](crate::fetch::crl::CrlFetcher::subscribe).
+    pub fn with_fetched_crls(
+        mut self,
+        fetched_crls: watch::Receiver<Arc<Vec<CertificateRevocationListDer<'static>>>>,
+    ) -> Self {
+        self.fetched_crls = Some(fetched_crls);
+        self
+    }
+
+    /// Replaces the CRLs used for revocation checking with those of the parsed data, call this on every reload.
+    /// The CA certificates of the data verify the CRLs of chains shipped without their CA certificate.
+    pub fn update_crls(&mut self, pki_data: &ParsedPkiData) {
+        self.crls = pki_data
+            .crls
+            .iter()
+            .map(|crl| CertificateRevocationListDer::from(crl.as_ref().to_vec()))
+            .collect();
+        self.crl_issuers = pki_data
+            .x509
+            .iter()
+            .filter(|der| {
+                X509Certificate::from_der(der).is_ok_and(|(_, certificate)| certificate.is_ca())
+            })
+            .map(|der| CertificateDer::from(der.as_ref().to_vec()))
+            .collect();
+    }
 }
 
 impl PkiValidator {
-    fn verify_certificate<'a>(
+    /// Verifies the chain at `now`, or at the ends of the clock skew tolerance around it.
+    fn verify_chain(
         &self,
-        certificate: &X509Certificate,
-        intermediate: Vec<X509Certificate<'a>>,
+        end: &CertificateDer,
+        intermediates: &[CertificateDer],
+        now: ASN1Time,
+    ) -> bool {
+        let now = UNIX_EPOCH + Duration::from_secs(now.timestamp().max(0) as u64);
+        let tolerance = self.config.clock_skew_tolerance;
+        let mut times = vec![now];
+        if !tolerance.is_zero() {
+            times.push(now + tolerance);
+            times.extend(now.checked_sub(tolerance));
+        }
+        times.into_iter().any(|time| {
+            validate_certificate_chain(
+                self.cert_chain_verifier.as_ref(),
+                end,
+                intermediates,
+                &self.config.server_name,
+                to_unix_time(time),
+            )
+        })
+    }
+
+    /// Validates a certificate and its intermediates, stopping at the first failure.
+    /// See [`PkiValidator::validate_identity`] for a report of every check.
+    pub fn verify_certificate(
+        &self,
+        certificate_der: &CertificateDer,
+        intermediate_der: &[CertificateDer],
     ) -> Result<(), ValidateCertificateError> {
-        if self.config.allow_self_signed {
-            if !is_self_signed(certificate) {
-                return Err(ValidateCertificateError::CertificateSelfSigned);
-            }
+        let certificate = &parse_certificate(certificate_der)?;
+        if !self.config.allow_self_signed && is_self_signed(certificate) {
+            return Err(ValidateCertificateError::CertificateSelfSigned);
         }
-        if self.config.validate_domain {
-            if !validate_certificate_domain(certificate, &self.config.server_name) {
-                return Err(ValidateCertificateError::NonMatchingServerName(
-                    certificate.subject.to_string(),
-                    self.config.server_name.to_str().to_string(),
-                ));
-            }
+        if self.config.validate_domain
+            && !validate_certificate_domain(
+                certificate,
+                &self.config.server_name,
+                self.config.allow_common_name_fallback,
+            )
+        {
+            return Err(ValidateCertificateError::NonMatchingServerName(
+                certificate.subject.to_string(),
+                self.config.server_name.to_str().to_string(),
+            ));
         }
 
+        let now = self.now();
         if self.config.validate_expiration {
-            if is_certificate_expired(certificate) {
-                return Err(ValidateCertificateError::CertificateHasExpired);
+            match validity_status(certificate, now, self.config.clock_skew_tolerance) {
+                ValidityStatus::Valid => {}
+                ValidityStatus::NotYetValid => {
+                    return Err(ValidateCertificateError::CertificateNotYetValid)
+                }
+                ValidityStatus::Expired => {
+                    return Err(ValidateCertificateError::CertificateHasExpired)
+                }
             }
         }
 
         if self.config.verify_certificate_chain {
-            if !validate_certificate_chain(
-                &self.cert_chain_verifier,
-                certificate,
-                &intermediate,
-                &self.config.server_name,
-            ) {
+            if !self.verify_chain(certificate_der, intermediate_der, now) {
                 return Err(ValidateCertificateError::InvalidCertificateChain);
             }
+            let mut chain = vec![certificate.clone()];
+            for der in intermediate_der {
+                chain.push(parse_certificate(der)?);
+            }
+            verify_chain_constraints(&chain)?;
         }
         Ok(())
     }
 
-    fn verify_identity(&self, identity: &Identity) -> Result<(), ValidateCertificateError> {
+    /// Validates an identity, stopping at the first failure.
+    /// See [`PkiValidator::validate_identity`] for a report of every check.
+    pub fn verify_identity(&self, identity: &Identity) -> Result<(), ValidateCertificateError> {
         if !validate_signature(&identity.certificate, &identity.private_key) {
             return Err(ValidateCertificateError::InvalidCertificateSignature);
         }
+        let now = self.now();
+        if self.config.check_revocation {
+            self.verify_revocation(identity, now)?;
+        }
+        if self.config.require_certificate_transparency {
+            self.verify_certificate_transparency(identity, &[], now)?;
+        }
 
-        if !validate_certificate_domain(&identity.certificate, &identity.server_name) {
+        if self.config.validate_domain
+            && !validate_certificate_domain(
+                &identity.certificate,
+                &self.config.server_name,
+                self.config.allow_common_name_fallback,
+            )
+        {
             return Err(ValidateCertificateError::NonMatchingServerName(
                 identity.certificate.subject.to_string(),
-                identity.server_name.to_str().to_string(),
+                self.config.server_name.to_str().to_string(),
             ));
         }
-        let mut temp_intermediate = identity.intermediate.clone();
-        temp_intermediate.push(identity.ca_certificate.clone());
-        if !validate_certificate_chain(
-            &self.cert_chain_verifier,
+        if self.config.verify_certificate_chain {
+            let mut temp_intermediate = identity.intermediate_der.clone();
+            if let Some(ca_certificate) = &identity.ca_certificate_der {
+                temp_intermediate.push(ca_certificate.clone());
+            }
+            if !self.verify_chain(&identity.certificate_der, &temp_intermediate, now) {
+                return Err(ValidateCertificateError::InvalidCertificateChain);
+            }
+            verify_chain_constraints(&identity.get_certificate_chain())?;
+        }
+        Ok(())
+    }
+
+    /// Runs every check on the identity at [`PkiValidator::now`], also after a failure, and collects warnings about
+    /// its chain.
+    pub fn validate_identity(&self, identity: &Identity) -> ValidationReport {
+        let now = self.now();
+        let mut report = ValidationReport::default();
+        let chain = identity.get_certificate_chain();
+        let leaf = || Some(CertificateRef::new(0, &identity.certificate));
+        let disabled = || CheckStatus::Skipped("Disabled".to_string());
+
+        let status = if validate_signature(&identity.certificate, &identity.private_key) {
+            CheckStatus::Passed
+        } else {
+            CheckStatus::Failed("The private key does not belong to the certificate".to_string())
+        };
+        report.push(Check::KeyPair, status, leaf());
+
+        let status = if self.config.allow_self_signed {
+            CheckStatus::Skipped("Self-signed certificates are allowed".to_string())
+        } else if is_self_signed(&identity.certificate) {
+            CheckStatus::Failed("The certificate is self-signed".to_string())
+        } else {
+            CheckStatus::Passed
+        };
+        report.push(Check::SelfSigned, status, leaf());
+
+        let status = if !self.config.validate_domain {
+            disabled()
+        } else if validate_certificate_domain(
             &identity.certificate,
-            &temp_intermediate,
-            &identity.server_name,
+            &self.config.server_name,
+            self.config.allow_common_name_fallback,
         ) {
-            return return Err(ValidateCertificateError::InvalidCertificateChain);
+            CheckStatus::Passed
+        } else {
+            CheckStatus::Failed(format!(
+                "The certificate is not valid for {}",
+                self.config.server_name.to_str()
+            ))
+        };
+        report.push(Check::ServerName, status, leaf());
+
+        for (index, certificate) in chain.iter().enumerate() {
+            let validity = certificate.validity();
+            let status = if !self.config.validate_expiration {
+                disabled()
+            } else {
+                match validity_status(certificate, now, self.config.clock_skew_tolerance) {
+                    ValidityStatus::Valid => CheckStatus::Passed,
+                    ValidityStatus::NotYetValid => {
+                        CheckStatus::Failed(format!("Not valid before {}", validity.not_before))
+                    }
+                    ValidityStatus::Expired => {
+                        CheckStatus::Failed(format!("Expired on {}", validity.not_after))
+                    }
+                }
+            };
+            report.push(
+                Check::Validity,
+                status,
+                Some(CertificateRef::new(index, certificate)),
+            );
         }
+
+        let status = if !self.config.verify_certificate_chain {
+            disabled()
+        } else {
+            let mut intermediates = identity.intermediate_der.clone();
+            intermediates.extend(identity.ca_certificate_der.clone());
+            if self.verify_chain(&identity.certificate_der, &intermediates, now) {
+                CheckStatus::Passed
+            } else {
+                CheckStatus::Failed("The chain does not lead to a trusted root".to_string())
+            }
+        };
+        report.push(Check::Chain, status, None);
+
+        let status = if !self.config.verify_certificate_chain {
+            disabled()
+        } else {
+            match verify_chain_constraints(&chain) {
+                Ok(()) => CheckStatus::Passed,
+                Err(err) => CheckStatus::Failed(err.to_string()),
+            }
+        };
+        report.push(Check::ChainConstraints, status, None);
+
+        let (status, certificate) = if !self.config.check_revocation {
+            (disabled(), None)
+        } else {
+            match self.verify_revocation(identity, now) {
+                Ok(()) => (CheckStatus::Passed, None),
+                Err(ValidateCertificateError::CertificateRevoked(serial, reason)) => {
+                    let certificate = chain
+                        .iter()
+                        .enumerate()
+                        .find(|(_, certificate)| certificate.raw_serial_as_string() == serial)
+                        .map(|(index, certificate)| CertificateRef::new(index, certificate));
+                    (
+                        CheckStatus::Failed(format!("Revoked, reason: {:?}", reason)),
+                        certificate,
+                    )
+                }
+                Err(err) => (CheckStatus::Failed(err.to_string()), None),
+            }
+        };
+        report.push(Check::Revocation, status, certificate);
+
+        let status = if !self.config.require_certificate_transparency {
+            disabled()
+        } else {
+            match self.verify_certificate_transparency(identity, &[], now) {
+                Ok(()) => CheckStatus::Passed,
+                Err(err) => CheckStatus::Failed(err.to_string()),
+            }
+        };
+        report.push(Check::CertificateTransparency, status, leaf());
+
+        for (index, certificate) in chain.iter().enumerate() {
+            let not_after = certificate.validity().not_after;
+            let seconds_left = not_after.timestamp() - now.timestamp();
+            if seconds_left >= 0 && seconds_left <= self.expiry_warning.as_secs() as i64 {
+                report.warnings.push(ValidationWarning::ExpiresSoon {
+                    certificate: CertificateRef::new(index, certificate),
+                    days_left: seconds_left / (24 * 60 * 60),
+                });
+            }
+            // The signature of a root is not relied upon.
+            if uses_sha1_signature(certificate) && !is_issued_by(certificate, certificate) {
+                report.warnings.push(ValidationWarning::Sha1Signature {
+                    certificate: CertificateRef::new(index, certificate),
+                });
+            }
+            if let Some(bits) = rsa_key_size(certificate).filter(|bits| *bits < 2048) {
+                report.warnings.push(ValidationWarning::WeakRsaKey {
+                    certificate: CertificateRef::new(index, certificate),
+                    bits,
+                });
+            }
+        }
+        report
+    }
+
+    /// Validates every identity, see [`PkiValidator::validate_identity`].
+    pub fn validate_identities<'a>(
+        &self,
+        identities: &'a Identities<'a>,
+    ) -> Vec<(&'a Identity<'a>, ValidationReport)> {
+        identities
+            .iter()
+            .map(|identity| (identity, self.validate_identity(identity)))
+            .collect()
+    }
+
+    /// Checks the leaf and intermediate certificates of the identity against the CRLs of their issuers.
+    /// Certificates without a CRL of their issuer pass, those whose issuer has CRLs but is neither part of the chain
+    /// nor of the data passed to [`PkiValidator::update_crls`] fail.
+    pub fn verify_revocation(
+        &self,
+        identity: &Identity,
+        now: ASN1Time,
+    ) -> Result<(), ValidateCertificateError> {
+        let fetched_crls = self
+            .fetched_crls
+            .as_ref()
+            .map(|fetched_crls| fetched_crls.borrow().clone())
+            .unwrap_or_default();
+        let crls: Vec<CertificateRevocationList> = self
+            .crls
+            .iter()
+            .chain(fetched_crls.iter())
+            .filter_map(|crl| CertificateRevocationList::from_der(crl).ok())
+            .map(|(_, crl)| crl)
+            .collect();
+        let issuers: Vec<X509Certificate> = self
+            .crl_issuers
+            .iter()
+            .filter_map(|der| X509Certificate::from_der(der).ok())
+            .map(|(_, certificate)| certificate)
+            .collect();
+        let chain = identity.get_certificate_chain();
+        for (index, certificate) in chain.iter().enumerate() {
+            // Without the CA certificate, the issuer of the last certificate comes from the watched data.
+            let issuer = match chain.get(index + 1) {
+                Some(issuer) if issuer == certificate => continue,
+                Some(issuer) => Some(issuer),
+                None if is_issued_by(certificate, certificate) => continue,
+                None => issuers
+                    .iter()
+                    .find(|issuer| is_issued_by(certificate, issuer)),
+            };
+            let Some(issuer) = issuer else {
+                if crls.iter().any(|crl| crl.issuer() == certificate.issuer()) {
+                    return Err(CrlError::IssuerNotFound.into());
+                }
+                continue;
+            };
+            if let RevocationStatus::Revoked { reason, .. } =
+                check_revocation(certificate, issuer, &crls, now)?
+            {
+                return Err(ValidateCertificateError::CertificateRevoked(
+                    certificate.raw_serial_as_string(),
+                    reason,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the identity's certificate was logged to enough of the trusted CT logs,
+    /// counting the embedded SCTs and those delivered in the TLS extension.
+    pub fn verify_certificate_transparency(
+        &self,
+        identity: &Identity,
+        tls_scts: &[SignedCertificateTimestamp],
+        now: ASN1Time,
+    ) -> Result<(), ValidateCertificateError> {
+        let issuer = identity
+            .intermediate
+            .first()
+            .or(identity.ca_certificate.as_ref());
+        check_sct_policy(
+            &identity.certificate_der,
+            issuer,
+            tls_scts,
+            &self.ct_logs,
+            &self.sct_policy,
+            now,
+        )?;
         Ok(())
     }
+
+    /// Checks the OCSP response for the identity's certificate, e.g. the one stapled by a peer,
+    /// tolerating missing or unusable responses according to the configured policy.
+    pub fn verify_ocsp(
+        &self,
+        identity: &Identity,
+        response: Option<&[u8]>,
+        now: ASN1Time,
+    ) -> Result<(), ValidateCertificateError> {
+        let issuer = identity
+            .intermediate
+            .first()
+            .or(identity.ca_certificate.as_ref());
+        let result = check_ocsp_status(response, &identity.certificate, issuer, now);
+        match self.config.ocsp_failure_policy.apply(result) {
+            Ok(()) => Ok(()),
+            Err(OcspError::CertificateRevoked(reason)) => {
+                Err(ValidateCertificateError::CertificateRevoked(
+                    identity.certificate.raw_serial_as_string(),
+                    reason,
+                ))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Checks the validity window of an OpenSSH host or user certificate,
+    /// and that it's valid for the configured domain as principal. When the chain is verified,
+    /// the certificate must be signed by one of the [SSH CA keys](PkiValidator::with_ssh_ca_keys).
+    pub fn verify_ssh_certificate(
+        &self,
+        certificate: &ssh_key::Certificate,
+    ) -> Result<(), ValidateCertificateError> {
+        let now = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let tolerance = self.config.clock_skew_tolerance.as_secs();
+        if self.config.validate_expiration {
+            // The instant of the tolerance window closest to the start of the validity window.
+            let at = certificate
+                .valid_after()
+                .clamp(now.saturating_sub(tolerance), now.saturating_add(tolerance));
+            if !is_ssh_certificate_valid_at(certificate, at) {
+                return Err(if is_ssh_certificate_expired(certificate, at) {
+                    ValidateCertificateError::CertificateHasExpired
+                } else {
+                    ValidateCertificateError::CertificateNotYetValid
+                });
+            }
+        }
+        if self.config.verify_certificate_chain {
+            if !self
+                .ssh_ca_keys
+                .iter()
+                .any(|ca_key| ca_key.key_data() == certificate.signature_key())
+            {
+                return Err(ValidateCertificateError::UntrustedSshCertificateAuthority(
+                    certificate.key_id().to_string(),
+                    certificate
+                        .signature_key()
+                        .fingerprint(ssh_key::HashAlg::Sha256)
+                        .to_string(),
+                ));
+            }
+            certificate
+                .verify_signature()
+                .map_err(|_| ValidateCertificateError::InvalidCertificateSignature)?;
+        }
+        let principal = self.config.server_name.to_str();
+        if self.config.validate_domain && !ssh_certificate_has_principal(certificate, &principal) {
+            return Err(ValidateCertificateError::NonMatchingSshPrincipal(
+                certificate.key_id().to_string(),
+                principal.to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use rustls_pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+    use x509_parser::pem::Pem;
+
+    use crate::identity::Identities as _;
+    use crate::parser::parse::PkiParser;
+    use crate::validate::clock::FixedClock;
+    use crate::validate::crl::{CrlError, RevocationReason};
+    use crate::validate::ocsp::{OcspError, OcspFailurePolicy};
+    use crate::validate::report::{Check, CheckStatus, ValidationWarning};
+    use crate::validate::trust::TrustStore;
+    use crate::validate::validate::{
+        is_certificate_expired, is_self_signed, PkiValidator, PkiValidatorConfig,
+        ValidateCertificateError,
+    };
+    use crate::{Identity, ParsedPkiData};
+
+    fn config() -> PkiValidatorConfig {
+        PkiValidatorConfig {
+            allow_self_signed: false,
+            validate_expiration: true,
+            validate_domain: false,
+            verify_certificate_chain: false,
+            allow_common_name_fallback: false,
+            check_revocation: true,
+            ocsp_failure_policy: OcspFailurePolicy::default(),
+            require_certificate_transparency: false,
+            clock_skew_tolerance: Duration::ZERO,
+            server_name: ServerName::try_from("foobar.com").unwrap(),
+        }
+    }
+
+    fn validator(config: PkiValidatorConfig, timestamp: u64) -> PkiValidator {
+        PkiValidator::new(config).with_clock(Arc::new(FixedClock::new(
+            UNIX_EPOCH + Duration::from_secs(timestamp),
+        )))
+    }
+
+    fn identity<'a>(certificate: &'a Pem, ca_certificate: Option<&'a Pem>) -> Identity<'a> {
+        Identity {
+            names: vec![],
+            certificate: certificate.parse_x509().unwrap(),
+            certificate_der: CertificateDer::from(certificate.contents.as_slice()),
+            private_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(vec![])),
+            intermediate: vec![],
+            intermediate_der: vec![],
+            ca_certificate: ca_certificate.map(|pem| pem.parse_x509().unwrap()),
+            ca_certificate_der: ca_certificate
+                .map(|pem| CertificateDer::from(pem.contents.as_slice())),
+            certificate_provenance: None,
+            private_key_provenance: None,
+        }
+    }
+
+    fn pems(data: &[u8]) -> Vec<Pem> {
+        Pem::iter_from_buffer(data)
+            .map(|pem| pem.unwrap())
+            .collect()
+    }
+
+    fn pki_data(inputs: &[&[u8]]) -> ParsedPkiData<'static> {
+        let mut parsed_pki_data = ParsedPkiData::default();
+        let mut pki_parser = PkiParser::new();
+        for input in inputs {
+            pki_parser
+                .parse_pem(&mut parsed_pki_data, Cursor::new(input.to_vec()))
+                .unwrap();
+        }
+        parsed_pki_data
+    }
+
+    #[test]
+    fn test_verify_revocation() {
+        let ca = pems(include_bytes!("../../tests/data/revocation-ca.pem"));
+        let leaves = pems(include_bytes!("../../tests/data/revocation-leaves.pem"));
+        let crls: [&[u8]; 2] = [
+            include_bytes!("../../tests/data/revocation-base.crl"),
+            include_bytes!("../../tests/data/revocation-delta.crl"),
+        ];
+        // 2024-01-15
+        let mut validator = validator(config(), 1_705_276_800);
+        validator.update_crls(&pki_data(&crls));
+
+        assert!(matches!(
+            validator.verify_revocation(&identity(&leaves[0], Some(&ca[0])), validator.now()),
+            Err(ValidateCertificateError::CertificateRevoked(serial, Some(RevocationReason::KeyCompromise)))
+                if serial == leaves[0].parse_x509().unwrap().raw_serial_as_string()
+        ));
+        assert!(validator
+            .verify_revocation(&identity(&leaves[2], Some(&ca[0])), validator.now())
+            .is_ok());
+
+        // The chain ends below the CA, its certificate is missing from the data.
+        assert!(matches!(
+            validator.verify_revocation(&identity(&leaves[0], None), validator.now()),
+            Err(ValidateCertificateError::CrlError(CrlError::IssuerNotFound))
+        ));
+        // The CA certificate is part of the data.
+        validator.update_crls(&pki_data(&[
+            crls[0],
+            crls[1],
+            include_bytes!("../../tests/data/revocation-ca.pem"),
+        ]));
+        assert!(matches!(
+            validator.verify_revocation(&identity(&leaves[0], None), validator.now()),
+            Err(ValidateCertificateError::CertificateRevoked(
+                _,
+                Some(RevocationReason::KeyCompromise)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_verify_ocsp() {
+        let ca = pems(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let leaf_pem = pems(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let leaf = identity(&leaf_pem[0], Some(&ca[0]));
+        let revoked_leaf = pems(include_bytes!("../../tests/data/ocsp-revoked-leaf.pem"));
+        let revoked_leaf = identity(&revoked_leaf[0], Some(&ca[0]));
+        let good: &[u8] = include_bytes!("../../tests/data/ocsp-good.der");
+        let revoked: &[u8] = include_bytes!("../../tests/data/ocsp-revoked.der");
+        // 2026-10-20, the responses are valid 2026-10-19 02:07:02 to 2036-10-16 02:07:02.
+        let soft_fail = validator(config(), 1_792_454_400);
+        let now = soft_fail.now();
+
+        assert!(soft_fail.verify_ocsp(&leaf, Some(good), now).is_ok());
+        assert!(soft_fail.verify_ocsp(&leaf, None, now).is_ok());
+        // The response is for another certificate.
+        assert!(soft_fail
+            .verify_ocsp(&revoked_leaf, Some(good), now)
+            .is_ok());
+        assert!(matches!(
+            soft_fail.verify_ocsp(&revoked_leaf, Some(revoked), now),
+            Err(ValidateCertificateError::CertificateRevoked(
+                _,
+                Some(RevocationReason::KeyCompromise)
+            ))
+        ));
+
+        let hard_fail = validator(
+            PkiValidatorConfig {
+                ocsp_failure_policy: OcspFailurePolicy::HardFail,
+                ..config()
+            },
+            1_792_454_400,
+        );
+        assert!(hard_fail.verify_ocsp(&leaf, Some(good), now).is_ok());
+        assert!(matches!(
+            hard_fail.verify_ocsp(&leaf, None, now),
+            Err(ValidateCertificateError::OcspError(
+                OcspError::MissingOcspResponse
+            ))
+        ));
+        assert!(matches!(
+            hard_fail.verify_ocsp(&revoked_leaf, Some(good), now),
+            Err(ValidateCertificateError::OcspError(
+                OcspError::MissingCertificateStatus
+            ))
+        ));
+        assert!(matches!(
+            hard_fail.verify_ocsp(&identity(&leaf_pem[0], None), Some(good), now),
+            Err(ValidateCertificateError::OcspError(
+                OcspError::MissingIssuer
+            ))
+        ));
+        assert!(matches!(
+            hard_fail.verify_ocsp(&revoked_leaf, Some(revoked), now),
+            Err(ValidateCertificateError::CertificateRevoked(..))
+        ));
+    }
+
+    #[test]
+    fn test_chain_constraints_follow_chain_verification() {
+        // The common name of the leaf is outside the names permitted by the CA.
+        let ca_pem = pems(include_bytes!("../../tests/data/nc-cn-ca.pem"));
+        let leaf = pems(include_bytes!("../../tests/data/nc-cn-outside.pem"));
+        let leaf = identity(&leaf[0], Some(&ca_pem[0]));
+
+        let disabled = validator(config(), 1_792_454_400);
+        let report = disabled.validate_identity(&leaf);
+        assert_eq!(
+            report.status_of(Check::ChainConstraints),
+            Some(&CheckStatus::Skipped("Disabled".to_string()))
+        );
+
+        let enabled = validator(
+            PkiValidatorConfig {
+                verify_certificate_chain: true,
+                ..config()
+            },
+            1_792_454_400,
+        )
+        .with_trust_store(
+            &TrustStore::from_certificates([CertificateDer::from(ca_pem[0].contents.clone())])
+                .unwrap(),
+            Arc::new(rustls::crypto::ring::default_provider()),
+        )
+        .unwrap();
+        let report = enabled.validate_identity(&leaf);
+        assert!(matches!(
+            report.status_of(Check::ChainConstraints),
+            Some(CheckStatus::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn test_self_issued_certificate() {
+        // Subject and issuer match, but the certificate is signed by the previous key of the CA.
+        let rollover = pems(include_bytes!("../../tests/data/self-issued.pem"));
+        let ca = pems(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let self_signed = CertificateDer::from(ca[0].contents.as_slice());
+        assert!(!is_self_signed(&rollover[0].parse_x509().unwrap()));
+        assert!(is_self_signed(&ca[0].parse_x509().unwrap()));
+
+        let validator = validator(config(), 1_792_454_400);
+        let report = validator.validate_identity(&identity(&rollover[0], None));
+        assert_eq!(
+            report.status_of(Check::SelfSigned),
+            Some(&CheckStatus::Passed)
+        );
+        assert!(matches!(
+            validator.verify_certificate(&self_signed, &[]),
+            Err(ValidateCertificateError::CertificateSelfSigned)
+        ));
+        let allowing = PkiValidator::new(PkiValidatorConfig {
+            allow_self_signed: true,
+            validate_expiration: false,
+            ..config()
+        });
+        assert!(allowing.verify_certificate(&self_signed, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_identity_follows_clock() {
+        let ca = pems(include_bytes!("../../tests/data/ocsp-ca.pem"));
+        let leaf = pems(include_bytes!("../../tests/data/ocsp-leaf.pem"));
+        let leaf = identity(&leaf[0], Some(&ca[0]));
+        let day = Duration::from_secs(24 * 60 * 60);
+        let not_after = UNIX_EPOCH
+            + Duration::from_secs(leaf.certificate.validity().not_after.timestamp() as u64);
+
+        let clock = Arc::new(FixedClock::new(not_after - 10 * day));
+        let validator = PkiValidator::new(config())
+            .with_clock(clock.clone())
+            .with_expiry_warning(30 * day);
+        let report = validator.validate_identity(&leaf);
+        assert_eq!(
+            report.status_of(Check::Validity),
+            Some(&CheckStatus::Passed)
+        );
+        assert!(report.warnings.iter().any(|warning| matches!(
+            warning,
+            ValidationWarning::ExpiresSoon { certificate, days_left: 10 } if certificate.chain_index == 0
+        )));
+        assert!(!leaf.is_any_expired(validator.now()));
+
+        clock.advance(20 * day);
+        let report = validator.validate_identity(&leaf);
+        assert!(matches!(
+            report.status_of(Check::Validity),
+            Some(CheckStatus::Failed(_))
+        ));
+        assert!(report.warnings.is_empty());
+        assert!(is_certificate_expired(&leaf.certificate, validator.now()));
+        assert!(leaf.is_any_expired(validator.now()));
+    }
+
+    #[test]
+    fn test_verify_ssh_certificate() {
+        let certificate =
+            ssh_key::Certificate::from_openssh(include_str!("../../tests/data/ssh-host-cert.pub"))
+                .unwrap();
+        let ca_key =
+            ssh_key::PublicKey::from_openssh(include_str!("../../tests/data/ssh-ca.pub")).unwrap();
+        let host_key =
+            ssh_key::PrivateKey::from_openssh(include_str!("../../tests/data/ssh-host.key"))
+                .unwrap();
+        let config = || PkiValidatorConfig {
+            validate_domain: true,
+            verify_certificate_chain: true,
+            server_name: ServerName::try_from("host.foobar.com").unwrap(),
+            ..config()
+        };
+        // Valid from 2024-01-01 (1704067200) to 2050-01-01.
+        let verify = |config: PkiValidatorConfig, timestamp, ca_keys: &[&ssh_key::PublicKey]| {
+            validator(config, timestamp)
+                .with_ssh_ca_keys(ca_keys.iter().map(|key| (*key).clone()).collect())
+                .verify_ssh_certificate(&certificate)
+        };
+
+        assert!(verify(config(), 1_720_000_000, &[&ca_key]).is_ok());
+        assert!(matches!(
+            verify(config(), 1_704_067_100, &[&ca_key]),
+            Err(ValidateCertificateError::CertificateNotYetValid)
+        ));
+        let tolerant = PkiValidatorConfig {
+            clock_skew_tolerance: Duration::from_secs(300),
+            ..config()
+        };
+        assert!(verify(tolerant, 1_704_067_100, &[&ca_key]).is_ok());
+        assert!(matches!(
+            verify(config(), 2_524_608_000, &[&ca_key]),
+            Err(ValidateCertificateError::CertificateHasExpired)
+        ));
+
+        let other_host = PkiValidatorConfig {
+            server_name: ServerName::try_from("other.foobar.com").unwrap(),
+            ..config()
+        };
+        assert!(matches!(
+            verify(other_host, 1_720_000_000, &[&ca_key]),
+            Err(ValidateCertificateError::NonMatchingSshPrincipal(..))
+        ));
+
+        // Signed by ssh-ca.pub, not by the host key.
+        assert!(matches!(
+            verify(config(), 1_720_000_000, &[host_key.public_key()]),
+            Err(ValidateCertificateError::UntrustedSshCertificateAuthority(
+                ..
+            ))
+        ));
+        assert!(matches!(
+            verify(config(), 1_720_000_000, &[]),
+            Err(ValidateCertificateError::UntrustedSshCertificateAuthority(
+                ..
+            ))
+        ));
+        let unverified = PkiValidatorConfig {
+            verify_certificate_chain: false,
+            ..config()
+        };
+        assert!(verify(unverified, 1_720_000_000, &[]).is_ok());
+    }
 }
@@ -1 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
 
+use pki_watcher::configuration::FilePkiStoreConfiguration;
+use pki_watcher::parser::parse::UnknownItemPolicy;
+use pki_watcher::passphrase::StaticPassphraseProvider;
+use pki_watcher::provenance::{PkiItem, SourceVersion, StoreKind};
+use pki_watcher::store::file_store::{FileStore, PkiFileStoreRetrievers};
+use pki_watcher::validate::lint::{LintPolicy, LintRule, Linter, Severity};
+
+pub struct StoreConfiguration {
+    pub file_path: String,
+}
+
+impl FilePkiStoreConfiguration for StoreConfiguration {
+    fn get_file_path(&self) -> String {
+        self.file_path.clone()
+    }
+}
+
+#[tokio::test]
+async fn test_file_store_der() {
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/key.der").to_string(),
+    };
+    let mut store = FileStore::new(&config);
+    store.retrieve().await.unwrap();
+    let parsed_pki_data = store.get_parsed_pki_data();
+    assert_eq!(parsed_pki_data.lock().await.pkc1.len(), 1);
+}
+
+#[tokio::test]
+async fn test_file_store_encrypted_der() {
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pkcs8.der").to_string(),
+    };
+    let mut store = FileStore::new(&config)
+        .with_passphrase_provider(Arc::new(StaticPassphraseProvider::new("mypass")));
+    store.retrieve().await.unwrap();
+    let parsed_pki_data = store.get_parsed_pki_data();
+    assert_eq!(parsed_pki_data.lock().await.pkcs8.len(), 1);
+}
+
+#[tokio::test]
+async fn test_file_store_provenance() {
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/certs.pem").to_string(),
+    };
+    let mut store = FileStore::new(&config);
+    store.retrieve().await.unwrap();
+    let parsed_pki_data = store.get_parsed_pki_data();
+    let parsed_pki_data = parsed_pki_data.lock().await;
+    assert!(parsed_pki_data.x509.len() > 1);
+    let provenance = parsed_pki_data
+        .provenance_of(PkiItem::Certificate(1))
+        .unwrap();
+    let source = provenance.source.as_ref().unwrap();
+    assert_eq!(source.store, StoreKind::File);
+    assert_eq!(source.resource, config.file_path);
+    assert!(matches!(source.version, Some(SourceVersion::Modified(_))));
+    assert_eq!(provenance.block_index, Some(1));
+    assert!(provenance.offset.unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_file_store_pem_with_leading_text() {
+    // openssl x509 -text output, the PEM block follows the decoded certificate.
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/ca.crt").to_string(),
+    };
+    let mut store = FileStore::new(&config);
+    store.retrieve().await.unwrap();
+    let parsed_pki_data = store.get_parsed_pki_data();
+    assert_eq!(parsed_pki_data.lock().await.x509.len(), 1);
+}
+
+#[tokio::test]
+async fn test_file_store_unknown_item_policy() {
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/dhparams.pem").to_string(),
+    };
+    assert!(FileStore::new(&config).retrieve().await.is_err());
+    let mut store = FileStore::new(&config).with_unknown_item_policy(UnknownItemPolicy::Lenient);
+    store.retrieve().await.unwrap();
+    let parsed_pki_data = store.get_parsed_pki_data();
+    assert_eq!(parsed_pki_data.lock().await.unknown.len(), 1);
+}
+
+#[tokio::test]
+async fn test_file_store_rejects_lint_errors() {
+    // The leaf is valid for 10 years.
+    let config = StoreConfiguration {
+        file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/ocsp-leaf.pem").to_string(),
+    };
+    let policy = LintPolicy {
+        max_validity: Some(Duration::from_secs(90 * 24 * 60 * 60)),
+        ..LintPolicy::default()
+    }
+    .with_severity(LintRule::MaxValidity, Some(Severity::Error));
+    let mut store = FileStore::new(&config).with_linter(Arc::new(Linter::new(policy)));
+    assert!(store.retrieve().await.is_err());
+    let parsed_pki_data = store.get_parsed_pki_data();
+    assert!(parsed_pki_data.lock().await.x509.is_empty());
+}